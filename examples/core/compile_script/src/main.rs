@@ -1,7 +1,7 @@
 use frida::{DeviceManager, Frida, Message, ScriptHandler, ScriptOption, ScriptRuntime};
 use std::sync::LazyLock;
 
-static FRIDA: LazyLock<Frida> = LazyLock::new(|| unsafe { Frida::obtain() });
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
 
 const BYTECODE_PATH: &str = "compiled.bin";
 
@@ -41,7 +41,7 @@ fn main() {
     let mut load_opts = ScriptOption::new()
         .set_name("compile_script_example")
         .set_runtime(ScriptRuntime::QJS);
-    let mut script = session
+    let script = session
         .create_script_from_bytes(&loaded, &mut load_opts)
         .expect("create_script_from_bytes failed");
 
@@ -1,7 +1,7 @@
 use frida::{DeviceManager, Frida, ScriptHandler, ScriptOption, ScriptRuntime};
 use std::sync::LazyLock;
 
-static FRIDA: LazyLock<Frida> = LazyLock::new(|| unsafe { Frida::obtain() });
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
@@ -14,7 +14,7 @@ fn main() {
     let device_manager = DeviceManager::obtain(&FRIDA);
     let pid: u32 = args[1].parse().unwrap();
 
-    if let Some(device) = device_manager.enumerate_all_devices().first() {
+    if let Some(device) = device_manager.enumerate_all_devices().unwrap().first() {
         println!("[*] First device: {}", device.get_name());
 
         let session = device.attach(pid).unwrap();
@@ -25,7 +25,7 @@ fn main() {
             let mut script_option = ScriptOption::new()
                 .set_name("example")
                 .set_runtime(ScriptRuntime::QJS);
-            let mut script = session
+            let script = session
                 .create_script("console.log('Log test');", &mut script_option)
                 .unwrap();
 
@@ -1,5 +1,5 @@
 //! Demonstrates `Device::enumerate_processes_with_options(Scope::Full)` and
-//! `Process::get_parameters`.
+//! `Process::parameters`.
 //!
 //! Given a process name, prints every running match with its pid + ppid +
 //! path, and marks the "main" process (the one whose parent PID is *not*
@@ -17,7 +17,7 @@ use frida::{Frida, Scope};
 use std::collections::HashSet;
 use std::sync::LazyLock;
 
-static FRIDA: LazyLock<Frida> = LazyLock::new(|| unsafe { Frida::obtain() });
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
 
 fn main() {
     let target_name = match std::env::args().nth(1) {
@@ -39,7 +39,9 @@ fn main() {
     // Scope::Full asks frida-core to populate each Process's parameter
     // table (ppid / path / user / started). Plain enumerate_processes()
     // uses Scope::Minimal and returns an empty parameter map.
-    let processes = device.enumerate_processes_with_options(Scope::Full);
+    let processes = device
+        .enumerate_processes_with_options(Scope::Full)
+        .unwrap();
 
     let matches: Vec<_> = processes
         .iter()
@@ -54,13 +56,10 @@ fn main() {
     let same_name_pids: HashSet<u32> = matches.iter().map(|p| p.get_pid()).collect();
 
     for p in &matches {
-        let params = p.get_parameters();
+        let params = p.parameters();
         let pid = p.get_pid();
-        let ppid = params.get("ppid").and_then(|v| v.get_int()).unwrap_or(0) as u32;
-        let path = params
-            .get("path")
-            .and_then(|v| v.get_string())
-            .unwrap_or("?");
+        let ppid = params.ppid.unwrap_or(0);
+        let path = params.path.as_deref().unwrap_or("?");
         let marker = if same_name_pids.contains(&ppid) {
             "  helper"
         } else {
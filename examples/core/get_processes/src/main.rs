@@ -1,12 +1,12 @@
 use frida::Frida;
 use std::sync::LazyLock;
 
-static FRIDA: LazyLock<Frida> = LazyLock::new(|| unsafe { Frida::obtain() });
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
 
 fn main() {
     let device_manager = frida::DeviceManager::obtain(&FRIDA);
     let local_device = device_manager.get_local_device().unwrap();
-    let processes = local_device.enumerate_processes();
+    let processes = local_device.enumerate_processes().unwrap();
 
     for process in processes {
         println!("{} {:?}", process.get_name(), process.get_pid());
@@ -3,7 +3,7 @@
 use frida::Frida;
 use std::sync::LazyLock;
 
-static FRIDA: LazyLock<Frida> = LazyLock::new(|| unsafe { Frida::obtain() });
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
 
 fn main() {
     println!("Hello, world!");
@@ -1,7 +1,7 @@
 use frida::{Frida, Inject};
 use std::sync::LazyLock;
 
-static FRIDA: LazyLock<Frida> = LazyLock::new(|| unsafe { Frida::obtain() });
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
 
 fn main() {
     let device_manager = frida::DeviceManager::obtain(&FRIDA);
@@ -2,7 +2,7 @@ use frida::{Frida, Message};
 use std::sync::LazyLock;
 use std::{thread, time::Duration};
 
-static FRIDA: LazyLock<Frida> = LazyLock::new(|| unsafe { Frida::obtain() });
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
 
 fn main() {
     let device_manager = frida::DeviceManager::obtain(&FRIDA);
@@ -42,7 +42,7 @@ fn main() {
             };
         "#;
         let mut script_option = frida::ScriptOption::default();
-        let mut script = match session.create_script(script_source, &mut script_option) {
+        let script = match session.create_script(script_source, &mut script_option) {
             Ok(s) => s,
             Err(err) => {
                 println!("{}", err);
@@ -1,7 +1,7 @@
 use frida::DeviceType;
 
 fn main() {
-    let frida = unsafe { frida::Frida::obtain() };
+    let frida = frida::Frida::obtain();
     let device_manager = frida::DeviceManager::obtain(&frida);
 
     // get the first usb device (assuming there is one attached)
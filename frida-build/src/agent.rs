@@ -0,0 +1,361 @@
+use std::{
+    env,
+    error::Error,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
+
+/// Error returned by a custom compile or bytecode step.
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+type CompileFn = Box<dyn FnOnce(&AgentSources) -> Result<String, BoxError>>;
+type BytecodeFn = Box<dyn FnOnce(&str) -> Result<Vec<u8>, BoxError>>;
+
+/// Errors raised while bundling an agent.
+#[derive(Debug, thiserror::Error)]
+pub enum AgentError {
+    /// A required environment variable is missing.
+    #[error("environment variable {0} is not set")]
+    Env(&'static str),
+
+    /// The compiler command could not be started.
+    #[error("failed to run {program}: {source}")]
+    Spawn {
+        /// Program of the command.
+        program: String,
+        /// Underlying error.
+        source: io::Error,
+    },
+
+    /// The compiler command failed.
+    #[error("{program} failed with {status}")]
+    CommandFailed {
+        /// Program of the command.
+        program: String,
+        /// Exit status of the command.
+        status: ExitStatus,
+    },
+
+    /// The compile step given to [`Agent::compile_with`] failed.
+    #[error("failed to compile the agent: {0}")]
+    Compile(BoxError),
+
+    /// The bytecode step given to [`Agent::bytecode_with`] failed.
+    #[error("failed to compile the agent to bytecode: {0}")]
+    Bytecode(BoxError),
+
+    /// A filesystem operation failed.
+    #[error("{context} {}: {source}", path.display())]
+    Io {
+        /// Operation that failed.
+        context: &'static str,
+        /// Path the operation was applied to.
+        path: PathBuf,
+        /// Underlying error.
+        source: io::Error,
+    },
+}
+
+/// Location of the sources of an agent, given to the compile step.
+#[derive(Clone, Debug)]
+pub struct AgentSources {
+    /// Directory containing the agent's `package.json`.
+    pub project_root: PathBuf,
+    /// Entrypoint of the agent.
+    pub entrypoint: PathBuf,
+}
+
+/// Files written by [`Agent::build`].
+#[derive(Clone, Debug)]
+pub struct AgentBundle {
+    /// The JavaScript bundle, `<name>.js`.
+    pub bundle: PathBuf,
+    /// The bytecode, `<name>.qjs`, if [`Agent::bytecode_with`] was used.
+    pub bytecode: Option<PathBuf>,
+}
+
+enum Compiler {
+    FridaCompile,
+    Command {
+        program: OsString,
+        args: Vec<String>,
+    },
+    Custom(CompileFn),
+}
+
+/// Bundles a TypeScript or JavaScript agent from a build script.
+///
+/// By default the agent is bundled with `frida-compile`, taken from the project's
+/// `node_modules` if installed there. Another command can be set with
+/// [`command`](Self::command), or the frida-core compiler used through
+/// [`compile_with`](Self::compile_with).
+///
+/// The bundle is written to `OUT_DIR`, and cargo is told to rerun the build script when
+/// the agent's sources change.
+///
+/// # Example
+///
+/// In `build.rs`, with `frida` as a build dependency to use the frida-core compiler:
+///
+/// ```ignore
+/// use frida::{BuildOptions, Compiler, DeviceManager, Frida};
+///
+/// fn main() {
+///     let frida = Frida::obtain();
+///     let manager = DeviceManager::obtain(&frida);
+///     frida_build::Agent::new("agent/index.ts")
+///         .compile_with(move |sources| {
+///             let options = BuildOptions::new()
+///                 .project_root(&sources.project_root.to_string_lossy());
+///             let entrypoint = sources.entrypoint.to_string_lossy();
+///             Ok(Compiler::new(&manager).build(&entrypoint, &options)?)
+///         })
+///         .build()
+///         .unwrap();
+/// }
+/// ```
+///
+/// Then in the crate:
+///
+/// ```ignore
+/// const AGENT: &str = include_str!(concat!(env!("OUT_DIR"), "/index.js"));
+///
+/// let script = session.create_script(AGENT, &mut ScriptOption::default())?;
+/// ```
+pub struct Agent {
+    entrypoint: PathBuf,
+    project_root: Option<PathBuf>,
+    name: Option<String>,
+    out_dir: Option<PathBuf>,
+    compiler: Compiler,
+    bytecode: Option<BytecodeFn>,
+}
+
+impl Agent {
+    /// Creates a builder for the agent whose entrypoint is `entrypoint`.
+    ///
+    /// A relative path is resolved against `CARGO_MANIFEST_DIR`.
+    pub fn new(entrypoint: impl AsRef<Path>) -> Self {
+        Agent {
+            entrypoint: entrypoint.as_ref().to_path_buf(),
+            project_root: None,
+            name: None,
+            out_dir: None,
+            compiler: Compiler::FridaCompile,
+            bytecode: None,
+        }
+    }
+
+    /// Sets the directory containing the agent's `package.json`.
+    ///
+    /// Defaults to the closest parent of the entrypoint containing a `package.json`, or
+    /// the entrypoint's directory.
+    pub fn project_root(mut self, project_root: impl AsRef<Path>) -> Self {
+        self.project_root = Some(project_root.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the name of the written files, defaulting to the entrypoint's file stem.
+    pub fn output_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Sets the directory the files are written to, defaulting to `OUT_DIR`.
+    pub fn out_dir(mut self, out_dir: impl AsRef<Path>) -> Self {
+        self.out_dir = Some(out_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Bundles the agent with `program`, run from the project root.
+    ///
+    /// In `args`, `{entrypoint}` and `{output}` are replaced by the paths of the
+    /// entrypoint and of the bundle to write.
+    pub fn command<S: AsRef<str>>(
+        mut self,
+        program: impl Into<OsString>,
+        args: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.compiler = Compiler::Command {
+            program: program.into(),
+            args: args
+                .into_iter()
+                .map(|arg| arg.as_ref().to_string())
+                .collect(),
+        };
+        self
+    }
+
+    /// Bundles the agent with `compile`, which returns the bundle.
+    pub fn compile_with<F>(mut self, compile: F) -> Self
+    where
+        F: FnOnce(&AgentSources) -> Result<String, BoxError> + 'static,
+    {
+        self.compiler = Compiler::Custom(Box::new(compile));
+        self
+    }
+
+    /// Also compiles the bundle to bytecode with `compile`, e.g. with
+    /// `Session::compile_script` and the QJS runtime.
+    pub fn bytecode_with<F>(mut self, compile: F) -> Self
+    where
+        F: FnOnce(&str) -> Result<Vec<u8>, BoxError> + 'static,
+    {
+        self.bytecode = Some(Box::new(compile));
+        self
+    }
+
+    /// Bundles the agent, returning the paths of the written files.
+    pub fn build(self) -> Result<AgentBundle, AgentError> {
+        let entrypoint = if self.entrypoint.is_absolute() {
+            self.entrypoint
+        } else {
+            let manifest_dir =
+                env::var_os("CARGO_MANIFEST_DIR").ok_or(AgentError::Env("CARGO_MANIFEST_DIR"))?;
+            Path::new(&manifest_dir).join(&self.entrypoint)
+        };
+        let project_root = match self.project_root {
+            Some(root) => root,
+            None => default_project_root(&entrypoint),
+        };
+        let out_dir = match self.out_dir {
+            Some(dir) => dir,
+            None => PathBuf::from(env::var_os("OUT_DIR").ok_or(AgentError::Env("OUT_DIR"))?),
+        };
+        let name = match self.name {
+            Some(name) => name,
+            None => entrypoint
+                .file_stem()
+                .map_or("agent".into(), |stem| stem.to_string_lossy().into_owned()),
+        };
+
+        rerun_if_changed(&project_root, &out_dir);
+        println!("cargo:rerun-if-changed={}", entrypoint.display());
+
+        let sources = AgentSources {
+            project_root,
+            entrypoint,
+        };
+        let bundle_path = out_dir.join(format!("{name}.js"));
+        match self.compiler {
+            Compiler::FridaCompile => {
+                let program = frida_compile(&sources.project_root);
+                let args = ["{entrypoint}", "-o", "{output}"].map(String::from);
+                run_command(&program, &args, &sources, &bundle_path)?;
+            }
+            Compiler::Command { program, args } => {
+                run_command(&program, &args, &sources, &bundle_path)?;
+            }
+            Compiler::Custom(compile) => {
+                let bundle = compile(&sources).map_err(AgentError::Compile)?;
+                fs::write(&bundle_path, bundle)
+                    .map_err(io_error("failed to write", &bundle_path))?;
+            }
+        }
+
+        let bytecode = match self.bytecode {
+            Some(compile) => {
+                let bundle = fs::read_to_string(&bundle_path)
+                    .map_err(io_error("failed to read", &bundle_path))?;
+                let bytecode = compile(&bundle).map_err(AgentError::Bytecode)?;
+                let path = out_dir.join(format!("{name}.qjs"));
+                fs::write(&path, bytecode).map_err(io_error("failed to write", &path))?;
+                Some(path)
+            }
+            None => None,
+        };
+
+        Ok(AgentBundle {
+            bundle: bundle_path,
+            bytecode,
+        })
+    }
+}
+
+fn io_error(context: &'static str, path: &Path) -> impl FnOnce(io::Error) -> AgentError {
+    let path = path.to_path_buf();
+    move |source| AgentError::Io {
+        context,
+        path,
+        source,
+    }
+}
+
+fn default_project_root(entrypoint: &Path) -> PathBuf {
+    let dir = entrypoint.parent().unwrap_or(Path::new("."));
+    dir.ancestors()
+        .find(|ancestor| ancestor.join("package.json").is_file())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// Returns the `frida-compile` installed in the project, or the one in `PATH`.
+fn frida_compile(project_root: &Path) -> OsString {
+    let name = if cfg!(windows) {
+        "frida-compile.cmd"
+    } else {
+        "frida-compile"
+    };
+    let local = project_root.join("node_modules").join(".bin").join(name);
+    if local.is_file() {
+        local.into_os_string()
+    } else {
+        name.into()
+    }
+}
+
+fn run_command(
+    program: &OsString,
+    args: &[String],
+    sources: &AgentSources,
+    output: &Path,
+) -> Result<(), AgentError> {
+    let entrypoint = sources.entrypoint.to_string_lossy();
+    let output_arg = output.to_string_lossy();
+    let display = program.to_string_lossy().into_owned();
+
+    let status = Command::new(program)
+        .args(args.iter().map(|arg| {
+            arg.replace("{entrypoint}", &entrypoint)
+                .replace("{output}", &output_arg)
+        }))
+        .current_dir(&sources.project_root)
+        .status()
+        .map_err(|source| AgentError::Spawn {
+            program: display.clone(),
+            source,
+        })?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(AgentError::CommandFailed {
+            program: display,
+            status,
+        })
+    }
+}
+
+/// Emits `rerun-if-changed` for the agent's sources, skipping dependencies, hidden
+/// directories and the output directory. Directories are emitted too, so that adding or
+/// removing a file triggers a rebuild.
+fn rerun_if_changed(dir: &Path, out_dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    println!("cargo:rerun-if-changed={}", dir.display());
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            if name != "node_modules" && !name.starts_with('.') && !out_dir.starts_with(&path) {
+                rerun_if_changed(&path, out_dir);
+            }
+        } else {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+}
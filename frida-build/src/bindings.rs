@@ -0,0 +1,222 @@
+/*
+ * Copyright © 2021 Keegan Saunders
+ * Copyright © 2021 S Rubenstein
+ *
+ * Licence: wxWindows Library Licence, Version 3.1
+ */
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::{
+    collections::BTreeSet,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+use syn::{Expr, FnArg, ImplItem, Item, LitStr, Type, visit_mut::VisitMut};
+
+/// Turns the methods of the `library` struct that bindgen generates with
+/// `dynamic_library_name` into free functions calling the library returned by `accessor`,
+/// an expression such as `crate::dynamic::library()`.
+///
+/// The bindings of a library loaded at runtime then have the same API as when linking it.
+/// The shims are returned as source to append to the bindings.
+pub fn dynamic_shims(bindings: &str, library: &str, accessor: &str) -> syn::Result<String> {
+    let file = syn::parse_file(bindings)?;
+    let accessor: Expr = syn::parse_str(accessor)?;
+
+    let mut shims = TokenStream::new();
+    for item in file.items {
+        let Item::Impl(block) = item else {
+            continue;
+        };
+        let is_library = matches!(&*block.self_ty, Type::Path(ty) if ty.path.is_ident(library));
+        if block.trait_.is_some() || !is_library {
+            continue;
+        }
+
+        for item in block.items {
+            // `new` and `from_library` are constructors, the rest resolve a symbol.
+            let ImplItem::Fn(mut method) = item else {
+                continue;
+            };
+            if method.sig.receiver().is_none() {
+                continue;
+            }
+
+            method.sig.inputs = method
+                .sig
+                .inputs
+                .into_iter()
+                .filter(|arg| !matches!(arg, FnArg::Receiver(_)))
+                .collect();
+            let mut rewriter = ShimRewriter {
+                accessor: &accessor,
+                missing: format!("{} is missing from the loaded library", method.sig.ident),
+            };
+            rewriter.visit_block_mut(&mut method.block);
+
+            let (attrs, sig, block) = (&method.attrs, &method.sig, &method.block);
+            shims.extend(quote! {
+                #(#attrs)*
+                pub #sig #block
+            });
+        }
+    }
+    Ok(shims.to_string())
+}
+
+/// Points the body of a library method at the loaded library, and names the symbol in the
+/// panic raised when it is missing.
+struct ShimRewriter<'a> {
+    accessor: &'a Expr,
+    missing: String,
+}
+
+impl VisitMut for ShimRewriter<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Path(path) if path.path.is_ident("self") => *expr = self.accessor.clone(),
+            _ => syn::visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_lit_str_mut(&mut self, lit: &mut LitStr) {
+        if lit.value().starts_with("Expected ") {
+            *lit = LitStr::new(&self.missing, lit.span());
+        }
+    }
+}
+
+/// Lists the functions of a sys crate that the Rust sources under `src_dir` call, so that a
+/// library loaded at runtime can be checked to export all of them.
+///
+/// Calls are identifiers starting with one of `prefixes`, e.g. `gum_` and `g_`, followed by
+/// an argument list, excluding method calls and function definitions. Files and directories
+/// in `skip`, relative to `src_dir`, aren't scanned: they hold modules that are gated on
+/// features or architectures, whose functions are checked when called instead. On targets
+/// where Frida renames its copy of GLib, the `_frida_` prefix is added to `g_` functions.
+///
+/// Cargo is told to rerun the build script when the sources change.
+pub fn required_symbols(
+    src_dir: &Path,
+    prefixes: &[&str],
+    skip: &[&str],
+) -> io::Result<Vec<String>> {
+    let skip: Vec<PathBuf> = skip.iter().map(|path| src_dir.join(path)).collect();
+    let mut functions = BTreeSet::new();
+    scan_dir(src_dir, prefixes, &skip, &mut functions)?;
+
+    let os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let vendor = env::var("CARGO_CFG_TARGET_VENDOR").unwrap_or_default();
+    let renames_glib = os != "windows" && vendor != "apple";
+    let mut symbols: Vec<String> = functions
+        .into_iter()
+        .map(
+            |function| match function.starts_with("g_") && renames_glib {
+                true => format!("_frida_{function}"),
+                false => function,
+            },
+        )
+        .collect();
+    symbols.sort();
+    Ok(symbols)
+}
+
+fn scan_dir(
+    dir: &Path,
+    prefixes: &[&str],
+    skip: &[PathBuf],
+    functions: &mut BTreeSet<String>,
+) -> io::Result<()> {
+    println!("cargo:rerun-if-changed={}", dir.display());
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.path());
+    for entry in entries {
+        let path = entry.path();
+        if skip.contains(&path) {
+            continue;
+        }
+        if path.is_dir() {
+            scan_dir(&path, prefixes, skip, functions)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            println!("cargo:rerun-if-changed={}", path.display());
+            called_functions(&fs::read_to_string(&path)?, prefixes, functions);
+        }
+    }
+    Ok(())
+}
+
+/// Adds the functions called in `source` to `functions`, skipping comments and strings.
+fn called_functions(source: &str, prefixes: &[&str], functions: &mut BTreeSet<String>) {
+    let bytes = source.as_bytes();
+    let is_ident = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_';
+    let mut previous_ident = "";
+    let mut previous_byte = 0u8;
+    let mut at = 0;
+
+    while at < bytes.len() {
+        let byte = bytes[at];
+        if source[at..].starts_with("//") {
+            at = source[at..].find('\n').map_or(bytes.len(), |end| at + end);
+        } else if source[at..].starts_with("/*") {
+            at = source[at + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| at + end + 4);
+        } else if byte == b'"' {
+            at += 1;
+            while at < bytes.len() && bytes[at] != b'"' {
+                at += if bytes[at] == b'\\' { 2 } else { 1 };
+            }
+            at += 1;
+            previous_byte = b'"';
+        } else if byte == b'\'' {
+            // A character literal, or a lifetime.
+            at += match (bytes.get(at + 1), bytes.get(at + 2)) {
+                (Some(b'\\'), _) => source[at + 2..]
+                    .find('\'')
+                    .map_or(bytes.len(), |end| end + 3),
+                (_, Some(b'\'')) => 3,
+                _ => 1,
+            };
+        } else if is_ident(byte) && !byte.is_ascii_digit() {
+            let start = at;
+            while at < bytes.len() && is_ident(bytes[at]) {
+                at += 1;
+            }
+            let ident = &source[start..at];
+            let called = source[at..].trim_start().starts_with('(');
+            if called
+                && previous_byte != b'.'
+                && previous_ident != "fn"
+                && prefixes.iter().any(|prefix| ident.starts_with(prefix))
+            {
+                functions.insert(ident.to_string());
+            }
+            previous_ident = ident;
+            previous_byte = bytes[at - 1];
+        } else {
+            if !byte.is_ascii_whitespace() {
+                previous_byte = byte;
+                previous_ident = "";
+            }
+            at += 1;
+        }
+    }
+}
+
+/// Writes [`required_symbols`] to `required_symbols.rs` in `OUT_DIR`, as a `&[&str]`
+/// expression to `include!`.
+pub fn write_required_symbols(src_dir: &Path, prefixes: &[&str], skip: &[&str]) -> io::Result<()> {
+    let symbols = required_symbols(src_dir, prefixes, skip)?;
+    let out_dir = env::var_os("OUT_DIR")
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::other("OUT_DIR is not set"))?;
+    let list: String = symbols
+        .iter()
+        .map(|symbol| format!("    {symbol:?},\n"))
+        .collect();
+    fs::write(
+        out_dir.join("required_symbols.rs"),
+        format!("&[\n{list}]\n"),
+    )
+}
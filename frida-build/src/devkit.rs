@@ -0,0 +1,440 @@
+/*
+ * Copyright © 2021 Keegan Saunders
+ * Copyright © 2021 S Rubenstein
+ *
+ * Licence: wxWindows Library Licence, Version 3.1
+ */
+
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    process,
+};
+use tar::Archive;
+use xz::read::XzDecoder;
+
+/// Environment variables read by [`DevkitOptions::from_env`].
+///
+/// Being plain environment variables, they can also be set from the `[env]` table of a
+/// `.cargo/config.toml`, e.g. `FRIDA_CORE_DEVKIT = { value = "devkits", relative = true }`.
+const ENV_VARS: &[&str] = &[
+    "FRIDA_DEVKIT_DIR",
+    "FRIDA_DEVKIT_CACHE",
+    "FRIDA_DEVKIT_OFFLINE",
+    "FRIDA_DOWNLOAD_CDN",
+];
+
+const DEFAULT_CDN: &str = "https://github.com/frida/frida/releases/download";
+
+/// Errors raised while provisioning a devkit.
+#[derive(Debug, thiserror::Error)]
+pub enum DevkitError {
+    /// A required environment variable is missing or invalid.
+    #[error("environment variable {0} is not set or not valid unicode")]
+    Env(String),
+
+    /// A devkit path given explicitly does not exist.
+    #[error("devkit {} does not exist", .0.display())]
+    NotFound(PathBuf),
+
+    /// The devkit isn't available locally and downloads are disabled.
+    #[error(
+        "{name} is not in the devkit cache {} and FRIDA_DEVKIT_OFFLINE is set; point \
+         FRIDA_{kind}_DEVKIT or FRIDA_DEVKIT_DIR to a local copy",
+        cache.display()
+    )]
+    Offline {
+        /// Name of the devkit, e.g. `frida-core-devkit-17.2.0-linux-x86_64`.
+        name: String,
+        /// Upper-case kind of the devkit, as used in environment variable names.
+        kind: String,
+        /// Cache directory that was searched.
+        cache: PathBuf,
+    },
+
+    /// The devkit archive doesn't have the expected SHA-256 checksum.
+    #[error("checksum mismatch for {}: expected {expected}, got {actual}", path.display())]
+    ChecksumMismatch {
+        /// Path of the archive.
+        path: PathBuf,
+        /// Expected checksum, in lowercase hex.
+        expected: String,
+        /// Actual checksum, in lowercase hex.
+        actual: String,
+    },
+
+    /// A checksum was given for a devkit that is not an archive.
+    #[error("cannot verify the checksum of {}: not a .tar.xz archive", .0.display())]
+    ChecksumUnverifiable(PathBuf),
+
+    /// The devkit is for another Frida version.
+    #[error("{} is a Frida {found} devkit, but Frida {expected} is required", path.display())]
+    VersionMismatch {
+        /// Path of the devkit.
+        path: PathBuf,
+        /// Version required by the crate, i.e. its `FRIDA_VERSION`.
+        expected: String,
+        /// Version of the devkit, taken from its name.
+        found: String,
+    },
+
+    /// The devkit could not be downloaded.
+    #[error("failed to download {url}: {source}")]
+    Download {
+        /// URL of the devkit.
+        url: String,
+        /// Underlying error.
+        source: reqwest::Error,
+    },
+
+    /// A filesystem operation failed.
+    #[error("{context} {}: {source}", path.display())]
+    Io {
+        /// Operation that failed.
+        context: &'static str,
+        /// Path the operation was applied to.
+        path: PathBuf,
+        /// Underlying error.
+        source: io::Error,
+    },
+}
+
+fn io_error(context: &'static str, path: &Path) -> impl FnOnce(io::Error) -> DevkitError {
+    let path = path.to_path_buf();
+    move |source| DevkitError::Io {
+        context,
+        path,
+        source,
+    }
+}
+
+/// Where and how to find a Frida devkit.
+///
+/// Devkits are looked up in order:
+/// 1. [`local`](Self::local), an archive or extracted directory;
+/// 2. [`search_dir`](Self::search_dir), a directory containing archives or extracted
+///    directories named after the devkit, e.g. `frida-core-devkit-17.2.0-linux-x86_64`;
+/// 3. [`cache_dir`](Self::cache_dir), where archives found in `search_dir` or downloaded are
+///    extracted, shared by every target directory. A devkit cached from another archive than
+///    the one found in `local` or `search_dir` is extracted anew;
+/// 4. the release CDN, unless [`offline`](Self::offline) is set.
+#[derive(Clone, Debug)]
+pub struct DevkitOptions {
+    /// Devkit kind: `core`, `gum` or `gumjs`.
+    pub kind: String,
+    /// Required Frida version.
+    pub version: String,
+    /// Target OS, in Frida's naming (`linux`, `macos`, `windows`, ...).
+    pub os: String,
+    /// Target architecture, in Frida's naming (`x86_64`, `arm64`, `armhf`, `x86`, ...).
+    pub arch: String,
+    /// Devkit archive (`.tar.xz`) or extracted directory to use.
+    pub local: Option<PathBuf>,
+    /// Directory to look for the devkit in.
+    pub search_dir: Option<PathBuf>,
+    /// Directory where devkits are extracted.
+    pub cache_dir: PathBuf,
+    /// Expected SHA-256 checksum of the devkit archive, in hex.
+    pub sha256: Option<String>,
+    /// Whether to fail instead of downloading a missing devkit.
+    pub offline: bool,
+    /// Base URL the devkit is downloaded from.
+    pub cdn: String,
+}
+
+impl DevkitOptions {
+    /// Reads the options for the current build script target from the environment.
+    ///
+    /// `<KIND>` stands for the upper-case kind, e.g. `CORE`:
+    /// - `FRIDA_<KIND>_DEVKIT`: [`local`](Self::local);
+    /// - `FRIDA_DEVKIT_DIR`: [`search_dir`](Self::search_dir);
+    /// - `FRIDA_DEVKIT_CACHE`: [`cache_dir`](Self::cache_dir), defaulting to
+    ///   `$CARGO_HOME/frida-devkits`;
+    /// - `FRIDA_<KIND>_DEVKIT_SHA256`: [`sha256`](Self::sha256);
+    /// - `FRIDA_DEVKIT_OFFLINE`: [`offline`](Self::offline), if set to anything but `0`;
+    /// - `FRIDA_DOWNLOAD_CDN`: [`cdn`](Self::cdn).
+    pub fn from_env(kind: &str, version: &str) -> Result<Self, DevkitError> {
+        let var = |name: &str| env::var(name).map_err(|_| DevkitError::Env(name.to_string()));
+        let os = var("CARGO_CFG_TARGET_OS")?;
+        let arch = frida_arch(&os, &var("CARGO_CFG_TARGET_ARCH")?);
+        let upper = kind.to_uppercase();
+
+        let cache_dir = match non_empty_var("FRIDA_DEVKIT_CACHE") {
+            Some(dir) => PathBuf::from(dir),
+            None => cargo_home()?.join("frida-devkits"),
+        };
+
+        Ok(DevkitOptions {
+            kind: kind.to_string(),
+            version: version.to_string(),
+            os,
+            arch,
+            local: non_empty_var(&format!("FRIDA_{upper}_DEVKIT")).map(PathBuf::from),
+            search_dir: non_empty_var("FRIDA_DEVKIT_DIR").map(PathBuf::from),
+            cache_dir,
+            sha256: env::var(format!("FRIDA_{upper}_DEVKIT_SHA256")).ok(),
+            offline: env::var_os("FRIDA_DEVKIT_OFFLINE").is_some_and(|v| v != "0"),
+            cdn: env::var("FRIDA_DOWNLOAD_CDN").unwrap_or_else(|_| DEFAULT_CDN.to_string()),
+        })
+    }
+
+    /// Returns the name of the devkit, e.g. `frida-core-devkit-17.2.0-linux-x86_64`.
+    pub fn devkit_name(&self) -> String {
+        format!(
+            "frida-{}-devkit-{}-{}-{}",
+            self.kind, self.version, self.os, self.arch
+        )
+    }
+
+    /// Finds, verifies and if needed extracts the devkit, returning its directory.
+    pub fn provision(&self) -> Result<PathBuf, DevkitError> {
+        let name = self.devkit_name();
+
+        if let Some(local) = &self.local {
+            if !local.exists() {
+                return Err(DevkitError::NotFound(local.clone()));
+            }
+            self.check_version(local)?;
+            return self.use_existing(local);
+        }
+
+        if let Some(dir) = &self.search_dir {
+            let archive = dir.join(format!("{name}.tar.xz"));
+            if archive.is_file() {
+                return self.use_existing(&archive);
+            }
+            let extracted = dir.join(&name);
+            if extracted.is_dir() {
+                return self.use_existing(&extracted);
+            }
+        }
+
+        let cached = self.cache_dir.join(&name);
+        if cached.is_dir() {
+            self.check_cached(&cached)?;
+            return Ok(cached);
+        }
+
+        if self.offline {
+            return Err(DevkitError::Offline {
+                name,
+                kind: self.kind.to_uppercase(),
+                cache: self.cache_dir.clone(),
+            });
+        }
+
+        let archive = self.download()?;
+        let result = self
+            .verified_checksum(&archive)
+            .and_then(|actual| self.extract(&archive, &actual));
+        let _ = fs::remove_file(&archive);
+        result
+    }
+
+    fn use_existing(&self, path: &Path) -> Result<PathBuf, DevkitError> {
+        if path.is_dir() {
+            if self.sha256.is_some() {
+                return Err(DevkitError::ChecksumUnverifiable(path.to_path_buf()));
+            }
+            return Ok(path.to_path_buf());
+        }
+
+        // The cached copy is only reused if it was extracted from this very archive, so that
+        // a newly given archive replaces a stale one.
+        let actual = self.verified_checksum(path)?;
+        let cached = self.cache_dir.join(self.devkit_name());
+        let stamp = fs::read_to_string(cached.join(".sha256")).unwrap_or_default();
+        if cached.is_dir() && stamp.trim() == actual {
+            return Ok(cached);
+        }
+        self.extract(path, &actual)
+    }
+
+    /// Checks the version in the name of `path`, if it follows the devkit naming.
+    fn check_version(&self, path: &Path) -> Result<(), DevkitError> {
+        let file_name = path.file_name().and_then(OsStr::to_str).unwrap_or_default();
+        let prefix = format!("frida-{}-devkit-", self.kind);
+        let found = file_name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.split('-').next());
+
+        match found {
+            Some(found) if found != self.version => Err(DevkitError::VersionMismatch {
+                path: path.to_path_buf(),
+                expected: self.version.clone(),
+                found: found.to_string(),
+            }),
+            Some(_) => Ok(()),
+            None => {
+                println!(
+                    "cargo:warning=cannot tell the Frida version of {}, expecting {}",
+                    path.display(),
+                    self.version
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks the checksum recorded when a cached devkit was extracted.
+    fn check_cached(&self, cached: &Path) -> Result<(), DevkitError> {
+        let Some(expected) = &self.sha256 else {
+            return Ok(());
+        };
+        let stamp = cached.join(".sha256");
+        let actual = fs::read_to_string(&stamp).map_err(io_error("failed to read", &stamp))?;
+        verify_checksum(cached, expected, actual.trim())
+    }
+
+    fn download(&self) -> Result<PathBuf, DevkitError> {
+        let name = self.devkit_name();
+        let cdn = self.cdn.trim_end_matches('/');
+        let url = format!("{cdn}/{}/{name}.tar.xz", self.version);
+        println!(
+            "cargo:warning=Frida {} devkit not found, downloading from {url}...",
+            self.kind
+        );
+
+        fs::create_dir_all(&self.cache_dir)
+            .map_err(io_error("failed to create", &self.cache_dir))?;
+        let archive = self
+            .cache_dir
+            .join(format!("{name}.tar.xz.{}.part", process::id()));
+
+        let download_error = |source| DevkitError::Download {
+            url: url.clone(),
+            source,
+        };
+        let mut response = reqwest::blocking::get(&url)
+            .and_then(|r| r.error_for_status())
+            .map_err(download_error)?;
+        let mut out = File::create(&archive).map_err(io_error("failed to create", &archive))?;
+        if let Err(e) = io::copy(&mut response, &mut out) {
+            let _ = fs::remove_file(&archive);
+            return Err(io_error("failed to write", &archive)(e));
+        }
+
+        Ok(archive)
+    }
+
+    /// Returns the checksum of `archive`, once checked against the expected one.
+    fn verified_checksum(&self, archive: &Path) -> Result<String, DevkitError> {
+        let actual = sha256_file(archive)?;
+        if let Some(expected) = &self.sha256 {
+            verify_checksum(archive, expected, &actual)?;
+        }
+        Ok(actual)
+    }
+
+    /// Extracts `archive`, whose checksum is `actual`, into the cache, replacing any devkit
+    /// extracted from another archive, and returns the extracted directory.
+    fn extract(&self, archive: &Path, actual: &str) -> Result<PathBuf, DevkitError> {
+        let target = self.cache_dir.join(self.devkit_name());
+        // Extract next to the target and rename, so that concurrent builds sharing the
+        // cache never see a partially extracted devkit.
+        let staging = self
+            .cache_dir
+            .join(format!("{}.{}.tmp", self.devkit_name(), process::id()));
+        let _ = fs::remove_dir_all(&staging);
+        fs::create_dir_all(&staging).map_err(io_error("failed to create", &staging))?;
+
+        let unpacked = File::open(archive)
+            .map(|file| Archive::new(XzDecoder::new(BufReader::new(file))))
+            .and_then(|mut tar| tar.unpack(&staging))
+            .and_then(|()| fs::write(staging.join(".sha256"), actual));
+        if let Err(e) = unpacked {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(io_error("failed to extract", archive)(e));
+        }
+
+        let _ = fs::remove_dir_all(&target);
+        if let Err(e) = fs::rename(&staging, &target) {
+            let _ = fs::remove_dir_all(&staging);
+            // Another build may have extracted the same devkit in the meantime.
+            if !target.is_dir() {
+                return Err(io_error("failed to move the devkit to", &target)(e));
+            }
+        }
+
+        Ok(target)
+    }
+}
+
+fn verify_checksum(path: &Path, expected: &str, actual: &str) -> Result<(), DevkitError> {
+    if expected.trim().eq_ignore_ascii_case(actual) {
+        Ok(())
+    } else {
+        Err(DevkitError::ChecksumMismatch {
+            path: path.to_path_buf(),
+            expected: expected.trim().to_lowercase(),
+            actual: actual.to_string(),
+        })
+    }
+}
+
+fn sha256_file(path: &Path) -> Result<String, DevkitError> {
+    let mut hasher = Sha256::new();
+    File::open(path)
+        .and_then(|mut file| io::copy(&mut file, &mut hasher))
+        .map_err(io_error("failed to read", path))?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+fn frida_arch(os: &str, arch: &str) -> String {
+    match (os, arch) {
+        (_, "aarch64") => "arm64",
+        ("linux", "arm") => "armhf",
+        (_, "i686") => "x86",
+        _ => arch,
+    }
+    .to_string()
+}
+
+fn non_empty_var(name: &str) -> Option<OsString> {
+    env::var_os(name).filter(|value| !value.is_empty())
+}
+
+fn cargo_home() -> Result<PathBuf, DevkitError> {
+    if let Some(home) = env::var_os("CARGO_HOME") {
+        return Ok(PathBuf::from(home));
+    }
+    let home = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    env::var_os(home)
+        .map(|home| Path::new(&home).join(".cargo"))
+        .ok_or_else(|| DevkitError::Env("CARGO_HOME".to_string()))
+}
+
+/// Provisions the devkit as configured in the environment (see
+/// [`DevkitOptions::from_env`]) and links against it, returning its include directory.
+pub fn use_devkit(kind: &str, version: &str) -> Result<String, DevkitError> {
+    let upper = kind.to_uppercase();
+    for var in ENV_VARS {
+        println!("cargo:rerun-if-env-changed={var}");
+    }
+    println!("cargo:rerun-if-env-changed=FRIDA_{upper}_DEVKIT");
+    println!("cargo:rerun-if-env-changed=FRIDA_{upper}_DEVKIT_SHA256");
+
+    let devkit = DevkitOptions::from_env(kind, version)?.provision()?;
+
+    println!("cargo:rustc-link-search={}", devkit.display());
+    println!("cargo:rustc-link-lib=static=frida-{kind}");
+
+    Ok(devkit.to_string_lossy().to_string())
+}
+
+/// Like [`use_devkit`], but reports errors and exits the build script.
+#[must_use]
+pub fn download_and_use_devkit(kind: &str, version: &str) -> String {
+    use_devkit(kind, version).unwrap_or_else(|e| {
+        eprintln!("error: cannot provision the Frida {kind} devkit: {e}");
+        process::exit(1);
+    })
+}
@@ -5,85 +5,15 @@
  * Licence: wxWindows Library Licence, Version 3.1
  */
 
-use std::{
-    env,
-    fs::{File, remove_file},
-    io::{self, Error},
-    path::Path,
-};
-use tar::Archive;
-use xz::read::XzDecoder;
+//! Build script helpers to provision the Frida devkits and bundle agents.
 
-/// private function to retry download in case of error.
-fn download_and_use_devkit_internal(
-    kind: &str,
-    version: &str,
-    force_download: bool,
-) -> Result<String, Error> {
-    let mut target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
-    let os = env::var("CARGO_CFG_TARGET_OS").unwrap();
+mod agent;
+pub use agent::*;
 
-    let out_dir = env::var_os("OUT_DIR").unwrap();
-    let out_dir_path = Path::new(&out_dir);
+#[cfg(feature = "dynamic-bindings")]
+mod bindings;
+#[cfg(feature = "dynamic-bindings")]
+pub use bindings::*;
 
-    match (os.as_str(), target_arch.as_str()) {
-        (_, "aarch64") => {
-            target_arch = "arm64".to_string();
-        }
-        ("linux", "arm") => {
-            target_arch = "armhf".to_string();
-        }
-        (_, "i686") => {
-            target_arch = "x86".to_string();
-        }
-        _ => {}
-    }
-
-    let devkit_name = format!("frida-{kind}-devkit-{version}-{os}-{target_arch}",);
-
-    let devkit_path = out_dir_path.join(&devkit_name);
-    let devkit_tar = out_dir_path.join(format!("{devkit_name}.tar.xz"));
-
-    if force_download {
-        drop(remove_file(&devkit_tar));
-    }
-
-    if !devkit_path.is_dir() {
-        if !devkit_tar.is_file() {
-            let cdn = env::var("FRIDA_DOWNLOAD_CDN")
-                .unwrap_or("https://github.com/frida/frida/releases/download".to_owned());
-            let cdn = cdn.trim_end_matches("/");
-
-            let frida_url = format!("{cdn}/{version}/{devkit_name}.tar.xz",);
-
-            println!(
-                "cargo:warning=Frida {} devkit not found, downloading from {}...",
-                kind, frida_url,
-            );
-            // Download devkit
-            let mut resp =
-                reqwest::blocking::get(&frida_url).expect("devkit download request failed");
-            let mut out = File::create(&devkit_tar).expect("failed to create devkit tar file");
-            io::copy(&mut resp, &mut out).expect("failed to copy devkit tar content");
-        }
-        let tar_xz = File::open(&devkit_tar).expect("failed to open devkit tar.xz for extraction");
-        let tar = XzDecoder::new(tar_xz);
-        let mut archive = Archive::new(tar);
-        archive.unpack(out_dir_path)?;
-    }
-
-    println!("cargo:rustc-link-search={}", out_dir.to_string_lossy());
-    println!("cargo:rustc-link-lib=static=frida-{kind}");
-
-    Ok(out_dir.to_string_lossy().to_string())
-}
-
-#[must_use]
-pub fn download_and_use_devkit(kind: &str, version: &str) -> String {
-    download_and_use_devkit_internal(kind, version, false)
-        .or_else(|e| {
-            println!("cargo:warning=Failed to unpack devkit: {e}, retrying download...");
-            download_and_use_devkit_internal(kind, version, true)
-        })
-        .expect("cannot extract the devkit tar.gz")
-}
+mod devkit;
+pub use devkit::*;
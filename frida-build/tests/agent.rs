@@ -0,0 +1,85 @@
+//! Tests for bundling agents with custom compile steps.
+
+use frida_build::{Agent, AgentError};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Creates a scratch agent project, returning its root.
+fn agent_project(test: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let root = std::env::temp_dir().join(format!(
+        "frida-build-agent-{}-{test}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("out")).unwrap();
+    fs::write(root.join("package.json"), "{}").unwrap();
+    fs::write(root.join("src").join("index.ts"), "send('hello');\n").unwrap();
+    root
+}
+
+#[test]
+fn compile_step_output_is_written_with_bytecode() {
+    let root = agent_project("custom");
+
+    let bundle = Agent::new(root.join("src").join("index.ts"))
+        .out_dir(root.join("out"))
+        .compile_with(|sources| {
+            Ok(format!(
+                "// root: {}\n{}",
+                sources.project_root.display(),
+                fs::read_to_string(&sources.entrypoint)?
+            ))
+        })
+        .bytecode_with(|bundle| Ok(bundle.len().to_le_bytes().to_vec()))
+        .build()
+        .unwrap();
+
+    assert_eq!(bundle.bundle, root.join("out").join("index.js"));
+    let js = fs::read_to_string(&bundle.bundle).unwrap();
+    // The project root is found through the package.json.
+    assert_eq!(js, format!("// root: {}\nsend('hello');\n", root.display()));
+    let bytecode = fs::read(bundle.bytecode.unwrap()).unwrap();
+    assert_eq!(bytecode, js.len().to_le_bytes());
+}
+
+#[test]
+fn compile_step_errors_are_reported() {
+    let root = agent_project("error");
+
+    let err = Agent::new(root.join("src").join("index.ts"))
+        .out_dir(root.join("out"))
+        .compile_with(|_| Err("syntax error".into()))
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, AgentError::Compile(_)));
+    assert_eq!(err.to_string(), "failed to compile the agent: syntax error");
+}
+
+#[cfg(unix)]
+#[test]
+fn command_placeholders_are_substituted() {
+    let root = agent_project("command");
+
+    let bundle = Agent::new(root.join("src").join("index.ts"))
+        .out_dir(root.join("out"))
+        .output_name("agent")
+        .command("sh", ["-c", "cp \"$0\" \"$1\"", "{entrypoint}", "{output}"])
+        .build()
+        .unwrap();
+    assert_eq!(bundle.bundle, root.join("out").join("agent.js"));
+    assert_eq!(
+        fs::read_to_string(&bundle.bundle).unwrap(),
+        "send('hello');\n"
+    );
+
+    let err = Agent::new(root.join("src").join("index.ts"))
+        .out_dir(root.join("out"))
+        .command("sh", ["-c", "exit 3"])
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, AgentError::CommandFailed { .. }));
+}
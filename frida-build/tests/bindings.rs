@@ -0,0 +1,82 @@
+//! Tests for the helpers behind the sys crates' `dynamic-loading` feature.
+
+use frida_build::{dynamic_shims, required_symbols};
+use std::{env, fs};
+
+/// The shape of the library struct bindgen generates with `dynamic_library_name`.
+const BINDINGS: &str = r#"
+pub struct FridaCoreLibrary {
+    __library: ::libloading::Library,
+    pub frida_init: Result<unsafe extern "C" fn(), ::libloading::Error>,
+    pub frida_add: Result<unsafe extern "C" fn(self_: i32, b: *mut u8) -> i32, ::libloading::Error>,
+}
+impl FridaCoreLibrary {
+    pub unsafe fn new<P>(path: P) -> Result<Self, ::libloading::Error>
+    where
+        P: AsRef<::std::ffi::OsStr>,
+    {
+        let library = unsafe { ::libloading::Library::new(path) }?;
+        unsafe { Self::from_library(library) }
+    }
+    pub unsafe fn frida_init(&self) {
+        unsafe { (self.frida_init.as_ref().expect("Expected function, got error."))() }
+    }
+    pub unsafe fn frida_add(&self, self_: i32, b: *mut u8) -> i32 {
+        unsafe { (self.frida_add.as_ref().expect("Expected function, got error."))(self_, b) }
+    }
+}
+"#;
+
+#[test]
+fn library_methods_become_free_functions() {
+    let shims = dynamic_shims(BINDINGS, "FridaCoreLibrary", "crate::dynamic::library()").unwrap();
+    let shims = syn::parse_file(&shims).unwrap();
+
+    let names: Vec<String> = shims
+        .items
+        .iter()
+        .map(|item| match item {
+            syn::Item::Fn(function) => {
+                assert!(function.sig.receiver().is_none());
+                function.sig.ident.to_string()
+            }
+            _ => panic!("unexpected item"),
+        })
+        .collect();
+    assert_eq!(names, ["frida_init", "frida_add"]);
+
+    let source = quote::quote!(#shims).to_string();
+    assert!(source.contains("crate :: dynamic :: library () . frida_add"));
+    assert!(source.contains("\"frida_add is missing from the loaded library\""));
+    // Identifiers merely containing `self` are kept.
+    assert!(source.contains("(self_ , b)"));
+}
+
+#[test]
+fn called_functions_are_required() {
+    let src = tempfile::tempdir().unwrap();
+    fs::create_dir(src.path().join("gated")).unwrap();
+    fs::write(
+        src.path().join("lib.rs"),
+        r#"
+        // frida_commented_out();
+        fn frida_defined() -> char { '"' }
+        fn call<'a>(device: &'a Device) {
+            let text = "frida_quoted()";
+            device.frida_method();
+            unsafe { frida_sys::frida_device_get_name(device.ptr) };
+            unsafe { g_free (text) };
+        }
+        "#,
+    )
+    .unwrap();
+    fs::write(src.path().join("gated").join("mod.rs"), "frida_gated();").unwrap();
+
+    // As seen by a build script for Linux, where Frida renames its copy of GLib.
+    unsafe {
+        env::set_var("CARGO_CFG_TARGET_OS", "linux");
+        env::set_var("CARGO_CFG_TARGET_VENDOR", "unknown");
+    }
+    let symbols = required_symbols(src.path(), &["frida_", "g_"], &["gated"]).unwrap();
+    assert_eq!(symbols, ["_frida_g_free", "frida_device_get_name"]);
+}
@@ -0,0 +1,201 @@
+//! Tests for provisioning devkits from local archives and the cache.
+//!
+//! These build small fake devkit archives and never hit the network.
+
+use frida_build::{DevkitError, DevkitOptions};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::path::Path;
+use tar::{Builder, Header};
+use tempfile::TempDir;
+use xz::write::XzEncoder;
+
+const VERSION: &str = "17.2.0";
+const NAME: &str = "frida-core-devkit-17.2.0-linux-x86_64";
+
+/// Creates an empty scratch directory, removed when dropped.
+fn scratch_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("frida-build-")
+        .tempdir()
+        .unwrap()
+}
+
+/// Writes a fake devkit archive containing only a header, returning its SHA-256.
+fn write_archive(path: &Path) -> String {
+    write_archive_with(path, b"/* frida-core */\n")
+}
+
+/// Writes a fake devkit archive whose header holds `contents`, returning its SHA-256.
+fn write_archive_with(path: &Path, contents: &[u8]) -> String {
+    let mut builder = Builder::new(XzEncoder::new(File::create(path).unwrap(), 6));
+    let mut header = Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "frida-core.h", contents)
+        .unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    Sha256::digest(fs::read(path).unwrap())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn options(dir: &Path) -> DevkitOptions {
+    DevkitOptions {
+        kind: "core".to_string(),
+        version: VERSION.to_string(),
+        os: "linux".to_string(),
+        arch: "x86_64".to_string(),
+        local: None,
+        search_dir: None,
+        cache_dir: dir.join("cache"),
+        sha256: None,
+        offline: true,
+        cdn: "http://127.0.0.1:9".to_string(),
+    }
+}
+
+#[test]
+fn local_archive_is_verified_and_extracted_into_the_cache() {
+    let tmp = scratch_dir();
+    let dir = tmp.path();
+    let archive = dir.join(format!("{NAME}.tar.xz"));
+    let checksum = write_archive(&archive);
+
+    let options = DevkitOptions {
+        local: Some(archive.clone()),
+        sha256: Some(checksum.to_uppercase()),
+        ..options(dir)
+    };
+    let devkit = options.provision().unwrap();
+    assert_eq!(devkit, dir.join("cache").join(NAME));
+    assert!(devkit.join("frida-core.h").is_file());
+
+    // The cached copy is reused, and still checked against the expected checksum.
+    fs::remove_file(&archive).unwrap();
+    let options = DevkitOptions {
+        local: None,
+        ..options
+    };
+    assert_eq!(options.provision().unwrap(), devkit);
+    let wrong = DevkitOptions {
+        sha256: Some("00".repeat(32)),
+        ..options
+    };
+    assert!(matches!(
+        wrong.provision(),
+        Err(DevkitError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn new_local_archive_replaces_the_cached_devkit() {
+    let tmp = scratch_dir();
+    let dir = tmp.path();
+    let archive = dir.join(format!("{NAME}.tar.xz"));
+    write_archive_with(&archive, b"/* old */\n");
+
+    let options = DevkitOptions {
+        local: Some(archive.clone()),
+        ..options(dir)
+    };
+    let devkit = options.provision().unwrap();
+    assert_eq!(
+        fs::read(devkit.join("frida-core.h")).unwrap(),
+        b"/* old */\n"
+    );
+
+    // Without an expected checksum, the cache must still not shadow the new archive.
+    write_archive_with(&archive, b"/* new */\n");
+    assert_eq!(options.provision().unwrap(), devkit);
+    assert_eq!(
+        fs::read(devkit.join("frida-core.h")).unwrap(),
+        b"/* new */\n"
+    );
+}
+
+#[test]
+fn checksum_mismatch_is_reported_without_extracting() {
+    let tmp = scratch_dir();
+    let dir = tmp.path();
+    let archive = dir.join(format!("{NAME}.tar.xz"));
+    write_archive(&archive);
+
+    let options = DevkitOptions {
+        search_dir: Some(dir.to_path_buf()),
+        sha256: Some("ab".repeat(32)),
+        ..options(dir)
+    };
+    match options.provision() {
+        Err(DevkitError::ChecksumMismatch { expected, .. }) => {
+            assert_eq!(expected, "ab".repeat(32))
+        }
+        other => panic!("expected a checksum mismatch, got {other:?}"),
+    }
+    assert!(!dir.join("cache").join(NAME).exists());
+}
+
+#[test]
+fn extracted_directory_is_used_in_place() {
+    let tmp = scratch_dir();
+    let dir = tmp.path();
+    let devkit = dir.join(NAME);
+    fs::create_dir_all(&devkit).unwrap();
+
+    let options = DevkitOptions {
+        search_dir: Some(dir.to_path_buf()),
+        ..options(dir)
+    };
+    assert_eq!(options.provision().unwrap(), devkit);
+
+    let with_checksum = DevkitOptions {
+        sha256: Some("00".repeat(32)),
+        ..options
+    };
+    assert!(matches!(
+        with_checksum.provision(),
+        Err(DevkitError::ChecksumUnverifiable(_))
+    ));
+}
+
+#[test]
+fn version_mismatch_is_rejected() {
+    let tmp = scratch_dir();
+    let dir = tmp.path();
+    let devkit = dir.join("frida-core-devkit-16.0.0-linux-x86_64");
+    fs::create_dir_all(&devkit).unwrap();
+
+    let options = DevkitOptions {
+        local: Some(devkit),
+        ..options(dir)
+    };
+    match options.provision() {
+        Err(DevkitError::VersionMismatch {
+            expected, found, ..
+        }) => {
+            assert_eq!(expected, VERSION);
+            assert_eq!(found, "16.0.0");
+        }
+        other => panic!("expected a version mismatch, got {other:?}"),
+    }
+}
+
+#[test]
+fn missing_devkit_fails_when_offline() {
+    let tmp = scratch_dir();
+    let dir = tmp.path();
+
+    let err = options(dir).provision().unwrap_err();
+    assert!(matches!(err, DevkitError::Offline { .. }));
+    assert!(err.to_string().contains("FRIDA_CORE_DEVKIT"));
+
+    let missing = DevkitOptions {
+        local: Some(dir.join("nowhere.tar.xz")),
+        ..options(dir)
+    };
+    assert!(matches!(missing.provision(), Err(DevkitError::NotFound(_))));
+}
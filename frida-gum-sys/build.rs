@@ -4,10 +4,27 @@
  * Licence: wxWindows Library Licence, Version 3.1
  */
 
+#[cfg(feature = "bindgen")]
 extern crate bindgen;
 
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// The C helpers call into frida-gum, so they need it linked.
+#[cfg(all(
+    feature = "dynamic-loading",
+    any(
+        feature = "event-sink",
+        feature = "invocation-listener",
+        feature = "stalker-observer",
+        feature = "stalker-params"
+    )
+))]
+compile_error!(
+    "the `dynamic-loading` feature can't be combined with `event-sink`, `invocation-listener`, \
+     `stalker-observer` or `stalker-params`"
+);
 
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
@@ -15,9 +32,13 @@ fn main() {
     let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap();
     let target_vendor = env::var("CARGO_CFG_TARGET_VENDOR").unwrap();
 
+    let version = include_str!("FRIDA_VERSION").trim();
+
     let docs = std::env::var("DOCS_RS").is_ok();
-    // We always use frida-gumjs.h for docs to not have to ship two big header files in this repo.
-    let use_gum_js = cfg!(feature = "js") || (!cfg!(feature = "auto-download") && docs);
+    let dynamic = cfg!(feature = "dynamic-loading");
+    // We always use frida-gumjs.h for docs and when loading the library at runtime, to not
+    // have to ship two big header files in this repo.
+    let use_gum_js = cfg!(feature = "js") || dynamic || (!cfg!(feature = "auto-download") && docs);
     #[cfg(any(
         feature = "event-sink",
         feature = "invocation-listener",
@@ -57,17 +78,17 @@ fn main() {
         env::var("CARGO_MANIFEST_DIR").unwrap()
     );
 
-    #[cfg(feature = "auto-download")]
+    #[cfg(all(feature = "auto-download", not(feature = "dynamic-loading")))]
     let include_dir = {
         use frida_build::download_and_use_devkit;
         if cfg!(feature = "js") {
-            download_and_use_devkit("gumjs", include_str!("FRIDA_VERSION").trim())
+            download_and_use_devkit("gumjs", version)
         } else {
-            download_and_use_devkit("gum", include_str!("FRIDA_VERSION").trim())
+            download_and_use_devkit("gum", version)
         }
     };
 
-    #[cfg(not(feature = "auto-download"))]
+    #[cfg(not(any(feature = "auto-download", feature = "dynamic-loading")))]
     if cfg!(feature = "js") {
         println!("cargo:rustc-link-lib=frida-gumjs");
     } else {
@@ -78,50 +99,50 @@ fn main() {
         println!("cargo:rustc-link-lib=pthread");
     }
 
-    let bindings = bindgen::Builder::default()
-        .use_core()
-        .formatter(bindgen::Formatter::Prettyplease);
+    // When loading frida-gum at runtime nothing is linked, and the bindings come from the
+    // headers shipped with the crate.
+    #[cfg(feature = "dynamic-loading")]
+    let bindings_include_dir = Some("include".to_string());
 
-    #[cfg(feature = "auto-download")]
-    let bindings = bindings.clang_arg(format!("-I{include_dir}"));
+    #[cfg(all(feature = "auto-download", not(feature = "dynamic-loading")))]
+    let bindings_include_dir = Some(include_dir.clone());
 
-    #[cfg(not(feature = "auto-download"))]
-    let bindings = if docs {
-        bindings.clang_arg("-Iinclude")
-    } else {
-        bindings
-    };
+    #[cfg(not(any(feature = "auto-download", feature = "dynamic-loading")))]
+    let bindings_include_dir = docs.then(|| "include".to_string());
 
-    let bindings = if use_gum_js {
-        bindings
-            .clang_arg("-DUSE_GUM_JS=1")
-            .header_contents("gum.h", "#include \"frida-gumjs.h\"")
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("bindings.rs");
+    let pregenerated = pregenerated_bindings_path(use_gum_js, dynamic);
+    let update = env::var_os("FRIDA_UPDATE_BINDINGS").is_some();
+    println!("cargo:rerun-if-env-changed=FRIDA_UPDATE_BINDINGS");
+    let header = bindings_include_dir.as_ref().map(|include_dir| {
+        let header = if use_gum_js {
+            "frida-gumjs.h"
+        } else {
+            "frida-gum.h"
+        };
+        Path::new(include_dir).join(header)
+    });
+
+    if cfg!(feature = "pregenerated-bindings") && !update {
+        println!("cargo:rerun-if-changed={}", pregenerated.display());
+        match read_pregenerated_bindings(&pregenerated, version, header.as_deref()) {
+            Ok(bindings) => fs::write(&out_path, bindings).unwrap(),
+            Err(reason) if cfg!(feature = "bindgen") => {
+                println!("cargo:warning={reason}, generating them with bindgen");
+                generate_bindings(bindings_include_dir.as_deref(), use_gum_js, &out_path);
+            }
+            Err(reason) => panic!("{reason}; enable the `bindgen` feature to generate them"),
+        }
     } else {
-        bindings
-            .clang_arg("-DUSE_GUM_JS=0")
-            .header_contents("gum.h", "#include \"frida-gum.h\"")
-    };
-
-    let bindings = bindings
-        .header("event_sink.h")
-        .header("invocation_listener.h")
-        .header("probe_listener.h")
-        .header("stalker_observer.h")
-        .header("stalker_params.h")
-        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
-        .blocklist_type("GumChainedPtr64Rebase")
-        .blocklist_type("GumChainedPtrArm64eRebase")
-        .blocklist_type("_GumChainedPtr64Rebase")
-        .blocklist_type("_GumChainedPtrArm64eRebase")
-        .generate_comments(false)
-        .layout_tests(false)
-        .generate()
-        .unwrap();
-
-    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
-    bindings
-        .write_to_file(out_path.join("bindings.rs"))
-        .unwrap();
+        generate_bindings(bindings_include_dir.as_deref(), use_gum_js, &out_path);
+        if update {
+            let header = header.expect(
+                "FRIDA_UPDATE_BINDINGS needs to know the devkit headers, enable the \
+                 `auto-download` or `dynamic-loading` feature",
+            );
+            write_pregenerated_bindings(&out_path, &pregenerated, version, &header);
+        }
+    }
 
     #[cfg(feature = "event-sink")]
     {
@@ -253,3 +274,153 @@ fn main() {
         _ => {}
     }
 }
+
+/// Checked-in bindings for the current target, see the `pregenerated-bindings` feature.
+fn pregenerated_bindings_path(use_gum_js: bool, dynamic: bool) -> PathBuf {
+    let target = env::var("TARGET").unwrap();
+    let suffix = match (use_gum_js, dynamic) {
+        (_, true) => "-dynamic",
+        (true, false) => "-js",
+        (false, false) => "",
+    };
+    PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap())
+        .join("bindings")
+        .join(format!("{target}{suffix}.rs"))
+}
+
+/// Reads checked-in bindings, which start with a `// Frida <version>` line, followed by a
+/// `// <header> fnv1a64 <checksum>` line identifying the devkit header they were generated
+/// from.
+///
+/// When the header of the devkit in use is known, the bindings must have been generated from
+/// it: the name of a devkit only tells its version, not whether it was built with other
+/// options or patched.
+fn read_pregenerated_bindings(
+    path: &Path,
+    version: &str,
+    header: Option<&Path>,
+) -> Result<String, String> {
+    let bindings = fs::read_to_string(path).map_err(|_| {
+        format!(
+            "no pregenerated bindings at {}; generate them with the `bindgen` feature and \
+             FRIDA_UPDATE_BINDINGS=1",
+            path.display()
+        )
+    })?;
+    let mut lines = bindings.lines();
+    let found = lines
+        .next()
+        .and_then(|line| line.strip_prefix("// Frida "))
+        .unwrap_or("an unknown version");
+    if found != version {
+        return Err(format!(
+            "pregenerated bindings at {} are for Frida {found}, but FRIDA_VERSION is {version}",
+            path.display()
+        ));
+    }
+
+    let recorded = lines.next().and_then(|line| line.strip_prefix("// "));
+    match header {
+        Some(header) => {
+            let expected = header_stamp(header)?;
+            if recorded != Some(expected.as_str()) {
+                return Err(format!(
+                    "pregenerated bindings at {} were not generated from {}",
+                    path.display(),
+                    header.display()
+                ));
+            }
+        }
+        None => println!(
+            "cargo:warning=cannot check the pregenerated bindings at {} against the linked \
+             devkit, whose headers are unknown; they must be for Frida {version}",
+            path.display()
+        ),
+    }
+    Ok(bindings)
+}
+
+fn write_pregenerated_bindings(generated: &Path, path: &Path, version: &str, header: &Path) {
+    let bindings = fs::read_to_string(generated).unwrap();
+    let stamp = header_stamp(header).unwrap();
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, format!("// Frida {version}\n// {stamp}\n{bindings}")).unwrap();
+    println!("cargo:warning=updated {}", path.display());
+}
+
+/// Identifies a header by its name and FNV-1a checksum, which is stable across toolchains
+/// unlike `DefaultHasher`.
+fn header_stamp(header: &Path) -> Result<String, String> {
+    let contents =
+        fs::read(header).map_err(|e| format!("cannot read {}: {e}", header.display()))?;
+    let checksum = contents
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    let name = header.file_name().unwrap().to_string_lossy();
+    Ok(format!("{name} fnv1a64 {checksum:016x}"))
+}
+
+#[cfg(feature = "bindgen")]
+fn generate_bindings(include_dir: Option<&str>, use_gum_js: bool, out_path: &Path) {
+    let bindings = bindgen::Builder::default().formatter(bindgen::Formatter::Prettyplease);
+
+    // The library struct of dynamic bindings needs std, for `libloading`.
+    #[cfg(feature = "dynamic-loading")]
+    let bindings = bindings
+        .dynamic_library_name(LIBRARY)
+        .dynamic_link_require_all(false)
+        .wrap_unsafe_ops(true);
+    #[cfg(not(feature = "dynamic-loading"))]
+    let bindings = bindings.use_core();
+
+    let bindings = match include_dir {
+        Some(include_dir) => bindings.clang_arg(format!("-I{include_dir}")),
+        None => bindings,
+    };
+
+    let bindings = if use_gum_js {
+        bindings
+            .clang_arg("-DUSE_GUM_JS=1")
+            .header_contents("gum.h", "#include \"frida-gumjs.h\"")
+    } else {
+        bindings
+            .clang_arg("-DUSE_GUM_JS=0")
+            .header_contents("gum.h", "#include \"frida-gum.h\"")
+    };
+
+    let bindings = bindings
+        .header("event_sink.h")
+        .header("invocation_listener.h")
+        .header("probe_listener.h")
+        .header("stalker_observer.h")
+        .header("stalker_params.h")
+        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+        .blocklist_type("GumChainedPtr64Rebase")
+        .blocklist_type("GumChainedPtrArm64eRebase")
+        .blocklist_type("_GumChainedPtr64Rebase")
+        .blocklist_type("_GumChainedPtrArm64eRebase")
+        .generate_comments(false)
+        .layout_tests(false)
+        .generate()
+        .unwrap();
+
+    #[allow(unused_mut)]
+    let mut bindings = bindings.to_string();
+    #[cfg(feature = "dynamic-loading")]
+    bindings.push_str(
+        &frida_build::dynamic_shims(&bindings, LIBRARY, "crate::dynamic::library()")
+            .expect("failed to parse the bindings generated for the library"),
+    );
+    fs::write(out_path, bindings).unwrap();
+}
+
+#[cfg(not(feature = "bindgen"))]
+fn generate_bindings(_include_dir: Option<&str>, _use_gum_js: bool, _out_path: &Path) {
+    panic!("frida-gum-sys needs either the `bindgen` or the `pregenerated-bindings` feature");
+}
+
+/// Struct generated by bindgen holding the functions of the library loaded at runtime.
+#[cfg(all(feature = "bindgen", feature = "dynamic-loading"))]
+const LIBRARY: &str = "FridaGumLibrary";
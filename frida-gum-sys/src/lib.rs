@@ -3,7 +3,7 @@
  *
  * Licence: wxWindows Library Licence, Version 3.1
  */
-#![no_std]
+#![cfg_attr(not(feature = "dynamic-loading"), no_std)]
 #![allow(non_upper_case_globals)]
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
@@ -16,8 +16,17 @@ mod bindings {
 
 pub use bindings::*;
 
+#[cfg(feature = "dynamic-loading")]
+mod dynamic;
+#[cfg(feature = "dynamic-loading")]
+pub use dynamic::{LoadError, is_loaded, load};
+
 #[cfg(not(any(target_os = "windows", target_vendor = "apple",)))]
-pub use {_frida_g_object_ref as g_object_ref, _frida_g_object_unref as g_object_unref};
+pub use {
+    _frida_g_object_ref as g_object_ref, _frida_g_object_unref as g_object_unref,
+    _frida_g_signal_connect_data as g_signal_connect_data,
+    _frida_g_signal_handler_disconnect as g_signal_handler_disconnect,
+};
 
 /// A single disassembled CPU instruction.
 #[repr(transparent)]
@@ -0,0 +1,27 @@
+/*
+ * Copyright © 2020-2021 Keegan Saunders
+ *
+ * Licence: wxWindows Library Licence, Version 3.1
+ */
+
+fn main() {
+    // The frida-gum functions called by the core modules, checked by `Gum::load_library`.
+    // The modules gated on features or architectures are skipped, their functions are only
+    // checked when called.
+    #[cfg(feature = "dynamic-loading")]
+    frida_build::write_required_symbols(
+        std::path::Path::new("src"),
+        &["gum_", "g_"],
+        &[
+            "backtracer.rs",
+            "instruction_writer",
+            "instruction_writer.rs",
+            "interceptor/invocation_listener.rs",
+            "memory_access_monitor.rs",
+            "script",
+            "stalker",
+            "stalker.rs",
+        ],
+    )
+    .expect("failed to list the frida-gum functions used by the core modules");
+}
@@ -36,6 +36,15 @@ pub enum Error {
 
     /// Failed to read bytes
     FailedToReadBytes,
+
+    /// Export not found
+    ExportNotFound,
+
+    /// Module not found among the loaded modules
+    ModuleNotFound,
+
+    /// Import not found
+    ImportNotFound,
 }
 
 impl fmt::Display for Error {
@@ -51,6 +60,9 @@ impl fmt::Display for Error {
             Error::FailedToCreateScript => write!(fmt, "Failed to create script"),
             Error::LoadScriptNotStarted => write!(fmt, "Load script not started"),
             Error::FailedToReadBytes => write!(fmt, "Failed to read bytes"),
+            Error::ExportNotFound => write!(fmt, "Export not found"),
+            Error::ModuleNotFound => write!(fmt, "Module not found"),
+            Error::ImportNotFound => write!(fmt, "Import not found"),
         }
     }
 }
@@ -8,12 +8,29 @@
 //!
 use {
     crate::{Error, Gum, NativePointer, Result},
-    core::ptr,
+    core::{
+        marker::PhantomData,
+        mem,
+        ops::{Deref, DerefMut},
+        ptr,
+    },
     frida_gum_sys as gum_sys,
 };
 
-#[cfg(feature = "invocation-listener")]
 use core::ffi::c_void;
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+
+mod deferred;
+pub use deferred::*;
+
+mod hook;
+pub use hook::*;
+
+mod replacement;
+pub use replacement::*;
+
 #[cfg(feature = "invocation-listener")]
 mod invocation_listener;
 #[cfg(feature = "invocation-listener")]
@@ -67,23 +84,87 @@ impl Interceptor {
         f: NativePointer,
         listener: &mut I,
     ) -> Result<Listener> {
-        let listener = invocation_listener_transform(listener);
-        match unsafe {
-            gum_sys::gum_interceptor_attach(self.interceptor, f.0, listener, ptr::null())
-        } {
-            gum_sys::GumAttachReturn_GUM_ATTACH_OK => {
-                Ok(Listener(NativePointer(listener as *mut c_void)))
-            }
-            gum_sys::GumAttachReturn_GUM_ATTACH_WRONG_SIGNATURE => {
-                Err(Error::InterceptorBadSignature)
-            }
-            gum_sys::GumAttachReturn_GUM_ATTACH_ALREADY_ATTACHED => {
-                Err(Error::InterceptorAlreadyAttached)
-            }
-            gum_sys::GumAttachReturn_GUM_ATTACH_POLICY_VIOLATION => Err(Error::PolicyViolation),
-            gum_sys::GumAttachReturn_GUM_ATTACH_WRONG_TYPE => Err(Error::WrongType),
-            _ => Err(Error::InterceptorError),
-        }
+        self.attach_listener(f, invocation_listener_transform(listener))
+    }
+
+    /// Attach a listener to the beginning of a function address, detaching it when the
+    /// returned [`HookHandle`] is dropped. The listener is borrowed for as long as the hook
+    /// is in place.
+    ///
+    /// # Safety
+    ///
+    /// The provided address *must* point to the start of a function in a valid
+    /// memory region.
+    #[cfg(feature = "invocation-listener")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "invocation-listener")))]
+    pub fn attach_scoped<'a, I: InvocationListener>(
+        &mut self,
+        f: NativePointer,
+        listener: &'a mut I,
+    ) -> Result<HookHandle<'a>> {
+        let listener = self.attach(f, listener)?;
+        Ok(HookHandle::new(self, Hook::Listener(listener)))
+    }
+
+    /// Attach closures to the beginning of a function address.
+    ///
+    /// The value returned by `on_enter` is kept in the invocation's listener data and
+    /// handed to `on_leave` when the same invocation returns, which makes it suitable for
+    /// passing arguments to `on_leave`. It is leaked if the function never returns, e.g.
+    /// because it unwinds or exits the thread. The closures are dropped once the returned
+    /// [`Listener`] is dropped and detached.
+    ///
+    /// # Safety
+    ///
+    /// The provided address *must* point to the start of a function in a valid
+    /// memory region.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let listener = interceptor.attach_with(
+    ///     open,
+    ///     |context| context.arg(0),
+    ///     |context, path| println!("open({path:#x}) = {}", context.return_value()),
+    /// )?;
+    /// ```
+    #[cfg(feature = "invocation-listener")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "invocation-listener")))]
+    pub fn attach_with<D, E, L>(
+        &mut self,
+        f: NativePointer,
+        on_enter: E,
+        on_leave: L,
+    ) -> Result<Listener>
+    where
+        E: Fn(InvocationContext) -> D + Send + Sync + 'static,
+        L: Fn(InvocationContext, D) + Send + Sync + 'static,
+    {
+        self.attach_listener(f, call_listener_transform(on_enter, on_leave))
+    }
+
+    /// Attach closures to the beginning of a function address like
+    /// [`Interceptor::attach_with`], detaching them when the returned [`HookHandle`] is
+    /// dropped.
+    ///
+    /// # Safety
+    ///
+    /// The provided address *must* point to the start of a function in a valid
+    /// memory region.
+    #[cfg(feature = "invocation-listener")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "invocation-listener")))]
+    pub fn attach_with_scoped<D, E, L>(
+        &mut self,
+        f: NativePointer,
+        on_enter: E,
+        on_leave: L,
+    ) -> Result<HookHandle<'static>>
+    where
+        E: Fn(InvocationContext) -> D + Send + Sync + 'static,
+        L: Fn(InvocationContext, D) + Send + Sync + 'static,
+    {
+        let listener = self.attach_with(f, on_enter, on_leave)?;
+        Ok(HookHandle::new(self, Hook::Listener(listener)))
     }
 
     /// Attach a listener to an instruction address.
@@ -98,13 +179,27 @@ impl Interceptor {
         instr: NativePointer,
         listener: &mut I,
     ) -> Result<Listener> {
-        let listener = probe_listener_transform(listener);
+        self.attach_listener(instr, probe_listener_transform(listener))
+    }
+
+    #[cfg(feature = "invocation-listener")]
+    fn attach_listener(
+        &mut self,
+        address: NativePointer,
+        listener: *mut gum_sys::GumInvocationListener,
+    ) -> Result<Listener> {
+        // The listener is released on error, as it's only kept alive by the interceptor.
+        let listener = Listener(NativePointer(listener as *mut c_void));
         match unsafe {
-            gum_sys::gum_interceptor_attach(self.interceptor, instr.0, listener, ptr::null())
+            gum_sys::gum_interceptor_attach(
+                self.interceptor,
+                address.0,
+                listener.0.0 as *mut _,
+                ptr::null_mut(),
+                gum_sys::GumAttachFlags_GUM_ATTACH_FLAGS_NONE,
+            )
         } {
-            gum_sys::GumAttachReturn_GUM_ATTACH_OK => {
-                Ok(Listener(NativePointer(listener as *mut c_void)))
-            }
+            gum_sys::GumAttachReturn_GUM_ATTACH_OK => Ok(listener),
             gum_sys::GumAttachReturn_GUM_ATTACH_WRONG_SIGNATURE => {
                 Err(Error::InterceptorBadSignature)
             }
@@ -137,6 +232,9 @@ impl Interceptor {
     /// Replace a function with another function. The new function should have the same signature
     /// as the old one.
     ///
+    /// `replacement_data` is available to the replacement through
+    /// `InvocationContext::replacement_data`, from `Interceptor::current_invocation`.
+    ///
     /// # Safety
     ///
     /// Assumes that the provided function and replacement addresses are valid and point to the
@@ -145,29 +243,97 @@ impl Interceptor {
         &mut self,
         function: NativePointer,
         replacement: NativePointer,
-        _replacement_data: NativePointer,
+        replacement_data: NativePointer,
     ) -> Result<NativePointer> {
         let mut original_function = NativePointer(ptr::null_mut());
-        unsafe {
-            match gum_sys::gum_interceptor_replace(
+        replace_result(unsafe {
+            gum_sys::gum_interceptor_replace(
                 self.interceptor,
                 function.0,
                 replacement.0,
+                replacement_data.0,
                 ptr::addr_of_mut!(original_function.0),
-                ptr::null(),
-            ) {
-                gum_sys::GumReplaceReturn_GUM_REPLACE_OK => Ok(original_function),
-                gum_sys::GumReplaceReturn_GUM_REPLACE_WRONG_SIGNATURE => {
-                    Err(Error::InterceptorBadSignature)
-                }
-                gum_sys::GumReplaceReturn_GUM_REPLACE_ALREADY_REPLACED => {
-                    Err(Error::InterceptorAlreadyReplaced)
-                }
-                gum_sys::GumReplaceReturn_GUM_REPLACE_POLICY_VIOLATION => {
-                    Err(Error::PolicyViolation)
-                }
-                gum_sys::GumReplaceReturn_GUM_REPLACE_WRONG_TYPE => Err(Error::WrongType),
-                _ => Err(Error::InterceptorError),
+            )
+        })?;
+        Ok(original_function)
+    }
+
+    /// Replace a function with another function like [`Interceptor::replace`], reverting the
+    /// replacement when the returned [`HookHandle`] is dropped. The original implementation
+    /// is given by [`HookHandle::original`].
+    ///
+    /// # Safety
+    ///
+    /// Assumes that the provided function and replacement addresses are valid and point to the
+    /// start of valid functions
+    pub fn replace_scoped(
+        &mut self,
+        function: NativePointer,
+        replacement: NativePointer,
+        replacement_data: NativePointer,
+    ) -> Result<HookHandle<'static>> {
+        let original = self.replace(function, replacement, replacement_data)?;
+        Ok(HookHandle::new(
+            self,
+            Hook::Replacement { function, original },
+        ))
+    }
+
+    /// Replace a function with a closure, which is given the original implementation of the
+    /// function and the arguments of each call. `S` is the signature of the function, such as
+    /// `unsafe extern "C" fn(*const c_char, c_int) -> c_int`.
+    ///
+    /// The closure may run on several threads at once, so it can't block other callers, and
+    /// calls made from it to the replaced function go to the original implementation.
+    ///
+    /// # Safety
+    ///
+    /// Assumes that the provided function address is valid, and points to the start of a
+    /// function with the signature `S`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// type Open = unsafe extern "C" fn(*const c_char, c_int) -> c_int;
+    ///
+    /// let opened = AtomicUsize::new(0);
+    /// let replacement = interceptor.replace_with::<Open, _>(open, move |original, (path, flags)| {
+    ///     opened.fetch_add(1, Ordering::Relaxed);
+    ///     unsafe { original(path, flags) }
+    /// })?;
+    /// ```
+    pub fn replace_with<S, F>(
+        &mut self,
+        function: NativePointer,
+        replacement: F,
+    ) -> Result<Replacement<S>>
+    where
+        S: NativeFunction,
+        F: Fn(S, S::Args) -> S::Output + Send + Sync + 'static,
+    {
+        let data = Box::into_raw(Box::new(ReplacementData {
+            original: ptr::null_mut(),
+            closure: replacement,
+        }));
+        let result = replace_result(unsafe {
+            gum_sys::gum_interceptor_replace(
+                self.interceptor,
+                function.0,
+                S::replacement::<F>().0,
+                data as *mut c_void,
+                ptr::addr_of_mut!((*data).original),
+            )
+        });
+        match result {
+            Ok(()) => Ok(Replacement::new(
+                function,
+                unsafe { S::from_ptr(NativePointer((*data).original)) },
+                data as *mut c_void,
+                drop_replacement_data::<F>,
+            )),
+            Err(err) => {
+                unsafe { drop_replacement_data::<F>(data as *mut c_void) };
+                Err(err)
             }
         }
     }
@@ -189,28 +355,35 @@ impl Interceptor {
         replacement: NativePointer,
     ) -> Result<NativePointer> {
         let mut original_function = NativePointer(ptr::null_mut());
-        unsafe {
-            match gum_sys::gum_interceptor_replace_fast(
+        replace_result(unsafe {
+            gum_sys::gum_interceptor_replace_fast(
                 self.interceptor,
                 function.0,
                 replacement.0,
                 ptr::addr_of_mut!(original_function.0),
-                ptr::null(),
-            ) {
-                gum_sys::GumReplaceReturn_GUM_REPLACE_OK => Ok(original_function),
-                gum_sys::GumReplaceReturn_GUM_REPLACE_WRONG_SIGNATURE => {
-                    Err(Error::InterceptorBadSignature)
-                }
-                gum_sys::GumReplaceReturn_GUM_REPLACE_ALREADY_REPLACED => {
-                    Err(Error::InterceptorAlreadyReplaced)
-                }
-                gum_sys::GumReplaceReturn_GUM_REPLACE_POLICY_VIOLATION => {
-                    Err(Error::PolicyViolation)
-                }
-                gum_sys::GumReplaceReturn_GUM_REPLACE_WRONG_TYPE => Err(Error::WrongType),
-                _ => Err(Error::InterceptorError),
-            }
-        }
+            )
+        })?;
+        Ok(original_function)
+    }
+
+    /// Replace a function with another function like [`Interceptor::replace_fast`], reverting
+    /// the replacement when the returned [`HookHandle`] is dropped. The original
+    /// implementation is given by [`HookHandle::original`].
+    ///
+    /// # Safety
+    ///
+    /// Assumes that the provided function and replacement addresses are valid and point to the
+    /// start of valid functions
+    pub fn replace_fast_scoped(
+        &mut self,
+        function: NativePointer,
+        replacement: NativePointer,
+    ) -> Result<HookHandle<'static>> {
+        let original = self.replace_fast(function, replacement)?;
+        Ok(HookHandle::new(
+            self,
+            Hook::Replacement { function, original },
+        ))
     }
 
     /// Reverts a function replacement for the given function, such that the implementation is the
@@ -252,6 +425,54 @@ impl Interceptor {
     pub fn end_transaction(&mut self) {
         unsafe { gum_sys::gum_interceptor_end_transaction(self.interceptor) };
     }
+
+    /// Begin an [`Interceptor`] transaction, which is ended when the returned [`Transaction`]
+    /// is dropped, including on panic. Hooks are placed through the transaction.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut transaction = interceptor.transaction();
+    /// transaction.replace(open, open_detour, NativePointer(ptr::null_mut()))?;
+    /// transaction.replace(close, close_detour, NativePointer(ptr::null_mut()))?;
+    /// ```
+    pub fn transaction(&mut self) -> Transaction<'_> {
+        self.begin_transaction();
+        Transaction { interceptor: self }
+    }
+
+    /// Ignore the hooks on the current thread until the returned guard is dropped, such as
+    /// while logging from a hook. Guards may be nested.
+    pub fn ignore_current_thread(&self) -> IgnoreCurrentThreadGuard {
+        unsafe { gum_sys::gum_interceptor_ignore_current_thread(self.interceptor) };
+        IgnoreCurrentThreadGuard {
+            interceptor: self.clone(),
+        }
+    }
+
+    /// Ignore the hooks on every thread but the current one until the returned guard is
+    /// dropped.
+    pub fn ignore_other_threads(&self) -> IgnoreOtherThreadsGuard {
+        unsafe { gum_sys::gum_interceptor_ignore_other_threads(self.interceptor) };
+        IgnoreOtherThreadsGuard {
+            interceptor: self.clone(),
+        }
+    }
+}
+
+fn replace_result(result: gum_sys::GumReplaceReturn) -> Result<()> {
+    match result {
+        gum_sys::GumReplaceReturn_GUM_REPLACE_OK => Ok(()),
+        gum_sys::GumReplaceReturn_GUM_REPLACE_WRONG_SIGNATURE => {
+            Err(Error::InterceptorBadSignature)
+        }
+        gum_sys::GumReplaceReturn_GUM_REPLACE_ALREADY_REPLACED => {
+            Err(Error::InterceptorAlreadyReplaced)
+        }
+        gum_sys::GumReplaceReturn_GUM_REPLACE_POLICY_VIOLATION => Err(Error::PolicyViolation),
+        gum_sys::GumReplaceReturn_GUM_REPLACE_WRONG_TYPE => Err(Error::WrongType),
+        _ => Err(Error::InterceptorError),
+    }
 }
 
 /// An instance of a listener attached to an instruction or function.
@@ -271,3 +492,151 @@ impl Clone for Listener {
         Self(NativePointer(unsafe { frida_gum_sys::g_object_ref(*ptr) }))
     }
 }
+
+enum Hook {
+    #[cfg(feature = "invocation-listener")]
+    Listener(Listener),
+    Replacement {
+        function: NativePointer,
+        original: NativePointer,
+    },
+}
+
+/// A hook placed with an [`Interceptor`], which is removed when the handle is dropped.
+///
+/// Unlike a [`Listener`], whose hook stays in place until [`Interceptor::detach`] is called,
+/// the hook follows the scope of the handle. It is returned by the `*_scoped` methods of the
+/// interceptor, such as [`Interceptor::replace_scoped`], and the lifetime `'a` is that of
+/// the listener borrowed by [`Interceptor::attach_scoped`]. Use [`HookHandle::leak`] for
+/// permanent hooks.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut listener = OpenListener;
+/// let hook = interceptor.attach_scoped(open, &mut listener)?;
+/// // `open` is hooked until `hook` goes out of scope, and `listener` can't be dropped before.
+/// ```
+pub struct HookHandle<'a> {
+    interceptor: Interceptor,
+    hook: Hook,
+    listener: PhantomData<&'a mut ()>,
+}
+
+impl HookHandle<'_> {
+    fn new(interceptor: &Interceptor, hook: Hook) -> Self {
+        Self {
+            interceptor: interceptor.clone(),
+            hook,
+            listener: PhantomData,
+        }
+    }
+
+    /// The original implementation of the replaced function, for a replacement placed with
+    /// [`Interceptor::replace_scoped`] or [`Interceptor::replace_fast_scoped`].
+    pub fn original(&self) -> Option<NativePointer> {
+        match &self.hook {
+            Hook::Replacement { original, .. } if !original.is_null() => Some(*original),
+            _ => None,
+        }
+    }
+}
+
+impl HookHandle<'static> {
+    /// Leave the hook in place for the rest of the process.
+    ///
+    /// Only hooks that don't borrow a listener can be leaked.
+    pub fn leak(self) {
+        mem::forget(self);
+    }
+
+    /// Detach the listener, returned by [`Interceptor::attach_with`], when the handle is
+    /// dropped.
+    ///
+    /// For a listener attached with [`Interceptor::attach`], use
+    /// [`Interceptor::attach_scoped`] instead, which also borrows the listener for as long as
+    /// it is attached.
+    #[cfg(feature = "invocation-listener")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "invocation-listener")))]
+    pub fn from_listener(interceptor: &Interceptor, listener: Listener) -> Self {
+        Self::new(interceptor, Hook::Listener(listener))
+    }
+
+    /// Revert the replacement of `function`, placed with [`Interceptor::replace`] or
+    /// [`Interceptor::replace_fast`], when the handle is dropped.
+    ///
+    /// # Safety
+    ///
+    /// `function` must have been replaced, and must not be reverted by other means while
+    /// the handle is alive: the replacement of whatever function is at that address once the
+    /// handle is dropped would be reverted instead.
+    pub unsafe fn from_replacement(interceptor: &Interceptor, function: NativePointer) -> Self {
+        Self::new(
+            interceptor,
+            Hook::Replacement {
+                function,
+                original: NativePointer(ptr::null_mut()),
+            },
+        )
+    }
+}
+
+impl Drop for HookHandle<'_> {
+    fn drop(&mut self) {
+        match &self.hook {
+            #[cfg(feature = "invocation-listener")]
+            Hook::Listener(listener) => self.interceptor.detach(listener.clone()),
+            Hook::Replacement { function, .. } => self.interceptor.revert(*function),
+        }
+    }
+}
+
+/// An [`Interceptor`] transaction, ended when dropped. Created with
+/// [`Interceptor::transaction`].
+pub struct Transaction<'a> {
+    interceptor: &'a mut Interceptor,
+}
+
+impl Deref for Transaction<'_> {
+    type Target = Interceptor;
+
+    fn deref(&self) -> &Interceptor {
+        self.interceptor
+    }
+}
+
+impl DerefMut for Transaction<'_> {
+    fn deref_mut(&mut self) -> &mut Interceptor {
+        self.interceptor
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        self.interceptor.end_transaction();
+    }
+}
+
+/// Hooks are ignored on the current thread while this guard is alive. Created with
+/// [`Interceptor::ignore_current_thread`].
+pub struct IgnoreCurrentThreadGuard {
+    interceptor: Interceptor,
+}
+
+impl Drop for IgnoreCurrentThreadGuard {
+    fn drop(&mut self) {
+        unsafe { gum_sys::gum_interceptor_unignore_current_thread(self.interceptor.interceptor) };
+    }
+}
+
+/// Hooks are ignored on every thread but the current one while this guard is alive. Created
+/// with [`Interceptor::ignore_other_threads`].
+pub struct IgnoreOtherThreadsGuard {
+    interceptor: Interceptor,
+}
+
+impl Drop for IgnoreOtherThreadsGuard {
+    fn drop(&mut self) {
+        unsafe { gum_sys::gum_interceptor_unignore_other_threads(self.interceptor.interceptor) };
+    }
+}
@@ -0,0 +1,124 @@
+/*
+ * Copyright © 2020-2021 Keegan Saunders
+ *
+ * Licence: wxWindows Library Licence, Version 3.1
+ */
+
+use {
+    super::{HookHandle, Interceptor},
+    crate::{
+        Error, Module, ModuleEvent, ModuleRegistry, ModuleSubscription, NativePointer, Result,
+    },
+};
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, string::String, string::ToString, sync::Arc};
+
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+type InstallFn =
+    Box<dyn FnMut(&mut Interceptor, NativePointer) -> Result<HookHandle<'static>> + Send>;
+
+struct DeferredState {
+    module_name: String,
+    symbol: String,
+    interceptor: Interceptor,
+    install: InstallFn,
+    handle: Option<HookHandle<'static>>,
+    error: Option<Error>,
+}
+
+// Gum's interceptor is thread-safe, and the installer is `Send`.
+unsafe impl Send for DeferredState {}
+
+impl DeferredState {
+    fn matches(&self, module: &Module) -> bool {
+        module.name() == self.module_name || module.path() == self.module_name
+    }
+
+    fn on_event(&mut self, event: ModuleEvent) {
+        match event {
+            ModuleEvent::Added(module) if self.handle.is_none() && self.matches(&module) => {
+                let result = module
+                    .find_export_by_name(&self.symbol)
+                    .ok_or(Error::ExportNotFound)
+                    .and_then(|function| (self.install)(&mut self.interceptor, function));
+                match result {
+                    Ok(handle) => {
+                        self.handle = Some(handle);
+                        self.error = None;
+                    }
+                    Err(err) => self.error = Some(err),
+                }
+            }
+            ModuleEvent::Removed(module) if self.matches(&module) => {
+                self.handle = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A hook placed on an export of a module as soon as the module is loaded, and removed when
+/// the module is unloaded. Created with [`Interceptor::hook_on_load`].
+///
+/// Dropping it removes the hook and stops waiting for the module.
+pub struct DeferredHook {
+    // Disconnected before the state, and thus the hook, is dropped.
+    _subscription: ModuleSubscription,
+    state: Arc<spin::Mutex<DeferredState>>,
+}
+
+impl DeferredHook {
+    /// Whether the module is loaded and the hook is placed.
+    pub fn is_installed(&self) -> bool {
+        self.state.lock().handle.is_some()
+    }
+
+    /// The error raised by the last attempt to place the hook, if it failed, such as
+    /// [`Error::ExportNotFound`] when the module lacks the export.
+    pub fn error(&self) -> Option<Error> {
+        self.state.lock().error.clone()
+    }
+}
+
+impl Interceptor {
+    /// Hook the export `symbol` of the module `module_name`, given by name or path, once the
+    /// module is loaded, or right away if it already is.
+    ///
+    /// `install` places the hook on the address of the export and returns a [`HookHandle`],
+    /// which is dropped to remove the hook when the module is unloaded. It runs each time the
+    /// module is loaded, with the loader's lock possibly held. A module it loads or unloads
+    /// itself is handled once it returns, and it must not use the returned [`DeferredHook`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let hook = interceptor.hook_on_load("libplugin.so", "plugin_init", |interceptor, function| {
+    ///     interceptor.attach_with_scoped(function, |_| (), |_, ()| println!("plugin_init"))
+    /// });
+    /// ```
+    pub fn hook_on_load<F>(&self, module_name: &str, symbol: &str, install: F) -> DeferredHook
+    where
+        F: FnMut(&mut Interceptor, NativePointer) -> Result<HookHandle<'static>> + Send + 'static,
+    {
+        let state = Arc::new(spin::Mutex::new(DeferredState {
+            module_name: module_name.to_string(),
+            symbol: symbol.to_string(),
+            interceptor: self.clone(),
+            install: Box::new(install),
+            handle: None,
+            error: None,
+        }));
+
+        let handler_state = state.clone();
+        let subscription = ModuleRegistry::obtain(&self._gum)
+            .subscribe(move |event: ModuleEvent| handler_state.lock().on_event(event));
+
+        DeferredHook {
+            _subscription: subscription,
+            state,
+        }
+    }
+}
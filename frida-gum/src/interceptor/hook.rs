@@ -0,0 +1,215 @@
+/*
+ * Copyright © 2020-2021 Keegan Saunders
+ *
+ * Licence: wxWindows Library Licence, Version 3.1
+ */
+
+use {
+    super::Interceptor,
+    crate::{Error, Gum, Module, NativePointer, Process, Result},
+    core::{
+        ptr,
+        sync::atomic::{AtomicPtr, Ordering},
+    },
+};
+
+/// Declares a hook replacing an exported function, for use with
+/// [`Interceptor`](crate::interceptor::Interceptor).
+///
+/// The function is looked up by name in the given module, which must already be loaded, with
+/// [`Module::find_export_by_name`](crate::Module::find_export_by_name), or in every module
+/// with [`Module::find_global_export_by_name`](crate::Module::find_global_export_by_name)
+/// when no module is given. The macro declares a unit struct named after the function, with:
+///
+/// - `install(&gum, &mut interceptor)` and `install_fast(&gum, &mut interceptor)` to replace
+///   the function through [`Interceptor::replace`](crate::interceptor::Interceptor::replace)
+///   or [`Interceptor::replace_fast`](crate::interceptor::Interceptor::replace_fast),
+///   failing with [`Error::ModuleNotFound`](crate::Error::ModuleNotFound) when the module
+///   isn't loaded and [`Error::ExportNotFound`](crate::Error::ExportNotFound) when the
+///   function isn't found;
+/// - `uninstall(&mut interceptor)` to revert the replacement;
+/// - `original()` returning the original implementation while installed.
+///
+/// In the body, the unsafe function `original` calls the original implementation with the
+/// same signature.
+///
+/// # Example
+///
+/// ```ignore
+/// use frida_gum::{Gum, gum_hook, interceptor::Interceptor};
+/// use libc::{c_char, c_int};
+///
+/// gum_hook! {
+///     fn open(path: *const c_char, flags: c_int) -> c_int in "libc.so.6" {
+///         println!("open: {:?}", unsafe { std::ffi::CStr::from_ptr(path) });
+///         unsafe { original(path, flags) }
+///     }
+/// }
+///
+/// let gum = Gum::obtain();
+/// let mut interceptor = Interceptor::obtain(&gum);
+/// open::install(&gum, &mut interceptor)?;
+/// ```
+#[macro_export]
+macro_rules! gum_hook {
+    (@ret $head:tt [$($ret:tt)*] in $module:literal { $($body:tt)* }) => {
+        $crate::gum_hook!(@hook $head [$($ret)*] [$module] { $($body)* });
+    };
+    (@ret $head:tt [$($ret:tt)*] { $($body:tt)* }) => {
+        $crate::gum_hook!(@hook $head [$($ret)*] [] { $($body)* });
+    };
+    // Collects the return type, which may be followed by the module.
+    (@ret $head:tt [$($ret:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::gum_hook!(@ret $head [$($ret)* $next] $($rest)*);
+    };
+    (@module $module:literal) => {
+        ::core::option::Option::Some($module)
+    };
+    (@module) => {
+        ::core::option::Option::None
+    };
+    (
+        @hook
+        [[$(#[$attr:meta])*] [$vis:vis] $name:ident ($($arg:ident: $ty:ty),*)]
+        [$($ret:tt)*]
+        [$($module:literal)?]
+        { $($body:tt)* }
+    ) => {
+        $(#[$attr])*
+        #[allow(non_camel_case_types)]
+        $vis struct $name;
+
+        impl $name {
+            #[doc(hidden)]
+            pub fn state() -> &'static $crate::interceptor::HookState {
+                static STATE: $crate::interceptor::HookState = $crate::interceptor::HookState::new();
+                &STATE
+            }
+
+            /// Replaces the function with the hook through `Interceptor::replace`.
+            pub fn install(
+                gum: &$crate::Gum,
+                interceptor: &mut $crate::interceptor::Interceptor,
+            ) -> $crate::Result<()> {
+                Self::state().install(
+                    gum,
+                    interceptor,
+                    $crate::gum_hook!(@module $($module)?),
+                    stringify!($name),
+                    $crate::NativePointer(Self::detour as *mut ::core::ffi::c_void),
+                    false,
+                )
+            }
+
+            /// Replaces the function with the hook through `Interceptor::replace_fast`.
+            pub fn install_fast(
+                gum: &$crate::Gum,
+                interceptor: &mut $crate::interceptor::Interceptor,
+            ) -> $crate::Result<()> {
+                Self::state().install(
+                    gum,
+                    interceptor,
+                    $crate::gum_hook!(@module $($module)?),
+                    stringify!($name),
+                    $crate::NativePointer(Self::detour as *mut ::core::ffi::c_void),
+                    true,
+                )
+            }
+
+            /// Reverts the replacement, if installed.
+            pub fn uninstall(interceptor: &mut $crate::interceptor::Interceptor) {
+                Self::state().uninstall(interceptor)
+            }
+
+            /// The original implementation of the function, while installed.
+            pub fn original() -> ::core::option::Option<unsafe extern "C" fn($($ty),*) $($ret)*> {
+                Self::state().original().map(|original| unsafe {
+                    ::core::mem::transmute::<
+                        *mut ::core::ffi::c_void,
+                        unsafe extern "C" fn($($ty),*) $($ret)*,
+                    >(original.0)
+                })
+            }
+
+            unsafe extern "C" fn detour($($arg: $ty),*) $($ret)* {
+                #[allow(dead_code)]
+                unsafe fn original($($arg: $ty),*) $($ret)* {
+                    let original = $name::original().expect("the hook is installed");
+                    unsafe { original($($arg),*) }
+                }
+
+                $($body)*
+            }
+        }
+    };
+    ($(#[$attr:meta])* $vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $($rest:tt)*) => {
+        $crate::gum_hook!(@ret [[$(#[$attr])*] [$vis] $name ($($arg: $ty),*)] [] $($rest)*);
+    };
+}
+
+/// The state of a hook declared with [`gum_hook!`](crate::gum_hook).
+#[doc(hidden)]
+pub struct HookState {
+    target: AtomicPtr<core::ffi::c_void>,
+    original: AtomicPtr<core::ffi::c_void>,
+}
+
+impl HookState {
+    pub const fn new() -> Self {
+        Self {
+            target: AtomicPtr::new(ptr::null_mut()),
+            original: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    pub fn install(
+        &self,
+        gum: &Gum,
+        interceptor: &mut Interceptor,
+        module: Option<&str>,
+        symbol: &str,
+        detour: NativePointer,
+        fast: bool,
+    ) -> Result<()> {
+        let target = match module {
+            Some(module) => Process::obtain(gum)
+                .find_module_by_name(module)
+                .ok_or(Error::ModuleNotFound)?
+                .find_export_by_name(symbol),
+            None => Module::find_global_export_by_name(symbol),
+        }
+        .ok_or(Error::ExportNotFound)?;
+
+        // The detour only becomes reachable once the transaction ends, after the original
+        // implementation is stored.
+        let mut transaction = interceptor.transaction();
+        let original = if fast {
+            transaction.replace_fast(target, detour)?
+        } else {
+            transaction.replace(target, detour, NativePointer(ptr::null_mut()))?
+        };
+        self.original.store(original.0, Ordering::Release);
+        self.target.store(target.0, Ordering::Release);
+        Ok(())
+    }
+
+    pub fn uninstall(&self, interceptor: &mut Interceptor) {
+        let target = self.target.swap(ptr::null_mut(), Ordering::AcqRel);
+        if !target.is_null() {
+            interceptor.revert(NativePointer(target));
+            // The trampoline calling the original implementation is gone with the hook.
+            self.original.store(ptr::null_mut(), Ordering::Release);
+        }
+    }
+
+    pub fn original(&self) -> Option<NativePointer> {
+        let original = self.original.load(Ordering::Acquire);
+        (!original.is_null()).then_some(NativePointer(original))
+    }
+}
+
+impl Default for HookState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
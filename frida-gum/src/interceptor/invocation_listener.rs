@@ -11,10 +11,13 @@
 
 use {
     crate::{CpuContext, CpuContextAccess, NativePointer},
-    core::{ffi::c_void, marker::PhantomData},
+    core::{ffi::c_void, marker::PhantomData, mem::size_of},
     frida_gum_sys as gum_sys,
 };
 
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+
 /// Represents a pair of listeners attached to a function.
 pub trait InvocationListener {
     /// Called when the attached function is entered.
@@ -55,6 +58,83 @@ pub(crate) fn invocation_listener_transform<I: InvocationListener>(
     unsafe { frida_gum_sys::gum_rust_invocation_listener_new(rust) }
 }
 
+/// The closures given to [`Interceptor::attach_with`](crate::interceptor::Interceptor::attach_with).
+struct CallListener<D, E, L> {
+    on_enter: E,
+    on_leave: L,
+    data: PhantomData<fn() -> D>,
+}
+
+/// The data of the current invocation, stored in the listener invocation data slot.
+unsafe fn invocation_data<D>(context: *mut gum_sys::GumInvocationContext) -> *mut D {
+    unsafe {
+        gum_sys::gum_invocation_context_get_listener_invocation_data(context, size_of::<D>() as _)
+            as *mut D
+    }
+}
+
+unsafe extern "C" fn call_closure_on_enter<D, E, L>(
+    context: *mut gum_sys::GumInvocationContext,
+    user_data: *mut c_void,
+) where
+    E: Fn(InvocationContext) -> D,
+{
+    unsafe {
+        let listener = &*(user_data as *const CallListener<D, E, L>);
+        let data = (listener.on_enter)(InvocationContext::from_raw(context));
+        // The slot isn't aligned for `D`.
+        invocation_data::<D>(context).write_unaligned(data);
+    }
+}
+
+unsafe extern "C" fn call_closure_on_leave<D, E, L>(
+    context: *mut gum_sys::GumInvocationContext,
+    user_data: *mut c_void,
+) where
+    L: Fn(InvocationContext, D),
+{
+    unsafe {
+        let listener = &*(user_data as *const CallListener<D, E, L>);
+        let data = invocation_data::<D>(context).read_unaligned();
+        (listener.on_leave)(InvocationContext::from_raw(context), data);
+    }
+}
+
+unsafe extern "C" fn drop_call_listener<D, E, L>(user_data: *mut c_void) {
+    unsafe { drop(Box::from_raw(user_data as *mut CallListener<D, E, L>)) }
+}
+
+pub(crate) fn call_listener_transform<D, E, L>(
+    on_enter: E,
+    on_leave: L,
+) -> *mut frida_gum_sys::GumInvocationListener
+where
+    E: Fn(InvocationContext) -> D + Send + Sync + 'static,
+    L: Fn(InvocationContext, D) + Send + Sync + 'static,
+{
+    const {
+        assert!(
+            size_of::<D>() <= gum_sys::GUM_MAX_LISTENER_DATA as usize,
+            "the invocation data must fit in GUM_MAX_LISTENER_DATA bytes"
+        )
+    };
+
+    let listener = Box::new(CallListener {
+        on_enter,
+        on_leave,
+        data: PhantomData,
+    });
+    // The listener owns the closures, which are dropped once it's finalized.
+    unsafe {
+        frida_gum_sys::gum_make_call_listener(
+            Some(call_closure_on_enter::<D, E, L>),
+            Some(call_closure_on_leave::<D, E, L>),
+            Box::into_raw(listener) as *mut c_void,
+            Some(drop_call_listener::<D, E, L>),
+        )
+    }
+}
+
 pub trait ProbeListener {
     fn on_hit(&mut self, context: InvocationContext);
 }
@@ -119,11 +199,17 @@ impl<'a> InvocationContext<'a> {
     }
 
     /// Get a numbered argument from the processor context, determined by the platform calling convention.
+    ///
+    /// Arguments past those passed in registers are read from the stack, where the calling
+    /// convention puts them, e.g. from the 7th on x86_64 System V and the 9th on AArch64.
+    /// Only integer and pointer arguments are counted.
     pub fn arg(&self, n: u32) -> usize {
         unsafe { gum_sys::gum_invocation_context_get_nth_argument(self.context, n) as usize }
     }
 
     /// Set a numbered argument in the processor context, determined by the platform calling convention.
+    ///
+    /// Arguments passed on the stack are written there, see [`arg`](Self::arg).
     pub fn set_arg(&self, n: u32, value: usize) {
         unsafe {
             gum_sys::gum_invocation_context_replace_nth_argument(
@@ -146,6 +232,27 @@ impl<'a> InvocationContext<'a> {
         }
     }
 
+    /// Get the address of the hooked function.
+    pub fn function(&self) -> NativePointer {
+        NativePointer(unsafe { (*self.context).function })
+    }
+
+    /// Get the system error, `errno` or the value of `GetLastError()`, saved when the hooked
+    /// function was entered or left.
+    pub fn system_error(&self) -> i32 {
+        unsafe { (*self.context).system_error }
+    }
+
+    /// Set the system error, `errno` or the value of `GetLastError()`, restored when the
+    /// listener returns. Setting it directly from a listener has no effect, as it is
+    /// overwritten by the saved value.
+    ///
+    /// A replacement sets the system error directly instead: the caller observes the value
+    /// left when the replacement returns, which is then saved over this one.
+    pub fn set_system_error(&self, value: i32) {
+        unsafe { (*self.context).system_error = value }
+    }
+
     /// Get the destination address after the function returns.
     pub fn return_addr(&self) -> usize {
         unsafe { gum_sys::gum_invocation_context_get_return_address(self.context) as usize }
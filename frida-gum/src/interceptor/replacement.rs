@@ -0,0 +1,170 @@
+/*
+ * Copyright © 2020-2021 Keegan Saunders
+ *
+ * Licence: wxWindows Library Licence, Version 3.1
+ */
+
+use {
+    super::Interceptor,
+    crate::NativePointer,
+    core::{ffi::c_void, mem},
+    frida_gum_sys as gum_sys,
+};
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+
+/// A C function pointer type, such as `unsafe extern "C" fn(*const c_char, c_int) -> c_int`,
+/// describing the signature of a function replaced with [`Interceptor::replace_with`].
+///
+/// It is implemented for `unsafe extern "C"` functions taking up to eight arguments.
+pub trait NativeFunction: Copy + Send + Sync + 'static {
+    /// The arguments of the function, as a tuple.
+    type Args;
+    /// The return type of the function.
+    type Output;
+
+    /// Converts a function address to the function pointer type.
+    ///
+    /// # Safety
+    ///
+    /// The address must point to a function with this signature.
+    unsafe fn from_ptr(ptr: NativePointer) -> Self;
+
+    /// The address of the function.
+    fn as_ptr(self) -> NativePointer;
+
+    /// A function with this signature calling the closure `F` found in the replacement data
+    /// of the current invocation.
+    #[doc(hidden)]
+    fn replacement<F>() -> NativePointer
+    where
+        F: Fn(Self, Self::Args) -> Self::Output + Send + Sync + 'static;
+}
+
+/// The replacement data of functions replaced with [`Interceptor::replace_with`].
+pub(crate) struct ReplacementData<F> {
+    /// Written by Gum when replacing the function, before the replacement is active.
+    pub(crate) original: *mut c_void,
+    /// Called from any thread, possibly from several at once.
+    pub(crate) closure: F,
+}
+
+/// The replacement data of the replaced function currently executing.
+///
+/// # Safety
+///
+/// Must be called from a function replaced with [`Interceptor::replace_with`] with a
+/// closure of type `F`.
+unsafe fn current_replacement_data<'a, F>() -> &'a ReplacementData<F> {
+    unsafe {
+        let context = gum_sys::gum_interceptor_get_current_invocation();
+        &*(gum_sys::gum_invocation_context_get_replacement_data(context)
+            as *const ReplacementData<F>)
+    }
+}
+
+pub(crate) unsafe fn drop_replacement_data<F>(data: *mut c_void) {
+    unsafe { drop(Box::from_raw(data as *mut ReplacementData<F>)) }
+}
+
+macro_rules! impl_native_function {
+    ($($arg:ident: $ty:ident),*) => {
+        impl<R: 'static, $($ty: 'static),*> NativeFunction for unsafe extern "C" fn($($ty),*) -> R {
+            type Args = ($($ty,)*);
+            type Output = R;
+
+            unsafe fn from_ptr(ptr: NativePointer) -> Self {
+                unsafe { mem::transmute::<*mut c_void, Self>(ptr.0) }
+            }
+
+            fn as_ptr(self) -> NativePointer {
+                NativePointer(self as *mut c_void)
+            }
+
+            fn replacement<F>() -> NativePointer
+            where
+                F: Fn(Self, Self::Args) -> R + Send + Sync + 'static,
+            {
+                unsafe extern "C" fn replacement<R, $($ty,)* F>($($arg: $ty),*) -> R
+                where
+                    F: Fn(unsafe extern "C" fn($($ty),*) -> R, ($($ty,)*)) -> R,
+                {
+                    unsafe {
+                        let data = current_replacement_data::<F>();
+                        let original =
+                            mem::transmute::<*mut c_void, unsafe extern "C" fn($($ty),*) -> R>(
+                                data.original,
+                            );
+                        (data.closure)(original, ($($arg,)*))
+                    }
+                }
+
+                NativePointer(replacement::<R, $($ty,)* F> as *mut c_void)
+            }
+        }
+    };
+}
+
+impl_native_function!();
+impl_native_function!(a0: A0);
+impl_native_function!(a0: A0, a1: A1);
+impl_native_function!(a0: A0, a1: A1, a2: A2);
+impl_native_function!(a0: A0, a1: A1, a2: A2, a3: A3);
+impl_native_function!(a0: A0, a1: A1, a2: A2, a3: A3, a4: A4);
+impl_native_function!(a0: A0, a1: A1, a2: A2, a3: A3, a4: A4, a5: A5);
+impl_native_function!(a0: A0, a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6);
+impl_native_function!(a0: A0, a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7);
+
+/// A function replaced with a closure by [`Interceptor::replace_with`].
+///
+/// Dropping it leaves the replacement in place and leaks the closure; use
+/// [`Replacement::revert`] to restore the function.
+pub struct Replacement<S> {
+    function: NativePointer,
+    original: S,
+    data: *mut c_void,
+    drop_data: unsafe fn(*mut c_void),
+}
+
+// The replacement data only holds the `Send + Sync` closure.
+unsafe impl<S: Send> Send for Replacement<S> {}
+unsafe impl<S: Sync> Sync for Replacement<S> {}
+
+impl<S: NativeFunction> Replacement<S> {
+    pub(crate) fn new(
+        function: NativePointer,
+        original: S,
+        data: *mut c_void,
+        drop_data: unsafe fn(*mut c_void),
+    ) -> Self {
+        Self {
+            function,
+            original,
+            data,
+            drop_data,
+        }
+    }
+
+    /// The replaced function.
+    pub fn function(&self) -> NativePointer {
+        self.function
+    }
+
+    /// The original implementation of the function, which calls it without going through
+    /// the replacement.
+    pub fn original(&self) -> S {
+        self.original
+    }
+
+    /// Restores the original implementation of the function and drops the closure.
+    ///
+    /// # Safety
+    ///
+    /// No thread may be executing the replacement anymore: reverting only stops new calls
+    /// from reaching it, and the closure is dropped right away.
+    pub unsafe fn revert(self, interceptor: &mut Interceptor) {
+        interceptor.revert(self.function);
+        unsafe { (self.drop_data)(self.data) };
+    }
+}
@@ -81,6 +81,9 @@ pub use module::*;
 mod module_map;
 pub use module_map::*;
 
+mod module_registry;
+pub use module_registry::*;
+
 mod process;
 pub use process::*;
 
@@ -107,6 +110,11 @@ pub use debug_symbol::*;
 mod thread;
 pub use thread::*;
 
+mod thread_registry;
+pub use thread_registry::*;
+
+mod subscription;
+
 #[cfg(feature = "backtrace")]
 #[cfg_attr(docsrs, doc(cfg(feature = "backtrace")))]
 mod backtracer;
@@ -119,6 +127,9 @@ mod script;
 #[cfg(feature = "script")]
 pub use script::*;
 
+#[cfg(feature = "dynamic-loading")]
+pub use frida_gum_sys::LoadError;
+
 #[doc(hidden)]
 pub type Result<T> = core::result::Result<T, error::Error>;
 
@@ -129,6 +140,18 @@ pub struct Gum {
 }
 
 impl Gum {
+    /// Loads frida-gum from the shared library at `path`, e.g. from an installed Frida
+    /// build.
+    ///
+    /// This must succeed before calling [`Gum::obtain`] or any other function of this crate,
+    /// and can only succeed once per process. The functions of the core modules are checked
+    /// to be exported by the library; the ones used by optional features and
+    /// architecture-specific modules panic when called if missing.
+    #[cfg(feature = "dynamic-loading")]
+    pub fn load_library(path: impl AsRef<std::ffi::OsStr>) -> core::result::Result<(), LoadError> {
+        frida_gum_sys::load(path, REQUIRED_SYMBOLS)
+    }
+
     pub fn obtain() -> Self {
         let mut singleton = GUM_SINGLETON.lock();
         let handle = singleton.get_or_insert_with(|| Arc::new(GumSingleton::obtain()));
@@ -153,6 +176,11 @@ impl Drop for Gum {
     }
 }
 
+/// The frida-gum functions used by the core modules, which must be exported by a library
+/// loaded with [`Gum::load_library`].
+#[cfg(feature = "dynamic-loading")]
+const REQUIRED_SYMBOLS: &[&str] = include!(concat!(env!("OUT_DIR"), "/required_symbols.rs"));
+
 static GUM_SINGLETON: spin::Mutex<GumSingletonHandle> = spin::Mutex::new(None);
 
 type GumSingletonHandle = Option<Arc<GumSingleton>>;
@@ -18,12 +18,19 @@ use crate::MemoryRange;
 use std::{ffi::CStr, string::ToString};
 
 use {
-    crate::{Gum, NativePointer, PageProtection, RangeDetails},
-    core::{ffi::c_void, fmt, fmt::Debug},
+    crate::{Error, Gum, NativePointer, PageProtection, RangeDetails, Result},
+    core::{
+        ffi::c_void,
+        fmt,
+        fmt::Debug,
+        mem,
+        sync::atomic::{AtomicPtr, Ordering},
+    },
     cstr_core::CString,
     frida_gum_sys as gum_sys,
     frida_gum_sys::{
-        GumExportDetails, GumModule, GumSectionDetails, GumSymbolDetails, gboolean, gpointer,
+        GumDependencyDetails, GumExportDetails, GumImportDetails, GumModule, GumSectionDetails,
+        GumSymbolDetails, gboolean, gpointer,
     },
 };
 
@@ -60,6 +67,46 @@ impl fmt::Display for ExportType {
     }
 }
 
+/// Import type.
+#[derive(Clone, FromPrimitive, Debug)]
+#[repr(u32)]
+pub enum ImportType {
+    Unknown = gum_sys::GumImportType_GUM_IMPORT_UNKNOWN as u32,
+    Function = gum_sys::GumImportType_GUM_IMPORT_FUNCTION as u32,
+    Variable = gum_sys::GumImportType_GUM_IMPORT_VARIABLE as u32,
+}
+
+impl fmt::Display for ImportType {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportType::Unknown => write!(fmt, "unknown"),
+            ImportType::Function => write!(fmt, "function"),
+            ImportType::Variable => write!(fmt, "variable"),
+        }
+    }
+}
+
+/// Dependency type.
+#[derive(Clone, FromPrimitive, Debug)]
+#[repr(u32)]
+pub enum DependencyType {
+    Regular = gum_sys::GumDependencyType_GUM_DEPENDENCY_REGULAR as u32,
+    Weak = gum_sys::GumDependencyType_GUM_DEPENDENCY_WEAK as u32,
+    Reexport = gum_sys::GumDependencyType_GUM_DEPENDENCY_REEXPORT as u32,
+    Upward = gum_sys::GumDependencyType_GUM_DEPENDENCY_UPWARD as u32,
+}
+
+impl fmt::Display for DependencyType {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DependencyType::Regular => write!(fmt, "regular"),
+            DependencyType::Weak => write!(fmt, "weak"),
+            DependencyType::Reexport => write!(fmt, "reexport"),
+            DependencyType::Upward => write!(fmt, "upward"),
+        }
+    }
+}
+
 /// Module symbol details returned by [`Module::enumerate_symbols`].
 pub struct SymbolDetails {
     pub name: String,
@@ -82,6 +129,25 @@ pub struct ExportDetails {
     pub address: usize,
 }
 
+/// Module import details returned by [`Module::enumerate_imports`].
+pub struct ImportDetails {
+    pub typ: ImportType,
+    pub name: String,
+    /// The module the import is resolved from, if known.
+    pub module: Option<String>,
+    /// The resolved address of the import, or 0 if unknown.
+    pub address: usize,
+    /// The address of the slot holding the resolved address, such as a GOT entry, or 0 if
+    /// unknown.
+    pub slot: usize,
+}
+
+/// Module dependency details returned by [`Module::enumerate_dependencies`].
+pub struct DependencyDetails {
+    pub name: String,
+    pub typ: DependencyType,
+}
+
 impl Drop for Module {
     fn drop(&mut self) {
         unsafe {
@@ -94,6 +160,9 @@ pub struct Module {
     inner: *mut GumModule,
 }
 
+// Gum modules are immutable once created, and reference counted atomically.
+unsafe impl Send for Module {}
+
 impl Module {
     pub(crate) fn from_raw(module: *mut GumModule) -> Self {
         unsafe {
@@ -207,6 +276,106 @@ impl Module {
         }
     }
 
+    /// Enumerates imports of module.
+    pub fn enumerate_imports(&self) -> Vec<ImportDetails> {
+        let result: Vec<ImportDetails> = vec![];
+
+        unsafe extern "C" fn callback(
+            details: *const GumImportDetails,
+            user_data: gpointer,
+        ) -> gboolean {
+            unsafe {
+                let res = &mut *(user_data as *mut Vec<ImportDetails>);
+                let name: String = NativePointer((*details).name as *mut _)
+                    .try_into()
+                    .unwrap_or_default();
+                let module: Option<String> =
+                    NativePointer((*details).module as *mut _).try_into().ok();
+
+                let typ = num::FromPrimitive::from_u32((*details).type_ as u32)
+                    .unwrap_or(ImportType::Unknown);
+                let info = ImportDetails {
+                    typ,
+                    name,
+                    module,
+                    address: (*details).address as usize,
+                    slot: (*details).slot as usize,
+                };
+                res.push(info);
+                1
+            }
+        }
+
+        unsafe {
+            frida_gum_sys::gum_module_enumerate_imports(
+                self.inner,
+                Some(callback),
+                &result as *const _ as *mut c_void,
+            );
+        }
+        result
+    }
+
+    /// Enumerates modules this module depends on.
+    pub fn enumerate_dependencies(&self) -> Vec<DependencyDetails> {
+        let result: Vec<DependencyDetails> = vec![];
+
+        unsafe extern "C" fn callback(
+            details: *const GumDependencyDetails,
+            user_data: gpointer,
+        ) -> gboolean {
+            unsafe {
+                let res = &mut *(user_data as *mut Vec<DependencyDetails>);
+                let name: String = NativePointer((*details).name as *mut _)
+                    .try_into()
+                    .unwrap_or_default();
+
+                // Dependency types added by newer versions of gum are skipped.
+                if let Some(typ) = num::FromPrimitive::from_u32((*details).type_ as u32) {
+                    res.push(DependencyDetails { name, typ });
+                }
+                1
+            }
+        }
+
+        unsafe {
+            frida_gum_sys::gum_module_enumerate_dependencies(
+                self.inner,
+                Some(callback),
+                &result as *const _ as *mut c_void,
+            );
+        }
+        result
+    }
+
+    /// Replaces the address in the slot of the import `name`, such as its GOT entry, so that
+    /// calls made by this module to the import go to `replacement`. Unlike the
+    /// [`Interceptor`](crate::interceptor::Interceptor), the imported function is left
+    /// untouched, and calls from other modules aren't affected.
+    ///
+    /// The slot is restored when the returned [`ImportHook`] is dropped, if the module is
+    /// still loaded. Its page is kept writable until then.
+    ///
+    /// # Safety
+    ///
+    /// `replacement` must have the signature of the import.
+    pub unsafe fn hook_import(&self, name: &str, replacement: NativePointer) -> Result<ImportHook> {
+        let slot = self
+            .enumerate_imports()
+            .into_iter()
+            .find(|import| import.name == name && import.slot != 0)
+            .ok_or(Error::ImportNotFound)?
+            .slot as *mut *mut c_void;
+
+        let original = NativePointer(unsafe { replace_slot(slot, replacement.0) }?);
+        Ok(ImportHook {
+            slot,
+            original,
+            replacement,
+            module_path: self.path(),
+        })
+    }
+
     /// Enumerates exports in module.
     pub fn enumerate_exports(&self) -> Vec<ExportDetails> {
         let result: Vec<ExportDetails> = vec![];
@@ -319,6 +488,131 @@ impl Module {
     }
 }
 
+/// A page holding hooked import slots, made writable if it wasn't.
+struct WritablePage {
+    address: usize,
+    protection: gum_sys::GumPageProtection,
+    hooks: usize,
+}
+
+/// The pages holding the slots of the live [`ImportHook`]s. Slots are written and pages
+/// protected with it locked, and a page stays writable until its last hook is dropped, so
+/// that hooks in the same page don't restore its protection under each other.
+static WRITABLE_PAGES: spin::Mutex<Vec<WritablePage>> = spin::Mutex::new(Vec::new());
+
+fn page_size() -> usize {
+    unsafe { gum_sys::gum_query_page_size() as usize }
+}
+
+/// Writes `value` to the pointer-sized `slot`, making its page writable until the slot is
+/// restored, and returns the previous value.
+unsafe fn replace_slot(slot: *mut *mut c_void, value: *mut c_void) -> Result<*mut c_void> {
+    let write = gum_sys::_GumPageProtection_GUM_PAGE_WRITE;
+    let address = slot as usize & !(page_size() - 1);
+
+    let mut pages = WRITABLE_PAGES.lock();
+    let index = match pages.iter().position(|page| page.address == address) {
+        Some(index) => index,
+        None => {
+            let mut protection: gum_sys::GumPageProtection = 0;
+            unsafe {
+                if gum_sys::gum_memory_query_protection(slot as *const c_void, &mut protection) == 0
+                {
+                    return Err(Error::MemoryAccessError);
+                }
+                if protection & write == 0
+                    && gum_sys::gum_try_mprotect(
+                        address as *mut c_void,
+                        page_size(),
+                        protection | write,
+                    ) == 0
+                {
+                    return Err(Error::MemoryAccessError);
+                }
+            }
+            pages.push(WritablePage {
+                address,
+                protection,
+                hooks: 0,
+            });
+            pages.len() - 1
+        }
+    };
+    pages[index].hooks += 1;
+    Ok(unsafe { (*(slot as *const AtomicPtr<c_void>)).swap(value, Ordering::SeqCst) })
+}
+
+/// Writes `original` back to `slot` if it still holds `replacement`, and restores the
+/// protection of its page once it holds no other hooked slot. When the module was unloaded,
+/// only the bookkeeping is dropped, as the page may now belong to another mapping.
+unsafe fn restore_slot(
+    slot: *mut *mut c_void,
+    replacement: *mut c_void,
+    original: *mut c_void,
+    loaded: bool,
+) {
+    let address = slot as usize & !(page_size() - 1);
+
+    let mut pages = WRITABLE_PAGES.lock();
+    let Some(index) = pages.iter().position(|page| page.address == address) else {
+        return;
+    };
+    if loaded {
+        // The slot is left alone if it was replaced again since.
+        let _ = unsafe { &*(slot as *const AtomicPtr<c_void>) }.compare_exchange(
+            replacement,
+            original,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+    }
+    pages[index].hooks -= 1;
+    if pages[index].hooks == 0 {
+        let page = pages.swap_remove(index);
+        if loaded && page.protection & gum_sys::_GumPageProtection_GUM_PAGE_WRITE == 0 {
+            unsafe {
+                gum_sys::gum_try_mprotect(page.address as *mut c_void, page_size(), page.protection)
+            };
+        }
+    }
+}
+
+/// An import slot of a module replaced by [`Module::hook_import`], restored when dropped.
+///
+/// Nothing is written when dropped after the module was unloaded.
+pub struct ImportHook {
+    slot: *mut *mut c_void,
+    original: NativePointer,
+    replacement: NativePointer,
+    module_path: String,
+}
+
+impl ImportHook {
+    /// The address of the slot.
+    pub fn slot(&self) -> NativePointer {
+        NativePointer(self.slot as *mut c_void)
+    }
+
+    /// The address previously in the slot, which calls the original import.
+    pub fn original(&self) -> NativePointer {
+        self.original
+    }
+
+    /// Leave the slot replaced for the rest of the process.
+    pub fn leak(self) {
+        mem::forget(self);
+    }
+}
+
+impl Drop for ImportHook {
+    fn drop(&mut self) {
+        let module = unsafe { gum_sys::gum_process_find_module_by_address(self.slot as u64) };
+        // The module found holds a reference, released with it.
+        let loaded = !module.is_null() && Module { inner: module }.path() == self.module_path;
+        unsafe { restore_slot(self.slot, self.replacement.0, self.original.0, loaded) };
+    }
+}
+
 impl Debug for Module {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Module")
@@ -0,0 +1,194 @@
+/*
+ * Copyright © 2020-2021 Keegan Saunders
+ *
+ * Licence: wxWindows Library Licence, Version 3.1
+ */
+
+//! Module load and unload notifications.
+//!
+
+use {
+    crate::{
+        Gum, Module,
+        subscription::{Dispatcher, Subscription},
+    },
+    frida_gum_sys as gum_sys,
+    frida_gum_sys::{GumModule, GumModuleRegistry, gboolean, gpointer},
+};
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use std::sync::mpsc::Sender;
+
+/// A change to the modules loaded in the process.
+pub enum ModuleEvent {
+    /// The module was loaded.
+    Added(Module),
+    /// The module was unloaded.
+    Removed(Module),
+}
+
+/// Handles the modules added to and removed from a [`ModuleRegistry`].
+///
+/// It is implemented for closures taking a [`ModuleEvent`], and for `Sender<ModuleEvent>`.
+pub trait ModuleHandler {
+    /// Called when a module is loaded.
+    fn on_added(&mut self, module: Module);
+    /// Called when a module is unloaded.
+    fn on_removed(&mut self, module: Module);
+}
+
+impl<F: FnMut(ModuleEvent)> ModuleHandler for F {
+    fn on_added(&mut self, module: Module) {
+        self(ModuleEvent::Added(module))
+    }
+
+    fn on_removed(&mut self, module: Module) {
+        self(ModuleEvent::Removed(module))
+    }
+}
+
+#[cfg(feature = "std")]
+impl ModuleHandler for Sender<ModuleEvent> {
+    fn on_added(&mut self, module: Module) {
+        let _ = self.send(ModuleEvent::Added(module));
+    }
+
+    fn on_removed(&mut self, module: Module) {
+        let _ = self.send(ModuleEvent::Removed(module));
+    }
+}
+
+/// The registry of the modules loaded in the process, which is notified as modules are
+/// loaded and unloaded.
+#[derive(Clone)]
+pub struct ModuleRegistry {
+    registry: *mut GumModuleRegistry,
+    _gum: Gum,
+}
+
+impl ModuleRegistry {
+    /// Obtain the module registry of the process.
+    pub fn obtain(gum: &Gum) -> Self {
+        Self {
+            registry: unsafe { gum_sys::gum_module_registry_obtain() },
+            _gum: gum.clone(),
+        }
+    }
+
+    /// The modules currently loaded.
+    pub fn modules(&self) -> Vec<Module> {
+        unsafe extern "C" fn on_module_found(
+            module: *mut GumModule,
+            user_data: gpointer,
+        ) -> gboolean {
+            unsafe { (*(user_data as *mut Vec<Module>)).push(Module::from_raw(module)) };
+            1
+        }
+
+        let mut modules = Vec::new();
+        unsafe {
+            gum_sys::gum_module_registry_enumerate_modules(
+                self.registry,
+                Some(on_module_found),
+                &mut modules as *mut _ as gpointer,
+            )
+        };
+        modules
+    }
+
+    /// Calls `handler` with every module currently loaded, then with every module loaded or
+    /// unloaded until the returned [`ModuleSubscription`] is dropped. No change is missed in
+    /// between.
+    ///
+    /// The handler is called from the thread loading or unloading the module, with the
+    /// loader's lock possibly held. A module loaded or unloaded by the handler itself is
+    /// handled once the running call returns.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let (tx, rx) = std::sync::mpsc::channel();
+    /// let subscription = ModuleRegistry::obtain(&gum).subscribe(tx);
+    ///
+    /// for event in rx {
+    ///     match event {
+    ///         ModuleEvent::Added(module) => println!("loaded {}", module.path()),
+    ///         ModuleEvent::Removed(module) => println!("unloaded {}", module.path()),
+    ///     }
+    /// }
+    /// ```
+    pub fn subscribe<H>(&self, handler: H) -> ModuleSubscription
+    where
+        H: ModuleHandler + Send + 'static,
+    {
+        unsafe extern "C" fn on_module_found<H: ModuleHandler>(
+            module: *mut GumModule,
+            user_data: gpointer,
+        ) -> gboolean {
+            let event = ModuleEvent::Added(Module::from_raw(module));
+            unsafe { ModuleDispatcher::<H>::from_user_data(user_data) }
+                .dispatch(event, deliver::<H>);
+            1
+        }
+
+        unsafe extern "C" fn on_module_added<H: ModuleHandler>(
+            _registry: *mut GumModuleRegistry,
+            module: *mut GumModule,
+            user_data: gpointer,
+        ) {
+            let event = ModuleEvent::Added(Module::from_raw(module));
+            unsafe { ModuleDispatcher::<H>::from_user_data(user_data) }
+                .dispatch(event, deliver::<H>);
+        }
+
+        unsafe extern "C" fn on_module_removed<H: ModuleHandler>(
+            _registry: *mut GumModuleRegistry,
+            module: *mut GumModule,
+            user_data: gpointer,
+        ) {
+            let event = ModuleEvent::Removed(Module::from_raw(module));
+            unsafe { ModuleDispatcher::<H>::from_user_data(user_data) }
+                .dispatch(event, deliver::<H>);
+        }
+
+        fn deliver<H: ModuleHandler>(handler: &mut H, event: ModuleEvent) {
+            match event {
+                ModuleEvent::Added(module) => handler.on_added(module),
+                ModuleEvent::Removed(module) => handler.on_removed(module),
+            }
+        }
+
+        let registry = self.registry;
+        ModuleSubscription {
+            _subscription: Subscription::new(
+                &self._gum,
+                registry,
+                |registry| unsafe { gum_sys::gum_module_registry_lock(registry) },
+                |registry| unsafe { gum_sys::gum_module_registry_unlock(registry) },
+                ModuleDispatcher::new(handler),
+                |dispatcher| unsafe {
+                    gum_sys::gum_module_registry_enumerate_modules(
+                        registry,
+                        Some(on_module_found::<H>),
+                        dispatcher,
+                    );
+                },
+                [
+                    (c"module-added", on_module_added::<H> as _),
+                    (c"module-removed", on_module_removed::<H> as _),
+                ],
+            ),
+        }
+    }
+}
+
+type ModuleDispatcher<H> = Dispatcher<H, ModuleEvent>;
+
+/// A handler subscribed with [`ModuleRegistry::subscribe`], unsubscribed and dropped when
+/// this is dropped.
+pub struct ModuleSubscription {
+    _subscription: Subscription<GumModuleRegistry, 2>,
+}
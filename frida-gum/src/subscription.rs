@@ -0,0 +1,148 @@
+/*
+ * Copyright © 2020-2021 Keegan Saunders
+ *
+ * Licence: wxWindows Library Licence, Version 3.1
+ */
+
+//! Handlers subscribed to the notifications of a registry, such as the module and thread
+//! registries.
+//!
+
+use {
+    crate::Gum,
+    core::{
+        ffi::{CStr, c_void},
+        mem,
+        sync::atomic::{AtomicUsize, Ordering},
+    },
+    frida_gum_sys as gum_sys,
+    frida_gum_sys::gpointer,
+};
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, collections::VecDeque};
+
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+
+/// A subscribed handler, called by one thread at a time.
+///
+/// An event emitted by the thread running the handler, such as for a module loaded by the
+/// handler itself, is queued and handled once the running call returns, rather than
+/// deadlocking on the handler's lock.
+pub(crate) struct Dispatcher<H, E> {
+    handler: spin::Mutex<H>,
+    // The thread running the handler, or 0.
+    owner: AtomicUsize,
+    pending: spin::Mutex<VecDeque<E>>,
+}
+
+impl<H, E> Dispatcher<H, E> {
+    pub(crate) fn new(handler: H) -> Self {
+        Self {
+            handler: spin::Mutex::new(handler),
+            owner: AtomicUsize::new(0),
+            pending: spin::Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// The dispatcher passed as `user_data` to the callbacks of a [`Subscription`].
+    pub(crate) unsafe fn from_user_data<'a>(user_data: gpointer) -> &'a Self {
+        unsafe { &*(user_data as *const Self) }
+    }
+
+    /// Calls `deliver` with the handler and `event`, then with the events queued meanwhile.
+    pub(crate) fn dispatch(&self, event: E, deliver: fn(&mut H, E)) {
+        let thread = unsafe { gum_sys::gum_process_get_current_thread_id() } as usize;
+        if self.owner.load(Ordering::Acquire) == thread {
+            self.pending.lock().push_back(event);
+            return;
+        }
+
+        let mut handler = self.handler.lock();
+        self.owner.store(thread, Ordering::Release);
+        deliver(&mut handler, event);
+        loop {
+            let next = self.pending.lock().pop_front();
+            let Some(event) = next else {
+                break;
+            };
+            deliver(&mut handler, event);
+        }
+        self.owner.store(0, Ordering::Release);
+    }
+}
+
+/// A [`Dispatcher`] connected to `N` signals of a registry `R`, which emits them with its
+/// lock held. The signals are disconnected and the dispatcher dropped when this is dropped.
+pub(crate) struct Subscription<R, const N: usize> {
+    registry: *mut R,
+    lock: fn(*mut R),
+    unlock: fn(*mut R),
+    handlers: [gum_sys::gulong; N],
+    dispatcher: *mut c_void,
+    drop_dispatcher: unsafe fn(*mut c_void),
+    _gum: Gum,
+}
+
+// The handler is `Send`, and the registries are thread-safe.
+unsafe impl<R, const N: usize> Send for Subscription<R, N> {}
+
+impl<R, const N: usize> Subscription<R, N> {
+    /// Calls `enumerate` with `dispatcher`, to hand it what the registry currently holds,
+    /// then connects the `callback` of each signal to it. The registry is locked by `lock`
+    /// and `unlock` meanwhile, so that no change is missed in between.
+    pub(crate) fn new<H: Send, E>(
+        gum: &Gum,
+        registry: *mut R,
+        lock: fn(*mut R),
+        unlock: fn(*mut R),
+        dispatcher: Dispatcher<H, E>,
+        enumerate: impl FnOnce(gpointer),
+        signals: [(&CStr, *mut c_void); N],
+    ) -> Self {
+        let dispatcher = Box::into_raw(Box::new(dispatcher)) as *mut c_void;
+        lock(registry);
+        enumerate(dispatcher);
+        let handlers = signals.map(|(signal, callback)| unsafe {
+            gum_sys::g_signal_connect_data(
+                registry as gpointer,
+                signal.as_ptr(),
+                Some(mem::transmute::<*mut c_void, unsafe extern "C" fn()>(
+                    callback,
+                )),
+                dispatcher,
+                None,
+                0,
+            )
+        });
+        unlock(registry);
+
+        Self {
+            registry,
+            lock,
+            unlock,
+            handlers,
+            dispatcher,
+            drop_dispatcher: drop_dispatcher::<H, E>,
+            _gum: gum.clone(),
+        }
+    }
+}
+
+unsafe fn drop_dispatcher<H, E>(dispatcher: *mut c_void) {
+    unsafe { drop(Box::from_raw(dispatcher as *mut Dispatcher<H, E>)) }
+}
+
+impl<R, const N: usize> Drop for Subscription<R, N> {
+    fn drop(&mut self) {
+        // Notifications are emitted with the registry locked, so none is running once the
+        // handlers are disconnected.
+        (self.lock)(self.registry);
+        for id in self.handlers {
+            unsafe { gum_sys::g_signal_handler_disconnect(self.registry as gpointer, id) };
+        }
+        (self.unlock)(self.registry);
+        unsafe { (self.drop_dispatcher)(self.dispatcher) };
+    }
+}
@@ -52,6 +52,9 @@ pub struct Thread {
     thread: *mut GumThreadDetails,
 }
 
+// The details are a copy owned by the thread.
+unsafe impl Send for Thread {}
+
 impl Thread {
     pub(crate) fn from_raw(thread: *const GumThreadDetails) -> Self {
         // I'm not sure if copying the threads details is needed, but it looks like that threads are not refcounted, so
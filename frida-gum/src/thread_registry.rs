@@ -0,0 +1,241 @@
+/*
+ * Copyright © 2020-2021 Keegan Saunders
+ *
+ * Licence: wxWindows Library Licence, Version 3.1
+ */
+
+//! Thread creation and termination notifications.
+//!
+
+use {
+    crate::{
+        Gum, Thread,
+        subscription::{Dispatcher, Subscription},
+    },
+    core::ffi::{CStr, c_char},
+    frida_gum_sys as gum_sys,
+    frida_gum_sys::{GumThreadDetails, GumThreadRegistry, gboolean, gpointer},
+};
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+#[cfg(feature = "std")]
+use std::sync::mpsc::Sender;
+
+/// A change to the threads of the process.
+pub enum ThreadEvent {
+    /// The thread was created.
+    Added(Thread),
+    /// The thread terminated.
+    Removed(Thread),
+    /// The thread was renamed.
+    Renamed {
+        /// The thread, with its new name.
+        thread: Thread,
+        /// The name of the thread before it was renamed, if it had one.
+        previous_name: Option<String>,
+    },
+}
+
+/// Handles the threads added to, removed from and renamed in a [`ThreadRegistry`].
+///
+/// It is implemented for closures taking a [`ThreadEvent`], and for `Sender<ThreadEvent>`.
+pub trait ThreadHandler {
+    /// Called when a thread is created.
+    fn on_added(&mut self, thread: Thread);
+    /// Called when a thread terminates.
+    fn on_removed(&mut self, thread: Thread);
+    /// Called when a thread is renamed.
+    fn on_renamed(&mut self, thread: Thread, previous_name: Option<String>);
+}
+
+impl<F: FnMut(ThreadEvent)> ThreadHandler for F {
+    fn on_added(&mut self, thread: Thread) {
+        self(ThreadEvent::Added(thread))
+    }
+
+    fn on_removed(&mut self, thread: Thread) {
+        self(ThreadEvent::Removed(thread))
+    }
+
+    fn on_renamed(&mut self, thread: Thread, previous_name: Option<String>) {
+        self(ThreadEvent::Renamed {
+            thread,
+            previous_name,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl ThreadHandler for Sender<ThreadEvent> {
+    fn on_added(&mut self, thread: Thread) {
+        let _ = self.send(ThreadEvent::Added(thread));
+    }
+
+    fn on_removed(&mut self, thread: Thread) {
+        let _ = self.send(ThreadEvent::Removed(thread));
+    }
+
+    fn on_renamed(&mut self, thread: Thread, previous_name: Option<String>) {
+        let _ = self.send(ThreadEvent::Renamed {
+            thread,
+            previous_name,
+        });
+    }
+}
+
+/// The registry of the threads of the process, which is notified as threads are created,
+/// terminate and are renamed.
+#[derive(Clone)]
+pub struct ThreadRegistry {
+    registry: *mut GumThreadRegistry,
+    _gum: Gum,
+}
+
+impl ThreadRegistry {
+    /// Obtain the thread registry of the process.
+    pub fn obtain(gum: &Gum) -> Self {
+        Self {
+            registry: unsafe { gum_sys::gum_thread_registry_obtain() },
+            _gum: gum.clone(),
+        }
+    }
+
+    /// The threads currently running.
+    pub fn threads(&self) -> Vec<Thread> {
+        unsafe extern "C" fn on_thread_found(
+            thread: *const GumThreadDetails,
+            user_data: gpointer,
+        ) -> gboolean {
+            unsafe { (*(user_data as *mut Vec<Thread>)).push(Thread::from_raw(thread)) };
+            1
+        }
+
+        let mut threads = Vec::new();
+        unsafe {
+            gum_sys::gum_thread_registry_enumerate_threads(
+                self.registry,
+                Some(on_thread_found),
+                &mut threads as *mut _ as gpointer,
+            )
+        };
+        threads
+    }
+
+    /// Calls `handler` with every thread currently running, then with every thread created,
+    /// terminated or renamed until the returned [`ThreadSubscription`] is dropped. No change is
+    /// missed in between.
+    ///
+    /// The handler may be called from any thread, such as to start following new threads
+    /// with `Stalker::follow`. A thread created or renamed by the handler itself is handled
+    /// once the running call returns.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let subscription = ThreadRegistry::obtain(&gum).subscribe(|event: ThreadEvent| match event {
+    ///     ThreadEvent::Added(thread) => println!("thread {} created", thread.id()),
+    ///     ThreadEvent::Removed(thread) => println!("thread {} terminated", thread.id()),
+    ///     ThreadEvent::Renamed { thread, .. } => println!("thread {:?} renamed", thread.name()),
+    /// });
+    /// ```
+    pub fn subscribe<H>(&self, handler: H) -> ThreadSubscription
+    where
+        H: ThreadHandler + Send + 'static,
+    {
+        unsafe extern "C" fn on_thread_found<H: ThreadHandler>(
+            thread: *const GumThreadDetails,
+            user_data: gpointer,
+        ) -> gboolean {
+            let event = ThreadEvent::Added(Thread::from_raw(thread));
+            unsafe { ThreadDispatcher::<H>::from_user_data(user_data) }
+                .dispatch(event, deliver::<H>);
+            1
+        }
+
+        unsafe extern "C" fn on_thread_added<H: ThreadHandler>(
+            _registry: *mut GumThreadRegistry,
+            thread: *const GumThreadDetails,
+            user_data: gpointer,
+        ) {
+            let event = ThreadEvent::Added(Thread::from_raw(thread));
+            unsafe { ThreadDispatcher::<H>::from_user_data(user_data) }
+                .dispatch(event, deliver::<H>);
+        }
+
+        unsafe extern "C" fn on_thread_removed<H: ThreadHandler>(
+            _registry: *mut GumThreadRegistry,
+            thread: *const GumThreadDetails,
+            user_data: gpointer,
+        ) {
+            let event = ThreadEvent::Removed(Thread::from_raw(thread));
+            unsafe { ThreadDispatcher::<H>::from_user_data(user_data) }
+                .dispatch(event, deliver::<H>);
+        }
+
+        unsafe extern "C" fn on_thread_renamed<H: ThreadHandler>(
+            _registry: *mut GumThreadRegistry,
+            thread: *const GumThreadDetails,
+            previous_name: *const c_char,
+            user_data: gpointer,
+        ) {
+            let previous_name = (!previous_name.is_null()).then(|| {
+                unsafe { CStr::from_ptr(previous_name) }
+                    .to_string_lossy()
+                    .to_string()
+            });
+            let event = ThreadEvent::Renamed {
+                thread: Thread::from_raw(thread),
+                previous_name,
+            };
+            unsafe { ThreadDispatcher::<H>::from_user_data(user_data) }
+                .dispatch(event, deliver::<H>);
+        }
+
+        fn deliver<H: ThreadHandler>(handler: &mut H, event: ThreadEvent) {
+            match event {
+                ThreadEvent::Added(thread) => handler.on_added(thread),
+                ThreadEvent::Removed(thread) => handler.on_removed(thread),
+                ThreadEvent::Renamed {
+                    thread,
+                    previous_name,
+                } => handler.on_renamed(thread, previous_name),
+            }
+        }
+
+        let registry = self.registry;
+        ThreadSubscription {
+            _subscription: Subscription::new(
+                &self._gum,
+                registry,
+                |registry| unsafe { gum_sys::gum_thread_registry_lock(registry) },
+                |registry| unsafe { gum_sys::gum_thread_registry_unlock(registry) },
+                ThreadDispatcher::new(handler),
+                |dispatcher| unsafe {
+                    gum_sys::gum_thread_registry_enumerate_threads(
+                        registry,
+                        Some(on_thread_found::<H>),
+                        dispatcher,
+                    );
+                },
+                [
+                    (c"thread-added", on_thread_added::<H> as _),
+                    (c"thread-removed", on_thread_removed::<H> as _),
+                    (c"thread-renamed", on_thread_renamed::<H> as _),
+                ],
+            ),
+        }
+    }
+}
+
+type ThreadDispatcher<H> = Dispatcher<H, ThreadEvent>;
+
+/// A handler subscribed with [`ThreadRegistry::subscribe`], unsubscribed and dropped when
+/// this is dropped.
+pub struct ThreadSubscription {
+    _subscription: Subscription<GumThreadRegistry, 3>,
+}
@@ -0,0 +1,42 @@
+//! Tests for hooks placed when a module is loaded.
+
+#![cfg(target_os = "linux")]
+
+use frida_gum::{Gum, NativePointer, interceptor::Interceptor};
+use std::ffi::{CString, c_char, c_int, c_void};
+
+// Part of glibc, and not loaded by the test binary.
+const LIBRARY: &str = "libresolv.so.2";
+const SYMBOL: &str = "__dn_count_labels";
+
+type CountLabels = unsafe extern "C" fn(*const c_char) -> c_int;
+
+unsafe extern "C" fn count_no_labels(_name: *const c_char) -> c_int {
+    -1
+}
+
+#[test]
+fn hook_follows_dlopen_and_dlclose() {
+    let gum = Gum::obtain();
+    let interceptor = Interceptor::obtain(&gum);
+
+    let hook = interceptor.hook_on_load(LIBRARY, SYMBOL, |interceptor, function| {
+        interceptor.replace_fast_scoped(function, NativePointer(count_no_labels as *mut c_void))
+    });
+    assert!(!hook.is_installed());
+
+    let name = CString::new(LIBRARY).unwrap();
+    let handle = unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW) };
+    assert!(!handle.is_null());
+    assert!(hook.is_installed());
+
+    let symbol = CString::new(SYMBOL).unwrap();
+    let count_labels = unsafe { libc::dlsym(handle, symbol.as_ptr()) };
+    assert!(!count_labels.is_null());
+    let count_labels: CountLabels = unsafe { std::mem::transmute(count_labels) };
+    assert_eq!(unsafe { count_labels(c"a.b.c".as_ptr()) }, -1);
+
+    assert_eq!(unsafe { libc::dlclose(handle) }, 0);
+    assert!(!hook.is_installed());
+    assert!(hook.error().is_none());
+}
@@ -0,0 +1,61 @@
+//! Tests for import slot hooks.
+//!
+//! The hooks replace slots of the test binary, which every thread calls through, so they
+//! live in their own test binary and run one at a time.
+
+#![cfg(target_os = "linux")]
+
+use frida_gum::{Gum, NativePointer, Process};
+use std::ffi::c_void;
+use std::sync::{Mutex, MutexGuard};
+
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial_guard() -> MutexGuard<'static, ()> {
+    SERIAL.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+extern "C" fn fake_getpid() -> libc::pid_t {
+    42
+}
+
+extern "C" fn fake_getppid() -> libc::pid_t {
+    43
+}
+
+#[test]
+fn import_slot_is_replaced_and_restored() {
+    let _serial = serial_guard();
+    let gum = Gum::obtain();
+    let main_module = Process::obtain(&gum).main_module();
+    let pid = unsafe { libc::getpid() };
+
+    let hook =
+        unsafe { main_module.hook_import("getpid", NativePointer(fake_getpid as *mut c_void)) }
+            .unwrap();
+    assert_eq!(unsafe { libc::getpid() }, 42);
+    drop(hook);
+    assert_eq!(unsafe { libc::getpid() }, pid);
+}
+
+#[test]
+fn hooks_in_the_same_page_are_restored_independently() {
+    let _serial = serial_guard();
+    let gum = Gum::obtain();
+    let main_module = Process::obtain(&gum).main_module();
+    let (pid, ppid) = unsafe { (libc::getpid(), libc::getppid()) };
+
+    let getpid =
+        unsafe { main_module.hook_import("getpid", NativePointer(fake_getpid as *mut c_void)) }
+            .unwrap();
+    let getppid =
+        unsafe { main_module.hook_import("getppid", NativePointer(fake_getppid as *mut c_void)) }
+            .unwrap();
+    assert_eq!(unsafe { (libc::getpid(), libc::getppid()) }, (42, 43));
+
+    // The page stays writable for the remaining hook.
+    drop(getpid);
+    assert_eq!(unsafe { (libc::getpid(), libc::getppid()) }, (pid, 43));
+    drop(getppid);
+    assert_eq!(unsafe { (libc::getpid(), libc::getppid()) }, (pid, ppid));
+}
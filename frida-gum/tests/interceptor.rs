@@ -0,0 +1,181 @@
+//! Tests for replacing functions with the Interceptor.
+//!
+//! Every test hooks its own function of the test binary, so that they can run in parallel.
+
+use frida_gum::{Gum, NativePointer, interceptor::Interceptor};
+use std::ffi::c_void;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static GUM: LazyLock<Gum> = LazyLock::new(Gum::obtain);
+
+type BinaryFn = unsafe extern "C" fn(i32, i32) -> i32;
+
+/// Calls `function` through an opaque pointer, so that the call isn't inlined and goes
+/// through the patched code.
+fn call(function: BinaryFn, a: i32, b: i32) -> i32 {
+    unsafe { black_box(function)(a, b) }
+}
+
+fn address(function: BinaryFn) -> NativePointer {
+    NativePointer(function as *mut c_void)
+}
+
+#[inline(never)]
+unsafe extern "C" fn add(a: i32, b: i32) -> i32 {
+    black_box(a).wrapping_add(black_box(b))
+}
+
+#[inline(never)]
+unsafe extern "C" fn subtract(a: i32, b: i32) -> i32 {
+    black_box(a).wrapping_sub(black_box(b))
+}
+
+#[inline(never)]
+unsafe extern "C" fn multiply(a: i32, b: i32) -> i32 {
+    black_box(a).wrapping_mul(black_box(b))
+}
+
+#[inline(never)]
+unsafe extern "C" fn maximum(a: i32, b: i32) -> i32 {
+    black_box(a).max(black_box(b))
+}
+
+#[inline(never)]
+unsafe extern "C" fn minimum(a: i32, b: i32) -> i32 {
+    black_box(a).min(black_box(b))
+}
+
+#[test]
+fn closure_replacement_calls_the_original() {
+    let mut interceptor = Interceptor::obtain(&GUM);
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let counter = calls.clone();
+    let replacement = interceptor
+        .replace_with::<BinaryFn, _>(address(add), move |original, (a, b)| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let sum = unsafe { original(a, b) };
+            sum * 10
+        })
+        .unwrap();
+    assert_eq!(call(add, 1, 2), 30);
+    assert_eq!(unsafe { (replacement.original())(1, 2) }, 3);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    unsafe { replacement.revert(&mut interceptor) };
+    assert_eq!(call(add, 1, 2), 3);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn dropping_a_hook_handle_reverts_the_replacement() {
+    let mut interceptor = Interceptor::obtain(&GUM);
+
+    let hook = interceptor
+        .replace_fast_scoped(address(maximum), address(minimum))
+        .unwrap();
+    assert_eq!(call(maximum, 1, 2), 1);
+    let original: BinaryFn = unsafe { std::mem::transmute(hook.original().unwrap().0) };
+    assert_eq!(call(original, 1, 2), 2);
+
+    drop(hook);
+    assert_eq!(call(maximum, 1, 2), 2);
+}
+
+#[test]
+fn hooks_placed_in_a_transaction_apply_once_it_ends() {
+    let mut interceptor = Interceptor::obtain(&GUM);
+
+    let replacement = {
+        let mut transaction = interceptor.transaction();
+        transaction
+            .replace_with::<BinaryFn, _>(address(multiply), |_, (a, b)| a + b)
+            .unwrap()
+    };
+    assert_eq!(call(multiply, 3, 4), 7);
+
+    unsafe { replacement.revert(&mut interceptor) };
+    assert_eq!(call(multiply, 3, 4), 12);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn errno_set_by_a_replacement_is_observed_by_the_caller() {
+    let mut interceptor = Interceptor::obtain(&GUM);
+    let replacement = interceptor
+        .replace_with::<BinaryFn, _>(address(subtract), |_, _| {
+            unsafe { *libc::__errno_location() = libc::ENOENT };
+            -1
+        })
+        .unwrap();
+
+    unsafe { *libc::__errno_location() = 0 };
+    assert_eq!(call(subtract, 2, 1), -1);
+    assert_eq!(
+        std::io::Error::last_os_error().raw_os_error(),
+        Some(libc::ENOENT)
+    );
+
+    unsafe { replacement.revert(&mut interceptor) };
+    assert_eq!(call(subtract, 2, 1), 1);
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+mod gum_hook {
+    use super::GUM;
+    use frida_gum::{Error, gum_hook, interceptor::Interceptor};
+    use std::ffi::{CStr, c_char, c_int};
+    use std::hint::black_box;
+
+    mod libc {
+        use std::ffi::{c_char, c_int};
+
+        unsafe extern "C" {
+            pub fn strverscmp(a: *const c_char, b: *const c_char) -> c_int;
+        }
+    }
+
+    gum_hook! {
+        fn strverscmp(a: *const c_char, b: *const c_char) -> c_int in "libc.so.6" {
+            let order = unsafe { original(a, b) };
+            -order
+        }
+    }
+
+    gum_hook! {
+        fn missing() in "libfrida-gum-missing.so" {}
+    }
+
+    fn compare(a: &CStr, b: &CStr) -> c_int {
+        let function: unsafe extern "C" fn(*const c_char, *const c_char) -> c_int =
+            libc::strverscmp;
+        unsafe { black_box(function)(a.as_ptr(), b.as_ptr()) }
+    }
+
+    #[test]
+    fn install_and_uninstall() {
+        let mut interceptor = Interceptor::obtain(&GUM);
+        assert!(strverscmp::original().is_none());
+
+        strverscmp::install(&GUM, &mut interceptor).unwrap();
+        assert!(strverscmp::original().is_some());
+        assert!(compare(c"1.2", c"1.10") > 0);
+
+        strverscmp::uninstall(&mut interceptor);
+        assert!(strverscmp::original().is_none());
+        assert!(compare(c"1.2", c"1.10") < 0);
+    }
+
+    #[test]
+    fn install_in_a_module_not_loaded() {
+        let mut interceptor = Interceptor::obtain(&GUM);
+        assert!(matches!(
+            missing::install(&GUM, &mut interceptor),
+            Err(Error::ModuleNotFound)
+        ));
+        assert!(missing::original().is_none());
+    }
+}
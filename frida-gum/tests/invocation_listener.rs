@@ -0,0 +1,190 @@
+//! Tests for attaching listeners with the Interceptor.
+//!
+//! Every test hooks its own function of the test binary, so that they can run in parallel.
+
+#![cfg(feature = "invocation-listener")]
+
+use frida_gum::{
+    Gum, NativePointer,
+    interceptor::{Interceptor, InvocationContext, InvocationListener},
+};
+use std::ffi::c_void;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+static GUM: LazyLock<Gum> = LazyLock::new(Gum::obtain);
+
+type BinaryFn = unsafe extern "C" fn(usize, usize) -> usize;
+
+/// Calls `function` through an opaque pointer, so that the call isn't inlined and goes
+/// through the patched code.
+fn call(function: BinaryFn, a: usize, b: usize) -> usize {
+    unsafe { black_box(function)(a, b) }
+}
+
+fn address(function: BinaryFn) -> NativePointer {
+    NativePointer(function as *mut c_void)
+}
+
+#[inline(never)]
+unsafe extern "C" fn multiply(a: usize, b: usize) -> usize {
+    black_box(a).wrapping_mul(black_box(b))
+}
+
+#[inline(never)]
+unsafe extern "C" fn add(a: usize, b: usize) -> usize {
+    black_box(a).wrapping_add(black_box(b))
+}
+
+#[inline(never)]
+unsafe extern "C" fn subtract(a: usize, b: usize) -> usize {
+    black_box(a).wrapping_sub(black_box(b))
+}
+
+type TenArgsFn = unsafe extern "C" fn(
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+) -> usize;
+
+/// Weighs each argument by its position, so that misplaced arguments change the result.
+#[allow(clippy::too_many_arguments)]
+#[inline(never)]
+unsafe extern "C" fn weighted_sum(
+    a0: usize,
+    a1: usize,
+    a2: usize,
+    a3: usize,
+    a4: usize,
+    a5: usize,
+    a6: usize,
+    a7: usize,
+    a8: usize,
+    a9: usize,
+) -> usize {
+    [a0, a1, a2, a3, a4, a5, a6, a7, a8, a9]
+        .into_iter()
+        .zip(1..)
+        .map(|(arg, weight)| black_box(arg) * weight)
+        .sum()
+}
+
+#[derive(Default)]
+struct CountingListener {
+    enters: usize,
+    leaves: usize,
+}
+
+impl InvocationListener for CountingListener {
+    fn on_enter(&mut self, _context: InvocationContext) {
+        self.enters += 1;
+    }
+
+    fn on_leave(&mut self, _context: InvocationContext) {
+        self.leaves += 1;
+    }
+}
+
+#[test]
+fn enter_data_is_handed_to_leave() {
+    let mut interceptor = Interceptor::obtain(&GUM);
+    let calls = Arc::new(Mutex::new(Vec::new()));
+
+    let recorded = calls.clone();
+    let listener = interceptor
+        .attach_with(
+            address(multiply),
+            |context| (context.arg(0), context.arg(1)),
+            move |context, (a, b)| {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((a, b, context.return_value()))
+            },
+        )
+        .unwrap();
+    assert_eq!(call(multiply, 6, 7), 42);
+    assert_eq!(call(multiply, 3, 5), 15);
+
+    interceptor.detach(listener);
+    assert_eq!(call(multiply, 2, 2), 4);
+    assert_eq!(*calls.lock().unwrap(), [(6, 7, 42), (3, 5, 15)]);
+}
+
+#[test]
+fn nested_ignore_guards_ignore_the_thread_until_the_outermost_is_dropped() {
+    let mut interceptor = Interceptor::obtain(&GUM);
+    let hits = Arc::new(AtomicUsize::new(0));
+
+    let counter = hits.clone();
+    let listener = interceptor
+        .attach_with(
+            address(add),
+            |_| (),
+            move |_, ()| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            },
+        )
+        .unwrap();
+
+    let outer = interceptor.ignore_current_thread();
+    let inner = interceptor.ignore_current_thread();
+    assert_eq!(call(add, 1, 2), 3);
+    drop(inner);
+    assert_eq!(call(add, 1, 2), 3);
+    assert_eq!(hits.load(Ordering::SeqCst), 0);
+
+    drop(outer);
+    assert_eq!(call(add, 1, 2), 3);
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+    interceptor.detach(listener);
+}
+
+#[test]
+fn dropping_a_scoped_listener_detaches_it() {
+    let mut interceptor = Interceptor::obtain(&GUM);
+    let mut listener = CountingListener::default();
+
+    let hook = interceptor
+        .attach_scoped(address(subtract), &mut listener)
+        .unwrap();
+    assert_eq!(call(subtract, 5, 3), 2);
+    drop(hook);
+    assert_eq!(call(subtract, 5, 3), 2);
+
+    assert_eq!((listener.enters, listener.leaves), (1, 1));
+}
+
+#[test]
+fn arguments_passed_on_the_stack_are_read_and_replaced() {
+    let mut interceptor = Interceptor::obtain(&GUM);
+    let args = Arc::new(Mutex::new(Vec::new()));
+
+    // Ten arguments don't fit in the argument registers of x86_64 (6) nor AArch64 (8).
+    let recorded = args.clone();
+    let listener = interceptor
+        .attach_with(
+            NativePointer(weighted_sum as TenArgsFn as *mut c_void),
+            move |context| {
+                *recorded.lock().unwrap() = (0..10).map(|n| context.arg(n)).collect();
+                context.set_arg(9, 100);
+            },
+            |_, ()| (),
+        )
+        .unwrap();
+    let result = unsafe { black_box(weighted_sum as TenArgsFn)(1, 2, 3, 4, 5, 6, 7, 8, 9, 10) };
+
+    interceptor.detach(listener);
+    assert_eq!(*args.lock().unwrap(), (1..=10).collect::<Vec<usize>>());
+    // The last argument, weighed by 10, was replaced by 100.
+    assert_eq!(result, 385 - 10 * 10 + 10 * 100);
+}
@@ -0,0 +1,28 @@
+//! Tests for module imports.
+
+#![cfg(target_os = "linux")]
+
+use frida_gum::{Gum, ImportType, Process};
+
+#[test]
+fn imports_are_enumerated() {
+    // Makes getpid an import of the test binary.
+    assert!(unsafe { libc::getpid() } > 0);
+    let gum = Gum::obtain();
+    let main_module = Process::obtain(&gum).main_module();
+
+    let imports = main_module.enumerate_imports();
+    let getpid = imports
+        .iter()
+        .find(|import| import.name == "getpid")
+        .unwrap();
+    assert!(matches!(getpid.typ, ImportType::Function));
+    assert_ne!(getpid.slot, 0);
+
+    let dependencies = main_module.enumerate_dependencies();
+    assert!(
+        dependencies
+            .iter()
+            .any(|dependency| dependency.name.starts_with("libc.so"))
+    );
+}
@@ -0,0 +1,51 @@
+//! Tests for module load and unload notifications.
+
+#![cfg(target_os = "linux")]
+
+use frida_gum::{Gum, ModuleEvent, ModuleRegistry};
+use std::sync::mpsc::{Receiver, channel};
+use std::time::Duration;
+
+// Part of glibc, and not loaded by the test binary.
+const LIBRARY: &str = "libresolv.so.2";
+
+/// Waits for the event about `LIBRARY`, skipping the other modules.
+fn next_event(rx: &Receiver<ModuleEvent>) -> ModuleEvent {
+    loop {
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let module = match &event {
+            ModuleEvent::Added(module) | ModuleEvent::Removed(module) => module,
+        };
+        if module.name() == LIBRARY {
+            return event;
+        }
+    }
+}
+
+/// Whether `LIBRARY` is in the registry.
+fn is_loaded(registry: &ModuleRegistry) -> bool {
+    registry
+        .modules()
+        .iter()
+        .any(|module| module.name() == LIBRARY)
+}
+
+#[test]
+fn dlopen_and_dlclose_are_notified() {
+    let gum = Gum::obtain();
+    let registry = ModuleRegistry::obtain(&gum);
+    assert!(!registry.modules().is_empty());
+    assert!(!is_loaded(&registry));
+
+    let (tx, rx) = channel();
+    let _subscription = registry.subscribe(tx);
+
+    let name = std::ffi::CString::new(LIBRARY).unwrap();
+    let handle = unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW) };
+    assert!(!handle.is_null());
+    assert!(matches!(next_event(&rx), ModuleEvent::Added(_)));
+    assert!(is_loaded(&registry));
+
+    assert_eq!(unsafe { libc::dlclose(handle) }, 0);
+    assert!(matches!(next_event(&rx), ModuleEvent::Removed(_)));
+}
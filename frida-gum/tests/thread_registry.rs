@@ -0,0 +1,46 @@
+//! Tests for thread creation and termination notifications.
+
+#![cfg(target_os = "linux")]
+
+use frida_gum::{Gum, ThreadEvent, ThreadRegistry};
+use std::sync::mpsc::{Receiver, channel};
+use std::time::Duration;
+
+/// Waits for the event about the thread `id`, skipping the other threads.
+fn next_event(rx: &Receiver<ThreadEvent>, id: usize) -> ThreadEvent {
+    loop {
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let thread = match &event {
+            ThreadEvent::Added(thread)
+            | ThreadEvent::Removed(thread)
+            | ThreadEvent::Renamed { thread, .. } => thread,
+        };
+        if thread.id() as usize == id {
+            return event;
+        }
+    }
+}
+
+#[test]
+fn thread_creation_and_termination_are_notified() {
+    let gum = Gum::obtain();
+    let registry = ThreadRegistry::obtain(&gum);
+    let current = unsafe { libc::gettid() } as usize;
+    assert!(
+        registry
+            .threads()
+            .iter()
+            .any(|thread| thread.id() as usize == current)
+    );
+
+    let (tx, rx) = channel();
+    let _subscription = registry.subscribe(tx);
+    // The running threads are notified first.
+    assert!(matches!(next_event(&rx, current), ThreadEvent::Added(_)));
+
+    let id = std::thread::spawn(|| unsafe { libc::gettid() } as usize)
+        .join()
+        .unwrap();
+    assert!(matches!(next_event(&rx, id), ThreadEvent::Added(_)));
+    assert!(matches!(next_event(&rx, id), ThreadEvent::Removed(_)));
+}
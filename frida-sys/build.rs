@@ -4,10 +4,12 @@
  * Licence: wxWindows Library Licence, Version 3.1
  */
 
+#[cfg(feature = "bindgen")]
 extern crate bindgen;
 
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 fn main() {
     println!(
@@ -16,15 +18,17 @@ fn main() {
     );
     let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap();
     let target_vendor = env::var("CARGO_CFG_TARGET_VENDOR").unwrap();
+    let version = include_str!("FRIDA_VERSION").trim();
 
-    #[cfg(feature = "auto-download")]
-    let include_dir = {
-        use frida_build::download_and_use_devkit;
-        download_and_use_devkit("core", include_str!("FRIDA_VERSION").trim())
-    };
+    let dynamic = cfg!(feature = "dynamic-loading");
 
-    #[cfg(not(feature = "auto-download"))]
-    println!("cargo:rustc-link-lib=frida-core");
+    // When loading frida-core at runtime nothing is linked, and the bindings come from the
+    // headers shipped with the crate.
+    let include_dir = if dynamic {
+        Some("include".to_string())
+    } else {
+        link_devkit(version)
+    };
 
     if target_os == "linux" {
         println!("cargo:rustc-link-lib=pthread");
@@ -42,18 +46,154 @@ fn main() {
         }
     }
 
-    let bindings = bindgen::Builder::default();
-
-    #[cfg(feature = "auto-download")]
-    let bindings = bindings.clang_arg(format!("-I{include_dir}"));
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("bindings.rs");
+    let pregenerated = pregenerated_bindings_path(dynamic);
+    let update = env::var_os("FRIDA_UPDATE_BINDINGS").is_some();
+    println!("cargo:rerun-if-env-changed=FRIDA_UPDATE_BINDINGS");
+    let header = include_dir
+        .as_ref()
+        .map(|include_dir| Path::new(include_dir).join("frida-core.h"));
 
-    #[cfg(not(feature = "auto-download"))]
-    let bindings = if std::env::var("DOCS_RS").is_ok() {
-        bindings.clang_arg("-Iinclude")
+    if cfg!(feature = "pregenerated-bindings") && !update {
+        println!("cargo:rerun-if-changed={}", pregenerated.display());
+        match read_pregenerated_bindings(&pregenerated, version, header.as_deref()) {
+            Ok(bindings) => fs::write(&out_path, bindings).unwrap(),
+            Err(reason) if cfg!(feature = "bindgen") => {
+                println!("cargo:warning={reason}, generating them with bindgen");
+                generate_bindings(include_dir.as_deref(), &out_path);
+            }
+            Err(reason) => panic!("{reason}; enable the `bindgen` feature to generate them"),
+        }
     } else {
-        bindings
+        generate_bindings(include_dir.as_deref(), &out_path);
+        if update {
+            let header = header.expect(
+                "FRIDA_UPDATE_BINDINGS needs to know the devkit headers, enable the \
+                 `auto-download` or `dynamic-loading` feature",
+            );
+            write_pregenerated_bindings(&out_path, &pregenerated, version, &header);
+        }
+    }
+
+    if target_os == "windows" {
+        for lib in [
+            "dnsapi", "iphlpapi", "psapi", "winmm", "ws2_32", "advapi32", "crypt32", "gdi32",
+            "kernel32", "ole32", "secur32", "shell32", "shlwapi", "user32", "setupapi",
+        ] {
+            println!("cargo:rustc-link-lib=dylib={lib}");
+        }
+    }
+}
+
+#[cfg(feature = "auto-download")]
+fn link_devkit(version: &str) -> Option<String> {
+    Some(frida_build::download_and_use_devkit("core", version))
+}
+
+#[cfg(not(feature = "auto-download"))]
+fn link_devkit(_version: &str) -> Option<String> {
+    println!("cargo:rustc-link-lib=frida-core");
+    env::var("DOCS_RS").is_ok().then(|| "include".to_string())
+}
+
+/// Checked-in bindings for the current target, see the `pregenerated-bindings` feature.
+fn pregenerated_bindings_path(dynamic: bool) -> PathBuf {
+    let target = env::var("TARGET").unwrap();
+    let suffix = if dynamic { "-dynamic" } else { "" };
+    PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap())
+        .join("bindings")
+        .join(format!("{target}{suffix}.rs"))
+}
+
+/// Reads checked-in bindings, which start with a `// Frida <version>` line, followed by a
+/// `// <header> fnv1a64 <checksum>` line identifying the devkit header they were generated
+/// from.
+///
+/// When the header of the devkit in use is known, the bindings must have been generated from
+/// it: the name of a devkit only tells its version, not whether it was built with other
+/// options or patched.
+fn read_pregenerated_bindings(
+    path: &Path,
+    version: &str,
+    header: Option<&Path>,
+) -> Result<String, String> {
+    let bindings = fs::read_to_string(path).map_err(|_| {
+        format!(
+            "no pregenerated bindings at {}; generate them with the `bindgen` feature and \
+             FRIDA_UPDATE_BINDINGS=1",
+            path.display()
+        )
+    })?;
+    let mut lines = bindings.lines();
+    let found = lines
+        .next()
+        .and_then(|line| line.strip_prefix("// Frida "))
+        .unwrap_or("an unknown version");
+    if found != version {
+        return Err(format!(
+            "pregenerated bindings at {} are for Frida {found}, but FRIDA_VERSION is {version}",
+            path.display()
+        ));
+    }
+
+    let recorded = lines.next().and_then(|line| line.strip_prefix("// "));
+    match header {
+        Some(header) => {
+            let expected = header_stamp(header)?;
+            if recorded != Some(expected.as_str()) {
+                return Err(format!(
+                    "pregenerated bindings at {} were not generated from {}",
+                    path.display(),
+                    header.display()
+                ));
+            }
+        }
+        None => println!(
+            "cargo:warning=cannot check the pregenerated bindings at {} against the linked \
+             devkit, whose headers are unknown; they must be for Frida {version}",
+            path.display()
+        ),
+    }
+    Ok(bindings)
+}
+
+fn write_pregenerated_bindings(generated: &Path, path: &Path, version: &str, header: &Path) {
+    let bindings = fs::read_to_string(generated).unwrap();
+    let stamp = header_stamp(header).unwrap();
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, format!("// Frida {version}\n// {stamp}\n{bindings}")).unwrap();
+    println!("cargo:warning=updated {}", path.display());
+}
+
+/// Identifies a header by its name and FNV-1a checksum, which is stable across toolchains
+/// unlike `DefaultHasher`.
+fn header_stamp(header: &Path) -> Result<String, String> {
+    let contents =
+        fs::read(header).map_err(|e| format!("cannot read {}: {e}", header.display()))?;
+    let checksum = contents
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    let name = header.file_name().unwrap().to_string_lossy();
+    Ok(format!("{name} fnv1a64 {checksum:016x}"))
+}
+
+#[cfg(feature = "bindgen")]
+fn generate_bindings(include_dir: Option<&str>, out_path: &Path) {
+    let bindings = bindgen::Builder::default();
+
+    let bindings = match include_dir {
+        Some(include_dir) => bindings.clang_arg(format!("-I{include_dir}")),
+        None => bindings,
     };
 
+    #[cfg(feature = "dynamic-loading")]
+    let bindings = bindings
+        .dynamic_library_name(LIBRARY)
+        .dynamic_link_require_all(false)
+        .wrap_unsafe_ops(true);
+
     let bindings = bindings
         .formatter(bindgen::Formatter::Prettyplease)
         .header_contents("core.h", "#include \"frida-core.h\"")
@@ -63,17 +203,21 @@ fn main() {
         .generate()
         .unwrap();
 
-    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
-    bindings
-        .write_to_file(out_path.join("bindings.rs"))
-        .unwrap();
+    #[allow(unused_mut)]
+    let mut bindings = bindings.to_string();
+    #[cfg(feature = "dynamic-loading")]
+    bindings.push_str(
+        &frida_build::dynamic_shims(&bindings, LIBRARY, "crate::dynamic::library()")
+            .expect("failed to parse the bindings generated for the library"),
+    );
+    fs::write(out_path, bindings).unwrap();
+}
 
-    if target_os == "windows" {
-        for lib in [
-            "dnsapi", "iphlpapi", "psapi", "winmm", "ws2_32", "advapi32", "crypt32", "gdi32",
-            "kernel32", "ole32", "secur32", "shell32", "shlwapi", "user32", "setupapi",
-        ] {
-            println!("cargo:rustc-link-lib=dylib={lib}");
-        }
-    }
+#[cfg(not(feature = "bindgen"))]
+fn generate_bindings(_include_dir: Option<&str>, _out_path: &Path) {
+    panic!("frida-sys needs either the `bindgen` or the `pregenerated-bindings` feature");
 }
+
+/// Struct generated by bindgen holding the functions of the library loaded at runtime.
+#[cfg(all(feature = "bindgen", feature = "dynamic-loading"))]
+const LIBRARY: &str = "FridaCoreLibrary";
@@ -0,0 +1,91 @@
+/*
+ * Copyright © 2020-2022 Keegan Saunders
+ *
+ * Licence: wxWindows Library Licence, Version 3.1
+ */
+
+use crate::bindings::FridaCoreLibrary;
+use std::ffi::{OsStr, c_void};
+use std::fmt;
+use std::sync::OnceLock;
+
+static LIBRARY: OnceLock<Library> = OnceLock::new();
+
+struct Library(FridaCoreLibrary);
+
+// The library only holds the addresses of its functions and variables, which never change.
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+/// Errors raised by [`load`].
+#[derive(Debug)]
+pub enum LoadError {
+    /// The shared library could not be opened.
+    Library(libloading::Error),
+    /// The shared library doesn't export these required symbols.
+    MissingSymbols(Vec<String>),
+    /// A library was already loaded.
+    AlreadyLoaded,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Library(err) => write!(f, "failed to load frida-core: {err}"),
+            LoadError::MissingSymbols(symbols) => write!(
+                f,
+                "the frida-core library lacks required symbols: {}",
+                symbols.join(", ")
+            ),
+            LoadError::AlreadyLoaded => write!(f, "frida-core was already loaded"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Library(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Loads frida-core from the shared library at `path`, resolving every function of the
+/// bindings.
+///
+/// Fails if one of the `required` symbols isn't exported by the library. Other missing
+/// functions panic when called. This must succeed before calling any other function, and
+/// can only succeed once per process.
+pub fn load(path: impl AsRef<OsStr>, required: &[&str]) -> Result<(), LoadError> {
+    if is_loaded() {
+        return Err(LoadError::AlreadyLoaded);
+    }
+
+    let library = unsafe { libloading::Library::new(path.as_ref()) }.map_err(LoadError::Library)?;
+    let missing: Vec<String> = required
+        .iter()
+        .filter(|symbol| unsafe { library.get::<*const c_void>(symbol.as_bytes()) }.is_err())
+        .map(|symbol| symbol.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(LoadError::MissingSymbols(missing));
+    }
+
+    let library = unsafe { FridaCoreLibrary::from_library(library) }.map_err(LoadError::Library)?;
+    LIBRARY
+        .set(Library(library))
+        .map_err(|_| LoadError::AlreadyLoaded)
+}
+
+/// Whether frida-core was loaded with [`load`].
+pub fn is_loaded() -> bool {
+    LIBRARY.get().is_some()
+}
+
+pub(crate) fn library() -> &'static FridaCoreLibrary {
+    &LIBRARY
+        .get()
+        .expect("frida-core must be loaded with frida_sys::load before use")
+        .0
+}
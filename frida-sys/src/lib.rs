@@ -16,26 +16,66 @@ mod bindings {
 
 pub use bindings::*;
 
+#[cfg(feature = "dynamic-loading")]
+mod dynamic;
+#[cfg(feature = "dynamic-loading")]
+pub use dynamic::{LoadError, is_loaded, load};
+
 #[cfg(not(any(target_vendor = "apple", target_os = "windows")))]
 pub use crate::{
     _frida_g_bytes_get_data as g_bytes_get_data, _frida_g_bytes_new as g_bytes_new,
-    _frida_g_bytes_unref as g_bytes_unref, _frida_g_clear_object as g_clear_object,
+    _frida_g_bytes_unref as g_bytes_unref, _frida_g_cancellable_cancel as g_cancellable_cancel,
+    _frida_g_cancellable_is_cancelled as g_cancellable_is_cancelled,
+    _frida_g_cancellable_new as g_cancellable_new, _frida_g_clear_object as g_clear_object,
+    _frida_g_error_free as g_error_free, _frida_g_free as g_free,
+    _frida_g_hash_table_insert as g_hash_table_insert,
     _frida_g_hash_table_iter_init as g_hash_table_iter_init,
     _frida_g_hash_table_iter_next as g_hash_table_iter_next,
-    _frida_g_hash_table_size as g_hash_table_size, _frida_g_idle_source_new as g_idle_source_new,
+    _frida_g_hash_table_size as g_hash_table_size, _frida_g_hash_table_unref as g_hash_table_unref,
+    _frida_g_idle_source_new as g_idle_source_new, _frida_g_object_ref as g_object_ref,
+    _frida_g_object_unref as g_object_unref, _frida_g_quark_to_string as g_quark_to_string,
     _frida_g_signal_connect_data as g_signal_connect_data,
     _frida_g_source_attach as g_source_attach,
     _frida_g_source_set_callback as g_source_set_callback, _frida_g_source_unref as g_source_unref,
+    _frida_g_strdup as g_strdup, _frida_g_timeout_source_new as g_timeout_source_new,
+    _frida_g_variant_builder_add_value as g_variant_builder_add_value,
+    _frida_g_variant_builder_end as g_variant_builder_end,
+    _frida_g_variant_builder_new as g_variant_builder_new,
+    _frida_g_variant_builder_unref as g_variant_builder_unref,
     _frida_g_variant_get_boolean as g_variant_get_boolean,
     _frida_g_variant_get_byte as g_variant_get_byte,
+    _frida_g_variant_get_child_value as g_variant_get_child_value,
+    _frida_g_variant_get_double as g_variant_get_double,
+    _frida_g_variant_get_fixed_array as g_variant_get_fixed_array,
     _frida_g_variant_get_int16 as g_variant_get_int16,
     _frida_g_variant_get_int32 as g_variant_get_int32,
     _frida_g_variant_get_int64 as g_variant_get_int64,
     _frida_g_variant_get_string as g_variant_get_string,
+    _frida_g_variant_get_strv as g_variant_get_strv,
     _frida_g_variant_get_type_string as g_variant_get_type_string,
     _frida_g_variant_get_uint16 as g_variant_get_uint16,
     _frida_g_variant_get_uint32 as g_variant_get_uint32,
     _frida_g_variant_get_uint64 as g_variant_get_uint64,
+    _frida_g_variant_get_variant as g_variant_get_variant,
     _frida_g_variant_iter_init as g_variant_iter_init,
-    _frida_g_variant_iter_loop as g_variant_iter_loop,
+    _frida_g_variant_iter_next_value as g_variant_iter_next_value,
+    _frida_g_variant_n_children as g_variant_n_children,
+    _frida_g_variant_new_boolean as g_variant_new_boolean,
+    _frida_g_variant_new_dict_entry as g_variant_new_dict_entry,
+    _frida_g_variant_new_double as g_variant_new_double,
+    _frida_g_variant_new_fixed_array as g_variant_new_fixed_array,
+    _frida_g_variant_new_int64 as g_variant_new_int64,
+    _frida_g_variant_new_string as g_variant_new_string,
+    _frida_g_variant_new_strv as g_variant_new_strv,
+    _frida_g_variant_new_tuple as g_variant_new_tuple,
+    _frida_g_variant_new_variant as g_variant_new_variant,
+    _frida_g_variant_ref_sink as g_variant_ref_sink, _frida_g_variant_unref as g_variant_unref,
 };
+
+// Variadic functions can't be called through the dynamically loaded library.
+#[cfg(not(any(
+    target_vendor = "apple",
+    target_os = "windows",
+    feature = "dynamic-loading"
+)))]
+pub use crate::_frida_g_variant_iter_loop as g_variant_iter_loop;
@@ -0,0 +1,12 @@
+/*
+ * Copyright © 2020-2022 Keegan Saunders
+ *
+ * Licence: wxWindows Library Licence, Version 3.1
+ */
+
+fn main() {
+    // The frida-core functions called by this crate, checked by `Frida::load_library`.
+    #[cfg(feature = "dynamic-loading")]
+    frida_build::write_required_symbols(std::path::Path::new("src"), &["frida_", "g_"], &[])
+        .expect("failed to list the frida-core functions used by the crate");
+}
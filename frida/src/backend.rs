@@ -0,0 +1,269 @@
+use serde_json::Value;
+use std::ffi::CString;
+
+use crate::{
+    Device, DeviceType, Error, Result, Script, ScriptHandler, ScriptOption, ScriptRuntime, Session,
+    SourceMap, SpawnOptions, SpawnStdio, Variant,
+};
+
+/// Options to spawn a program with [`DeviceBackend::spawn`].
+///
+/// Unlike [`SpawnOptions`], they are plain data, so backends other than [`Device`] don't
+/// need frida-core. Unset options keep the defaults of the backend.
+#[derive(Clone, Debug, Default)]
+pub struct SpawnConfig {
+    /// The argv vector, including the program name.
+    pub argv: Option<Vec<String>>,
+    /// Environment variables added to the ones of the backend.
+    pub env: Option<Vec<(String, String)>>,
+    /// The whole environment of the program.
+    pub envp: Option<Vec<(String, String)>>,
+    /// The working directory.
+    pub cwd: Option<String>,
+    /// The Standard I/O handling.
+    pub stdio: Option<SpawnStdio>,
+    /// Auxiliary, backend-specific options.
+    pub aux: Vec<(String, Variant)>,
+}
+
+/// Options to create a script with [`SessionBackend::create_script`].
+///
+/// Unlike [`ScriptOption`], they are plain data, so backends other than [`Session`] don't
+/// need frida-core.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptConfig {
+    /// The name of the script.
+    pub name: Option<String>,
+    /// The JavaScript runtime running the script.
+    pub runtime: Option<ScriptRuntime>,
+    /// The source map resolving the positions of the script's errors.
+    pub source_map: Option<SourceMap>,
+}
+
+impl TryFrom<&SpawnConfig> for SpawnOptions {
+    type Error = Error;
+
+    /// Fails with [`Error::CStringFailed`] if a string contains a NUL byte, or an auxiliary
+    /// option has no GVariant representation.
+    fn try_from(config: &SpawnConfig) -> Result<Self> {
+        fn strings(pairs: &Option<Vec<(String, String)>>) -> impl Iterator<Item = &String> {
+            pairs.iter().flatten().flat_map(|(key, value)| [key, value])
+        }
+        let has_nul = config
+            .argv
+            .iter()
+            .flatten()
+            .chain(strings(&config.env))
+            .chain(strings(&config.envp))
+            .chain(&config.cwd)
+            .any(|string| string.contains('\0'));
+        if has_nul {
+            return Err(Error::CStringFailed);
+        }
+
+        let mut options = SpawnOptions::new();
+        if let Some(argv) = &config.argv {
+            options = options.argv(argv);
+        }
+        if let Some(env) = &config.env {
+            options = options.env(env.iter().map(|(key, value)| (key, value)));
+        }
+        if let Some(envp) = &config.envp {
+            options = options.envp(envp.iter().map(|(key, value)| (key, value)));
+        }
+        if let Some(cwd) = &config.cwd {
+            options = options.cwd(CString::new(cwd.as_str()).unwrap());
+        }
+        if let Some(stdio) = config.stdio {
+            options = options.stdio(stdio);
+        }
+        for (key, value) in &config.aux {
+            options = options.try_aux(key, value).ok_or(Error::CStringFailed)?;
+        }
+        Ok(options)
+    }
+}
+
+impl From<&ScriptConfig> for ScriptOption {
+    fn from(config: &ScriptConfig) -> Self {
+        let mut options = ScriptOption::new();
+        if let Some(name) = &config.name {
+            options = options.set_name(name);
+        }
+        if let Some(runtime) = config.runtime {
+            options = options.set_runtime(runtime);
+        }
+        if let Some(source_map) = &config.source_map {
+            options = options.set_source_map(source_map.clone());
+        }
+        options
+    }
+}
+
+/// Operations on a device.
+///
+/// Implemented by [`Device`] and by the in-memory [`MockDevice`](crate::mock::MockDevice),
+/// so that host-side logic written against this trait can be unit-tested without
+/// attaching to a real process.
+///
+/// # Example
+///
+/// ```
+/// use frida::mock::MockDevice;
+/// use frida::{DeviceBackend, ScriptBackend, ScriptConfig, SessionBackend};
+/// use serde_json::json;
+///
+/// fn double<D: DeviceBackend>(device: &D, pid: u32, n: i64) -> frida::Result<i64> {
+///     let session = device.attach(pid)?;
+///     let script = session.create_script(
+///         "rpc.exports = { double: n => n * 2 };",
+///         &ScriptConfig::default(),
+///     )?;
+///     script.load()?;
+///     let result = script.call("double", Some(json!([n])))?;
+///     Ok(result.and_then(|v| v.as_i64()).unwrap_or_default())
+/// }
+///
+/// let device = MockDevice::new("mock", "Mock Device");
+/// device.add_process(1234, "target");
+/// device.on_create_script(|script| {
+///     script.export("double", |args| Ok(json!(args[0].as_i64().unwrap() * 2)));
+/// });
+///
+/// assert_eq!(double(&device, 1234, 21).unwrap(), 42);
+/// ```
+pub trait DeviceBackend {
+    /// Session type returned by [`attach`](DeviceBackend::attach).
+    type Session: SessionBackend;
+
+    /// Returns the device's id.
+    fn get_id(&self) -> &str;
+
+    /// Returns the device's name.
+    fn get_name(&self) -> &str;
+
+    /// Returns the device's type.
+    fn get_type(&self) -> DeviceType;
+
+    /// Attaches to the process `pid`.
+    fn attach(&self, pid: u32) -> Result<Self::Session>;
+
+    /// Spawns `program` suspended, returning its PID.
+    fn spawn(&self, program: &str, options: &SpawnConfig) -> Result<u32>;
+
+    /// Resumes the process `pid`.
+    fn resume(&self, pid: u32) -> Result<()>;
+
+    /// Kills the process `pid`.
+    fn kill(&self, pid: u32) -> Result<()>;
+}
+
+/// Operations on a session, see [`DeviceBackend`].
+pub trait SessionBackend {
+    /// Script type returned by [`create_script`](SessionBackend::create_script).
+    type Script: ScriptBackend;
+
+    /// Returns if the session is detached or not.
+    fn is_detached(&self) -> bool;
+
+    /// Creates a script from JavaScript `source`.
+    fn create_script(&self, source: &str, options: &ScriptConfig) -> Result<Self::Script>;
+
+    /// Detaches the session.
+    fn detach(&self) -> Result<()>;
+}
+
+/// Operations on a script, see [`DeviceBackend`].
+pub trait ScriptBackend {
+    /// Loads the script.
+    fn load(&self) -> Result<()>;
+
+    /// Unloads the script.
+    fn unload(&self) -> Result<()>;
+
+    /// Sets the handler of the messages sent by the script.
+    fn handle_message<I: ScriptHandler + Send + 'static>(&self, handler: I) -> Result<()>;
+
+    /// Posts a JSON-encoded message to the script with optional binary data.
+    fn post(&self, message: &str, data: Option<&[u8]>) -> Result<()>;
+
+    /// Lists the functions exported through `rpc.exports`.
+    fn list_exports(&self) -> Result<Vec<String>>;
+
+    /// Calls the function `function_name` exported through `rpc.exports`.
+    fn call(&self, function_name: &str, args: Option<Value>) -> Result<Option<Value>>;
+}
+
+impl DeviceBackend for Device {
+    type Session = Session;
+
+    fn get_id(&self) -> &str {
+        Device::get_id(self)
+    }
+
+    fn get_name(&self) -> &str {
+        Device::get_name(self)
+    }
+
+    fn get_type(&self) -> DeviceType {
+        Device::get_type(self)
+    }
+
+    fn attach(&self, pid: u32) -> Result<Session> {
+        Device::attach(self, pid)
+    }
+
+    fn spawn(&self, program: &str, options: &SpawnConfig) -> Result<u32> {
+        Device::spawn(self, program, &SpawnOptions::try_from(options)?)
+    }
+
+    fn resume(&self, pid: u32) -> Result<()> {
+        Device::resume(self, pid)
+    }
+
+    fn kill(&self, pid: u32) -> Result<()> {
+        Device::kill(self, pid)
+    }
+}
+
+impl SessionBackend for Session {
+    type Script = Script;
+
+    fn is_detached(&self) -> bool {
+        Session::is_detached(self)
+    }
+
+    fn create_script(&self, source: &str, options: &ScriptConfig) -> Result<Script> {
+        Session::create_script(self, source, &mut ScriptOption::from(options))
+    }
+
+    fn detach(&self) -> Result<()> {
+        Session::detach(self)
+    }
+}
+
+impl ScriptBackend for Script {
+    fn load(&self) -> Result<()> {
+        Script::load(self)
+    }
+
+    fn unload(&self) -> Result<()> {
+        Script::unload(self)
+    }
+
+    fn handle_message<I: ScriptHandler + Send + 'static>(&self, handler: I) -> Result<()> {
+        Script::handle_message(self, handler)
+    }
+
+    fn post(&self, message: &str, data: Option<&[u8]>) -> Result<()> {
+        Script::post(self, message, data)
+    }
+
+    fn list_exports(&self) -> Result<Vec<String>> {
+        Script::list_exports(self)
+    }
+
+    fn call(&self, function_name: &str, args: Option<Value>) -> Result<Option<Value>> {
+        self.exports.call(function_name, args)
+    }
+}
@@ -0,0 +1,104 @@
+use frida_sys::GCancellable;
+use std::time::Duration;
+
+use crate::Frida;
+
+/// Handle allowing a blocking Frida operation to be cancelled from another thread.
+///
+/// Pass it to one of the `*_with_cancellable` functions and call [`Cancellable::cancel`]
+/// from anywhere to abort the operation, which then fails with
+/// [`Error::Cancelled`](crate::Error::Cancelled). Clones share the same underlying
+/// `GCancellable`.
+///
+/// # Example
+/// ```no_run
+///# use std::time::Duration;
+///# let frida = frida::Frida::obtain();
+///# let device_manager = frida::DeviceManager::obtain(&frida);
+/// let cancellable = frida::Cancellable::with_timeout(&frida, Duration::from_secs(5));
+/// match device_manager.get_device_by_id_with_cancellable("<some id>", Some(&cancellable)) {
+///     Err(frida::Error::Cancelled) => println!("no such device after 5 seconds"),
+///     result => println!("{}", result.is_ok()),
+/// }
+/// ```
+pub struct Cancellable {
+    cancellable_ptr: *mut GCancellable,
+}
+
+// GCancellable is thread-safe: it exists to be triggered from other threads.
+unsafe impl Send for Cancellable {}
+unsafe impl Sync for Cancellable {}
+
+impl Cancellable {
+    /// Create a new cancellable.
+    pub fn new() -> Self {
+        Self {
+            cancellable_ptr: unsafe { frida_sys::g_cancellable_new() },
+        }
+    }
+
+    /// Create a new cancellable that cancels itself once `timeout` has elapsed.
+    ///
+    /// The timer runs on the Frida main context.
+    pub fn with_timeout(_frida: &Frida, timeout: Duration) -> Self {
+        unsafe extern "C" fn on_timeout(user_data: frida_sys::gpointer) -> frida_sys::gboolean {
+            unsafe {
+                frida_sys::g_cancellable_cancel(user_data as *mut GCancellable);
+                frida_sys::G_SOURCE_REMOVE as frida_sys::gboolean
+            }
+        }
+        unsafe extern "C" fn release(user_data: frida_sys::gpointer) {
+            unsafe { frida_sys::g_object_unref(user_data) }
+        }
+
+        let cancellable = Self::new();
+        unsafe {
+            let millis = timeout.as_millis().min(frida_sys::guint::MAX as u128);
+            let source = frida_sys::g_timeout_source_new(millis as frida_sys::guint);
+            frida_sys::g_source_set_callback(
+                source,
+                Some(on_timeout),
+                frida_sys::g_object_ref(cancellable.cancellable_ptr as _),
+                Some(release),
+            );
+            frida_sys::g_source_attach(source, frida_sys::frida_get_main_context());
+            frida_sys::g_source_unref(source);
+        }
+        cancellable
+    }
+
+    /// Cancel the operations using this cancellable.
+    pub fn cancel(&self) {
+        unsafe { frida_sys::g_cancellable_cancel(self.cancellable_ptr) }
+    }
+
+    /// Returns whether [`Cancellable::cancel`] was called or the timeout elapsed.
+    pub fn is_cancelled(&self) -> bool {
+        unsafe { frida_sys::g_cancellable_is_cancelled(self.cancellable_ptr) != 0 }
+    }
+}
+
+impl Default for Cancellable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for Cancellable {
+    fn clone(&self) -> Self {
+        Self {
+            cancellable_ptr: unsafe { frida_sys::g_object_ref(self.cancellable_ptr as _) as _ },
+        }
+    }
+}
+
+impl Drop for Cancellable {
+    fn drop(&mut self) {
+        unsafe { frida_sys::g_object_unref(self.cancellable_ptr as _) }
+    }
+}
+
+/// Returns the raw `GCancellable` to pass to a `*_sync` call, or null for none.
+pub(crate) fn cancellable_ptr(cancellable: Option<&Cancellable>) -> *mut GCancellable {
+    cancellable.map_or(std::ptr::null_mut(), |c| c.cancellable_ptr)
+}
@@ -0,0 +1,155 @@
+use frida_sys::{_FridaCompiler, FridaBuildOptions, FridaCompilerOptions};
+use std::ffi::{CStr, CString};
+
+use crate::cancellable::{Cancellable, cancellable_ptr};
+use crate::device_manager::DeviceManager;
+use crate::error::check_gerror;
+use crate::{Error, Result};
+
+/// Bundles a TypeScript or JavaScript agent into a single script, like the
+/// `frida-compile` tool.
+///
+/// The resulting bundle can be passed to [`Session::create_script`](crate::Session::create_script).
+pub struct Compiler {
+    compiler_ptr: *mut _FridaCompiler,
+    // The compiler relies on the manager, which it doesn't keep alive itself.
+    _manager: DeviceManager,
+}
+
+// frida-core marshals every compiler operation onto its own main context.
+unsafe impl Send for Compiler {}
+unsafe impl Sync for Compiler {}
+
+impl Compiler {
+    /// Creates a compiler using `manager`.
+    pub fn new(manager: &DeviceManager) -> Self {
+        Compiler {
+            compiler_ptr: unsafe { frida_sys::frida_compiler_new(manager.as_ptr()) },
+            _manager: manager.clone(),
+        }
+    }
+
+    /// Bundles the agent whose entrypoint is `entrypoint`, returning the bundle.
+    ///
+    /// A relative `entrypoint` is resolved against the project root of `options`.
+    pub fn build(&self, entrypoint: &str, options: &BuildOptions) -> Result<String> {
+        self.build_with_cancellable(entrypoint, options, None)
+    }
+
+    /// Bundles the agent whose entrypoint is `entrypoint`, giving up once `cancellable` is
+    /// cancelled.
+    pub fn build_with_cancellable(
+        &self,
+        entrypoint: &str,
+        options: &BuildOptions,
+        cancellable: Option<&Cancellable>,
+    ) -> Result<String> {
+        let entrypoint = CString::new(entrypoint).map_err(|_| Error::CStringFailed)?;
+        let mut error: *mut frida_sys::GError = std::ptr::null_mut();
+        let bundle = unsafe {
+            frida_sys::frida_compiler_build_sync(
+                self.compiler_ptr,
+                entrypoint.as_ptr(),
+                options.options_ptr,
+                cancellable_ptr(cancellable),
+                &mut error,
+            )
+        };
+        check_gerror(error, Error::CompilationFailed)?;
+
+        let result = unsafe { CStr::from_ptr(bundle).to_string_lossy().into_owned() };
+        unsafe { frida_sys::g_free(bundle as _) };
+        Ok(result)
+    }
+}
+
+impl Drop for Compiler {
+    fn drop(&mut self) {
+        unsafe { frida_sys::frida_unref(self.compiler_ptr as _) }
+    }
+}
+
+/// Whether a bundle embeds source maps.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum SourceMaps {
+    /// Embed the source maps in the bundle.
+    Included,
+    /// Leave source maps out.
+    Omitted,
+}
+
+/// Compression applied to a bundle.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum JsCompression {
+    /// Leave the code as is.
+    None,
+    /// Minify the code with terser.
+    Terser,
+}
+
+/// Options of [`Compiler::build`].
+pub struct BuildOptions {
+    options_ptr: *mut FridaBuildOptions,
+}
+
+// The options are only mutated through the consuming builder methods.
+unsafe impl Send for BuildOptions {}
+unsafe impl Sync for BuildOptions {}
+
+impl BuildOptions {
+    /// Creates the default options: the project root is the current directory, source
+    /// maps are included and the code isn't compressed.
+    pub fn new() -> Self {
+        Self {
+            options_ptr: unsafe { frida_sys::frida_build_options_new() },
+        }
+    }
+
+    /// Sets the directory containing the agent's `package.json`.
+    pub fn project_root(self, project_root: &str) -> Self {
+        let project_root = CString::new(project_root).unwrap();
+        unsafe {
+            frida_sys::frida_compiler_options_set_project_root(
+                self.compiler_options(),
+                project_root.as_ptr(),
+            )
+        }
+        self
+    }
+
+    /// Sets whether the bundle embeds source maps.
+    pub fn source_maps(self, source_maps: SourceMaps) -> Self {
+        let value = match source_maps {
+            SourceMaps::Included => frida_sys::FridaSourceMaps_FRIDA_SOURCE_MAPS_INCLUDED,
+            SourceMaps::Omitted => frida_sys::FridaSourceMaps_FRIDA_SOURCE_MAPS_OMITTED,
+        };
+        unsafe { frida_sys::frida_compiler_options_set_source_maps(self.compiler_options(), value) }
+        self
+    }
+
+    /// Sets the compression applied to the bundle.
+    pub fn compression(self, compression: JsCompression) -> Self {
+        let value = match compression {
+            JsCompression::None => frida_sys::FridaJsCompression_FRIDA_JS_COMPRESSION_NONE,
+            JsCompression::Terser => frida_sys::FridaJsCompression_FRIDA_JS_COMPRESSION_TERSER,
+        };
+        unsafe { frida_sys::frida_compiler_options_set_compression(self.compiler_options(), value) }
+        self
+    }
+
+    fn compiler_options(&self) -> *mut FridaCompilerOptions {
+        self.options_ptr as _
+    }
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for BuildOptions {
+    fn drop(&mut self) {
+        unsafe { frida_sys::g_object_unref(self.options_ptr as _) }
+    }
+}
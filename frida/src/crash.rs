@@ -0,0 +1,45 @@
+use frida_sys::_FridaCrash;
+use std::collections::HashMap;
+use std::ffi::CStr;
+
+use crate::variant::{Variant, variant_map_from_hash_table};
+
+/// Details about a process crash, as collected by Frida's crash reporter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Crash {
+    /// PID of the crashed process.
+    pub pid: u32,
+    /// Name of the crashed process.
+    pub process_name: String,
+    /// Short human-readable summary of the crash.
+    pub summary: String,
+    /// Full crash report.
+    pub report: String,
+    /// Additional, platform-specific crash parameters.
+    pub parameters: HashMap<String, Variant>,
+}
+
+impl Crash {
+    /// Copy the details out of a `FridaCrash`, which remains owned by the caller.
+    pub(crate) unsafe fn from_raw(crash_ptr: *mut _FridaCrash) -> Crash {
+        unsafe {
+            let string = |ptr: *const frida_sys::gchar| {
+                if ptr.is_null() {
+                    String::new()
+                } else {
+                    CStr::from_ptr(ptr).to_string_lossy().into_owned()
+                }
+            };
+
+            Crash {
+                pid: frida_sys::frida_crash_get_pid(crash_ptr),
+                process_name: string(frida_sys::frida_crash_get_process_name(crash_ptr)),
+                summary: string(frida_sys::frida_crash_get_summary(crash_ptr)),
+                report: string(frida_sys::frida_crash_get_report(crash_ptr)),
+                parameters: variant_map_from_hash_table(frida_sys::frida_crash_get_parameters(
+                    crash_ptr,
+                )),
+            }
+        }
+    }
+}
@@ -4,27 +4,41 @@
  * Licence: wxWindows Library Licence, Version 3.1
  */
 
-use frida_sys::_FridaDevice;
+use frida_sys::{_FridaDevice, g_bytes_get_data, g_bytes_new, g_bytes_unref, gsize};
 use std::collections::HashMap;
-use std::ffi::{CStr, CString};
-use std::marker::PhantomData;
-
+use std::ffi::{CStr, CString, c_void};
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+
+use crate::cancellable::{Cancellable, cancellable_ptr};
+use crate::crash::Crash;
+use crate::device_manager::ManagerRef;
+use crate::error::check_gerror;
+use crate::parameters::SystemParameters;
 use crate::process::Process;
 use crate::session::Session;
-use crate::variant::Variant;
+use crate::signal::connect_handler;
+use crate::variant::{Variant, variant_map_from_hash_table};
 use crate::{Error, Result, SpawnOptions};
 
 /// Access to a Frida device.
-pub struct Device<'a> {
+///
+/// Cloning a device takes a new reference to the same underlying object. Each handle
+/// keeps the [`DeviceManager`](crate::DeviceManager) it came from alive.
+pub struct Device {
     pub(crate) device_ptr: *mut _FridaDevice,
-    phantom: PhantomData<&'a _FridaDevice>,
+    manager: Arc<ManagerRef>,
 }
 
-impl<'a> Device<'a> {
-    pub(crate) fn from_raw(device_ptr: *mut _FridaDevice) -> Device<'a> {
+// frida-core marshals every device operation onto its own main context.
+unsafe impl Send for Device {}
+unsafe impl Sync for Device {}
+
+impl Device {
+    pub(crate) fn from_raw(device_ptr: *mut _FridaDevice, manager: Arc<ManagerRef>) -> Device {
         Device {
             device_ptr,
-            phantom: PhantomData,
+            manager,
         }
     }
 
@@ -46,9 +60,9 @@ impl<'a> Device<'a> {
     /// # Example
     /// ```ignore
     ///# use frida::DeviceType;
-    ///# let frida = unsafe { frida::Frida::obtain() };
+    ///# let frida = frida::Frida::obtain();
     ///# let device_manager = frida::DeviceManager::obtain(&frida);
-    ///# let device = device_manager.enumerate_all_devices().into_iter().find(|device| device.get_id() == "local").unwrap();
+    ///# let device = device_manager.enumerate_all_devices().unwrap().into_iter().find(|device| device.get_id() == "local").unwrap();
     /// assert_eq!(device.get_type(), DeviceType::Local);
     /// ```
     pub fn get_type(&self) -> DeviceType {
@@ -60,9 +74,9 @@ impl<'a> Device<'a> {
     /// # Example
     /// ```ignore
     ///# use std::collections::HashMap;
-    ///# let frida = unsafe { frida::Frida::obtain() };
+    ///# let frida = frida::Frida::obtain();
     ///# let device_manager = frida::DeviceManager::obtain(&frida);
-    ///# let device = device_manager.enumerate_all_devices().into_iter().find(|device| device.get_id() == "local").unwrap();
+    ///# let device = device_manager.enumerate_all_devices().unwrap().into_iter().find(|device| device.get_id() == "local").unwrap();
     /// let params = device.query_system_parameters().unwrap();
     /// let os_version = params
     ///     .get("os")
@@ -85,44 +99,35 @@ impl<'a> Device<'a> {
             )
         };
 
-        if !error.is_null() {
-            let message = unsafe { CString::from_raw((*error).message) }
-                .into_string()
-                .map_err(|_| Error::CStringFailed)?;
-            let code = unsafe { (*error).code };
+        check_gerror(error, Error::DeviceQuerySystemParametersFailed)?;
 
-            return Err(Error::DeviceQuerySystemParametersFailed { code, message });
-        }
-
-        let mut iter: frida_sys::GHashTableIter =
-            unsafe { std::mem::MaybeUninit::zeroed().assume_init() };
-        unsafe { frida_sys::g_hash_table_iter_init(&mut iter, ht) };
-        let size = unsafe { frida_sys::g_hash_table_size(ht) };
-        let mut map = HashMap::with_capacity(size as usize);
-
-        let mut key = std::ptr::null_mut();
-        let mut val = std::ptr::null_mut();
-        while (unsafe { frida_sys::g_hash_table_iter_next(&mut iter, &mut key, &mut val) }
-            != frida_sys::FALSE as i32)
-        {
-            let key = unsafe { CStr::from_ptr(key as _) };
-            let val = unsafe { Variant::from_ptr(val as _) };
-            map.insert(key.to_string_lossy().to_string(), val);
-        }
+        let map = unsafe { variant_map_from_hash_table(ht) };
+        unsafe { frida_sys::g_hash_table_unref(ht) };
 
         Ok(map)
     }
 
+    /// Returns the device's system parameters as a [`SystemParameters`].
+    ///
+    /// # Example
+    /// ```ignore
+    ///# let frida = frida::Frida::obtain();
+    ///# let device_manager = frida::DeviceManager::obtain(&frida);
+    ///# let device = device_manager.get_local_device().unwrap();
+    /// let params = device.system_parameters().unwrap();
+    /// println!("{:?} {:?}", params.os_name, params.os_version);
+    /// ```
+    pub fn system_parameters(&self) -> Result<SystemParameters> {
+        self.query_system_parameters().map(SystemParameters::from)
+    }
+
     /// Returns if the device is lost or not.
     pub fn is_lost(&self) -> bool {
         unsafe { frida_sys::frida_device_is_lost(self.device_ptr) == 1 }
     }
 
     /// Returns all processes (with [`Scope::Minimal`] — name + pid only).
-    pub fn enumerate_processes<'b>(&'a self) -> Vec<Process<'b>>
-    where
-        'a: 'b,
-    {
+    pub fn enumerate_processes(&self) -> Result<Vec<Process>> {
         self.enumerate_processes_with_options(Scope::Minimal)
     }
 
@@ -130,11 +135,7 @@ impl<'a> Device<'a> {
     ///
     /// With [`Scope::Full`] each returned [`Process`] populates
     /// [`Process::get_parameters`] (ppid, path, user, started, ...).
-    pub fn enumerate_processes_with_options<'b>(&'a self, scope: Scope) -> Vec<Process<'b>>
-    where
-        'a: 'b,
-    {
-        let mut processes = Vec::new();
+    pub fn enumerate_processes_with_options(&self, scope: Scope) -> Result<Vec<Process>> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
 
         let opts = unsafe { frida_sys::frida_process_query_options_new() };
@@ -153,42 +154,48 @@ impl<'a> Device<'a> {
 
         unsafe { frida_sys::frida_unref(opts as _) };
 
-        if error.is_null() {
-            let num_processes = unsafe { frida_sys::frida_process_list_size(processes_ptr) };
-            processes.reserve(num_processes as usize);
+        check_gerror(error, Error::ProcessEnumerationFailed)?;
+
+        let num_processes = unsafe { frida_sys::frida_process_list_size(processes_ptr) };
+        let mut processes = Vec::with_capacity(num_processes as usize);
 
-            for i in 0..num_processes {
-                let process_ptr = unsafe { frida_sys::frida_process_list_get(processes_ptr, i) };
-                let process = Process::from_raw(process_ptr);
-                processes.push(process);
-            }
+        for i in 0..num_processes {
+            let process_ptr = unsafe { frida_sys::frida_process_list_get(processes_ptr, i) };
+            let process = Process::from_raw(process_ptr, self.manager.frida.clone());
+            processes.push(process);
         }
 
         unsafe { frida_sys::frida_unref(processes_ptr as _) };
-        processes
+
+        Ok(processes)
     }
 
     /// Creates [`Session`] and attaches the device to the current PID.
-    pub fn attach<'b>(&'a self, pid: u32) -> Result<Session<'b>>
-    where
-        'a: 'b,
-    {
+    pub fn attach(&self, pid: u32) -> Result<Session> {
+        self.attach_with_cancellable(pid, None)
+    }
+
+    /// Creates [`Session`] and attaches the device to the current PID, giving up once
+    /// `cancellable` is cancelled.
+    pub fn attach_with_cancellable(
+        &self,
+        pid: u32,
+        cancellable: Option<&Cancellable>,
+    ) -> Result<Session> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
         let session = unsafe {
             frida_sys::frida_device_attach_sync(
                 self.device_ptr,
                 pid,
                 std::ptr::null_mut(),
-                std::ptr::null_mut(),
+                cancellable_ptr(cancellable),
                 &mut error,
             )
         };
 
-        if error.is_null() {
-            Ok(Session::from_raw(session))
-        } else {
-            Err(Error::DeviceAttachError)
-        }
+        check_gerror(error, Error::DeviceAttachError)?;
+
+        Ok(Session::from_raw(session, self.clone()))
     }
 
     /// Spawn a process on the device
@@ -196,7 +203,19 @@ impl<'a> Device<'a> {
     /// Returns the PID of the newly spawned process.
     /// On spawn, the process will be halted, and [`resume`](Device::resume) will need to be
     /// called to continue execution.
-    pub fn spawn<S: AsRef<str>>(&mut self, program: S, options: &SpawnOptions) -> Result<u32> {
+    pub fn spawn<S: AsRef<str>>(&self, program: S, options: &SpawnOptions) -> Result<u32> {
+        self.spawn_with_cancellable(program, options, None)
+    }
+
+    /// Spawn a process on the device, giving up once `cancellable` is cancelled.
+    ///
+    /// See [`spawn`](Device::spawn) for details.
+    pub fn spawn_with_cancellable<S: AsRef<str>>(
+        &self,
+        program: S,
+        options: &SpawnOptions,
+        cancellable: Option<&Cancellable>,
+    ) -> Result<u32> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
         let program = CString::new(program.as_ref()).unwrap();
 
@@ -205,19 +224,12 @@ impl<'a> Device<'a> {
                 self.device_ptr,
                 program.as_ptr(),
                 options.options_ptr,
-                std::ptr::null_mut(),
+                cancellable_ptr(cancellable),
                 &mut error,
             )
         };
 
-        if !error.is_null() {
-            let message = unsafe { CString::from_raw((*error).message) }
-                .into_string()
-                .map_err(|_| Error::CStringFailed)?;
-            let code = unsafe { (*error).code };
-
-            return Err(Error::SpawnFailed { code, message });
-        }
+        check_gerror(error, Error::SpawnFailed)?;
 
         Ok(pid)
     }
@@ -234,20 +246,88 @@ impl<'a> Device<'a> {
             )
         };
 
-        if !error.is_null() {
-            let message = unsafe { CString::from_raw((*error).message) }
-                .into_string()
-                .map_err(|_| Error::CStringFailed)?;
-            let code = unsafe { (*error).code };
+        check_gerror(error, Error::ResumeFailed)?;
+
+        Ok(())
+    }
+
+    /// Writes `data` to the standard input of a process spawned with
+    /// [`SpawnStdio::Pipe`](crate::SpawnStdio::Pipe).
+    pub fn input(&self, pid: u32, data: &[u8]) -> Result<()> {
+        let mut error: *mut frida_sys::GError = std::ptr::null_mut();
+        unsafe {
+            let g_data = g_bytes_new(data.as_ptr() as _, data.len() as _);
+            frida_sys::frida_device_input_sync(
+                self.device_ptr,
+                pid,
+                g_data,
+                std::ptr::null_mut(),
+                &mut error,
+            );
+            g_bytes_unref(g_data);
+        }
+
+        check_gerror(error, Error::InputFailed)?;
 
-            return Err(Error::ResumeFailed { code, message });
+        Ok(())
+    }
+
+    /// Handles the `output` signal for the device and wraps into [`OutputHandler`].
+    ///
+    /// The handler receives everything written to stdout and stderr by processes
+    /// spawned with [`SpawnStdio::Pipe`](crate::SpawnStdio::Pipe). It is called from the
+    /// Frida main context and stays connected for as long as the device exists.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    ///# use frida::{Output, SpawnOptions, SpawnStdio};
+    ///# let frida = frida::Frida::obtain();
+    ///# let device_manager = frida::DeviceManager::obtain(&frida);
+    ///# let device = device_manager.get_local_device().unwrap();
+    /// let (tx, rx) = std::sync::mpsc::channel::<Output>();
+    /// device.handle_output(tx).unwrap();
+    ///
+    /// let options = SpawnOptions::new().argv(["echo", "hi"]).stdio(SpawnStdio::Pipe);
+    /// let pid = device.spawn("/bin/echo", &options).unwrap();
+    /// device.resume(pid).unwrap();
+    ///
+    /// let output = rx.recv().unwrap();
+    /// assert_eq!(output.data, b"hi\n");
+    /// ```
+    pub fn handle_output<H: OutputHandler + Send + 'static>(&self, handler: H) -> Result<()> {
+        unsafe {
+            connect_handler(
+                self.device_ptr as _,
+                "output",
+                call_on_output::<H> as *mut c_void,
+                handler,
+            )?;
         }
+        Ok(())
+    }
 
+    /// Handles the `process-crashed` signal for the device and wraps into [`CrashHandler`].
+    ///
+    /// The handler receives a [`Crash`] for every process on the device that crashes while
+    /// Frida's crash reporter is active. It is called from the Frida main context.
+    pub fn handle_process_crashed<H: CrashHandler + Send + 'static>(
+        &self,
+        handler: H,
+    ) -> Result<()> {
+        unsafe {
+            connect_handler(
+                self.device_ptr as _,
+                "process-crashed",
+                call_on_process_crashed::<H> as *mut c_void,
+                handler,
+            )?;
+        }
         Ok(())
     }
 
     /// Kill a process on the device
-    pub fn kill(&mut self, pid: u32) -> Result<()> {
+    pub fn kill(&self, pid: u32) -> Result<()> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
         unsafe {
             frida_sys::frida_device_kill_sync(
@@ -258,25 +338,109 @@ impl<'a> Device<'a> {
             )
         };
 
-        if !error.is_null() {
-            let message = unsafe { CString::from_raw((*error).message) }
-                .into_string()
-                .map_err(|_| Error::CStringFailed)?;
-            let code = unsafe { (*error).code };
-
-            return Err(Error::KillFailed { code, message });
-        }
+        check_gerror(error, Error::KillFailed)?;
 
         Ok(())
     }
 }
 
-impl Drop for Device<'_> {
+impl Clone for Device {
+    fn clone(&self) -> Self {
+        unsafe { frida_sys::g_object_ref(self.device_ptr as _) };
+        Device::from_raw(self.device_ptr, self.manager.clone())
+    }
+}
+
+impl Drop for Device {
     fn drop(&mut self) {
         unsafe { frida_sys::frida_unref(self.device_ptr as _) }
     }
 }
 
+/// Data written by a spawned process to one of its piped standard streams.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    /// PID of the process that produced the data.
+    pub pid: u32,
+    /// File descriptor the data was written to (1 for stdout, 2 for stderr).
+    pub fd: i32,
+    /// The bytes written. An empty buffer signals that the stream was closed.
+    pub data: Vec<u8>,
+}
+
+/// Represents a device output signal handler.
+pub trait OutputHandler {
+    /// Handler called when a process spawned with [`SpawnStdio::Pipe`](crate::SpawnStdio::Pipe)
+    /// writes to its stdout or stderr.
+    fn on_output(&mut self, pid: u32, fd: i32, data: &[u8]);
+}
+
+impl OutputHandler for Sender<Output> {
+    fn on_output(&mut self, pid: u32, fd: i32, data: &[u8]) {
+        let _ = self.send(Output {
+            pid,
+            fd,
+            data: data.to_vec(),
+        });
+    }
+}
+
+unsafe extern "C" fn call_on_output<H: OutputHandler>(
+    _device_ptr: *mut _FridaDevice,
+    pid: frida_sys::guint,
+    fd: frida_sys::gint,
+    data: *mut frida_sys::GBytes,
+    user_data: *mut c_void,
+) {
+    unsafe {
+        let handler = match (user_data as *mut H).as_mut() {
+            Some(handler) => handler,
+            None => return,
+        };
+
+        let mut size: gsize = 0;
+        let raw = if data.is_null() {
+            std::ptr::null()
+        } else {
+            g_bytes_get_data(data, &mut size) as *const u8
+        };
+        let data = if raw.is_null() || size == 0 {
+            &[][..]
+        } else {
+            std::slice::from_raw_parts(raw, size as usize)
+        };
+
+        handler.on_output(pid, fd, data);
+    }
+}
+
+/// Represents a device `process-crashed` signal handler.
+pub trait CrashHandler {
+    /// Handler called when a process on the device crashes.
+    fn on_crash(&mut self, crash: Crash);
+}
+
+impl CrashHandler for Sender<Crash> {
+    fn on_crash(&mut self, crash: Crash) {
+        let _ = self.send(crash);
+    }
+}
+
+unsafe extern "C" fn call_on_process_crashed<H: CrashHandler>(
+    _device_ptr: *mut _FridaDevice,
+    crash: *mut frida_sys::_FridaCrash,
+    user_data: *mut c_void,
+) {
+    unsafe {
+        if crash.is_null() {
+            return;
+        }
+        if let Some(handler) = (user_data as *mut H).as_mut() {
+            handler.on_crash(Crash::from_raw(crash));
+        }
+    }
+}
+
 #[repr(u32)]
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
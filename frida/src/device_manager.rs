@@ -6,154 +6,270 @@
 
 use frida_sys::_FridaDeviceManager;
 use std::ffi::CString;
-use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::DeviceType;
 use crate::Error;
 use crate::Frida;
 use crate::Result;
+use crate::cancellable::{Cancellable, cancellable_ptr};
 use crate::device::{self, Device};
+use crate::error::check_gerror;
 
 /// Platform-independent device manager abstraction access.
-pub struct DeviceManager<'a> {
+///
+/// Cloning is cheap and yields a handle to the same manager. The manager is closed by
+/// [`close`](DeviceManager::close), or once the last handle, including the [`Device`]s
+/// obtained from it, is dropped, in which case it is closed asynchronously on frida-core's
+/// main context. Call `close` to wait for the helper processes and threads to shut down.
+#[derive(Clone)]
+pub struct DeviceManager {
+    manager: Arc<ManagerRef>,
+}
+
+/// Strong reference to a `FridaDeviceManager`, shared by the manager and its devices.
+pub(crate) struct ManagerRef {
     manager_ptr: *mut _FridaDeviceManager,
-    phantom: PhantomData<&'a _FridaDeviceManager>,
+    closed: AtomicBool,
+    // Schedules the close of a dropped manager on the main context.
+    pub(crate) frida: Frida,
+}
+
+impl ManagerRef {
+    fn close(&self) -> Result<()> {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let mut error: *mut frida_sys::GError = std::ptr::null_mut();
+        unsafe {
+            frida_sys::frida_device_manager_close_sync(
+                self.manager_ptr,
+                std::ptr::null_mut(),
+                &mut error,
+            )
+        };
+
+        check_gerror(error, Error::DeviceManagerCloseFailed)
+    }
+}
+
+// frida-core marshals every manager operation onto its own main context.
+unsafe impl Send for ManagerRef {}
+unsafe impl Sync for ManagerRef {}
+
+impl Drop for ManagerRef {
+    fn drop(&mut self) {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            unsafe { frida_sys::frida_unref(self.manager_ptr as _) };
+            return;
+        }
+
+        // `close_sync` waits on frida-core's main context, which deadlocks when the last
+        // handle is dropped from a callback running on it. Close asynchronously on the main
+        // context instead, releasing the manager once closed. Errors can't be reported from
+        // here; use `DeviceManager::close` to observe them.
+        unsafe extern "C" fn on_closed(
+            source: *mut frida_sys::GObject,
+            result: *mut frida_sys::GAsyncResult,
+            _user_data: frida_sys::gpointer,
+        ) {
+            let mut error: *mut frida_sys::GError = std::ptr::null_mut();
+            unsafe {
+                frida_sys::frida_device_manager_close_finish(source as _, result, &mut error);
+                let _ = check_gerror(error, Error::DeviceManagerCloseFailed);
+                frida_sys::frida_unref(source as _);
+            }
+        }
+
+        let manager = ClosingManager(self.manager_ptr);
+        self.frida.schedule_on_main(move || {
+            // Moves the whole wrapper rather than its pointer into the closure.
+            let manager = manager;
+            unsafe {
+                frida_sys::frida_device_manager_close(
+                    manager.0,
+                    std::ptr::null_mut(),
+                    Some(on_closed),
+                    std::ptr::null_mut(),
+                )
+            }
+        });
+    }
 }
 
-impl<'a> DeviceManager<'a> {
+/// A manager left to close on frida-core's main context.
+struct ClosingManager(*mut _FridaDeviceManager);
+
+// The manager is only used on the main context once handed over.
+unsafe impl Send for ClosingManager {}
+
+impl DeviceManager {
+    pub(crate) fn as_ptr(&self) -> *mut _FridaDeviceManager {
+        self.manager.manager_ptr
+    }
+
     /// Obtain an DeviceManager handle, ensuring that the runtime is properly initialized. This may be called as many
     /// times as needed, and results in a no-op if the DeviceManager is already initialized.
-    pub fn obtain<'b>(_frida: &'b Frida) -> Self
-    where
-        'b: 'a,
-    {
+    pub fn obtain(frida: &Frida) -> Self {
         DeviceManager {
-            manager_ptr: unsafe { frida_sys::frida_device_manager_new() },
-            phantom: PhantomData,
+            manager: Arc::new(ManagerRef {
+                manager_ptr: unsafe { frida_sys::frida_device_manager_new() },
+                closed: AtomicBool::new(false),
+                frida: frida.clone(),
+            }),
         }
     }
 
+    /// Closes the device manager, shutting down the helper processes and threads it
+    /// started.
+    ///
+    /// Devices obtained from this manager, and from its clones, are lost afterwards.
+    /// Closing an already closed manager does nothing.
+    pub fn close(&self) -> Result<()> {
+        self.manager.close()
+    }
+
     /// Returns all devices.
-    pub fn enumerate_all_devices<'b>(&'a self) -> Vec<Device<'b>>
-    where
-        'a: 'b,
-    {
-        let mut devices = Vec::new();
+    pub fn enumerate_all_devices(&self) -> Result<Vec<Device>> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
 
         let devices_ptr = unsafe {
             frida_sys::frida_device_manager_enumerate_devices_sync(
-                self.manager_ptr,
+                self.manager.manager_ptr,
                 std::ptr::null_mut(),
                 &mut error,
             )
         };
 
-        if error.is_null() {
-            let num_devices = unsafe { frida_sys::frida_device_list_size(devices_ptr) };
-            devices.reserve(num_devices as usize);
+        check_gerror(error, Error::DeviceEnumerationFailed)?;
 
-            for i in 0..num_devices {
-                let device =
-                    Device::from_raw(unsafe { frida_sys::frida_device_list_get(devices_ptr, i) });
-                devices.push(device);
-            }
+        let num_devices = unsafe { frida_sys::frida_device_list_size(devices_ptr) };
+        let mut devices = Vec::with_capacity(num_devices as usize);
+
+        for i in 0..num_devices {
+            let device = Device::from_raw(
+                unsafe { frida_sys::frida_device_list_get(devices_ptr, i) },
+                self.manager.clone(),
+            );
+            devices.push(device);
         }
 
         unsafe { frida_sys::frida_unref(devices_ptr as _) }
-        devices
+
+        Ok(devices)
     }
 
     /// Returns the device of the specified type.
-    pub fn get_device_by_type(&'a self, r#type: DeviceType) -> Result<Device<'a>> {
+    pub fn get_device_by_type(&self, r#type: DeviceType) -> Result<Device> {
+        self.get_device_by_type_with_cancellable(r#type, None)
+    }
+
+    /// Returns the device of the specified type, giving up once `cancellable` is cancelled.
+    pub fn get_device_by_type_with_cancellable(
+        &self,
+        r#type: DeviceType,
+        cancellable: Option<&Cancellable>,
+    ) -> Result<Device> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
 
         let device_ptr = unsafe {
             frida_sys::frida_device_manager_get_device_by_type_sync(
-                self.manager_ptr,
+                self.manager.manager_ptr,
                 r#type.into(),
                 0,
-                std::ptr::null_mut(),
+                cancellable_ptr(cancellable),
                 &mut error,
             )
         };
 
-        if !error.is_null() {
-            return Err(Error::DeviceLookupFailed);
-        }
+        check_gerror(error, Error::DeviceLookupFailed)?;
 
-        Ok(Device::from_raw(device_ptr))
+        Ok(Device::from_raw(device_ptr, self.manager.clone()))
     }
 
     /// Returns the remote device with the specified host.
-    pub fn get_remote_device(&'a self, host: &str) -> Result<Device<'a>> {
+    pub fn get_remote_device(&self, host: &str) -> Result<Device> {
+        self.get_remote_device_with_cancellable(host, None)
+    }
+
+    /// Returns the remote device with the specified host, giving up once `cancellable` is
+    /// cancelled.
+    pub fn get_remote_device_with_cancellable(
+        &self,
+        host: &str,
+        cancellable: Option<&Cancellable>,
+    ) -> Result<Device> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
         let host_cstring = CString::new(host).map_err(|_| Error::CStringFailed)?;
 
         let device_ptr = unsafe {
             frida_sys::frida_device_manager_add_remote_device_sync(
-                self.manager_ptr,
+                self.manager.manager_ptr,
                 host_cstring.as_ptr(),
                 std::ptr::null_mut(),
-                std::ptr::null_mut(),
+                cancellable_ptr(cancellable),
                 &mut error,
             )
         };
 
-        if !error.is_null() {
-            return Err(Error::DeviceLookupFailed);
-        }
+        check_gerror(error, Error::DeviceLookupFailed)?;
 
-        Ok(Device::from_raw(device_ptr))
+        Ok(Device::from_raw(device_ptr, self.manager.clone()))
     }
 
     /// Returns the local device.
-    pub fn get_local_device(&'a self) -> Result<Device<'a>> {
+    pub fn get_local_device(&self) -> Result<Device> {
         self.get_device_by_type(device::DeviceType::Local)
     }
 
+    /// Returns the local device, giving up once `cancellable` is cancelled.
+    pub fn get_local_device_with_cancellable(
+        &self,
+        cancellable: Option<&Cancellable>,
+    ) -> Result<Device> {
+        self.get_device_by_type_with_cancellable(device::DeviceType::Local, cancellable)
+    }
+
     /// Returns the device with the specified id.
     ///
     /// # Example
     /// ```no_run
-    /// let frida = unsafe { frida::Frida::obtain() };
+    /// let frida = frida::Frida::obtain();
     /// let device_manager = frida::DeviceManager::obtain(&frida);
     ///
     /// let id = "<some id>";
     /// let device = device_manager.get_device_by_id(id).unwrap();
     /// assert_eq!(device.get_id(), id);
     /// ```
-    pub fn get_device_by_id(&'a self, device_id: &str) -> Result<Device<'a>> {
+    pub fn get_device_by_id(&self, device_id: &str) -> Result<Device> {
+        self.get_device_by_id_with_cancellable(device_id, None)
+    }
+
+    /// Returns the device with the specified id, giving up once `cancellable` is cancelled.
+    ///
+    /// See [`Cancellable`] for an example using a timeout.
+    pub fn get_device_by_id_with_cancellable(
+        &self,
+        device_id: &str,
+        cancellable: Option<&Cancellable>,
+    ) -> Result<Device> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
         let cstring = CString::new(device_id).unwrap();
 
         let device_ptr = unsafe {
             frida_sys::frida_device_manager_get_device_by_id_sync(
-                self.manager_ptr,
+                self.manager.manager_ptr,
                 cstring.as_ptr(),
                 0,
-                std::ptr::null_mut(),
+                cancellable_ptr(cancellable),
                 &mut error,
             )
         };
 
-        if !error.is_null() {
-            return Err(Error::DeviceLookupFailed);
-        }
-
-        Ok(Device::from_raw(device_ptr))
-    }
-}
+        check_gerror(error, Error::DeviceLookupFailed)?;
 
-impl Drop for DeviceManager<'_> {
-    fn drop(&mut self) {
-        unsafe {
-            frida_sys::frida_device_manager_close_sync(
-                self.manager_ptr,
-                std::ptr::null_mut(),
-                std::ptr::null_mut(),
-            );
-            frida_sys::frida_unref(self.manager_ptr as _)
-        }
+        Ok(Device::from_raw(device_ptr, self.manager.clone()))
     }
 }
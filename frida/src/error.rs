@@ -4,83 +4,95 @@
  * Licence: wxWindows Library Licence, Version 3.1
  */
 
+use std::ffi::CStr;
 use thiserror::Error;
 
 /// Custom `Error` for Frida
 #[derive(Error, Debug)]
 pub enum Error {
     /// Failed to attach to a device.
-    #[error("Failed to attach")]
-    DeviceAttachError,
+    #[error("Failed to attach: {0}")]
+    DeviceAttachError(GError),
 
     /// Failled to lookup a device.
-    #[error("Failed to lookup device")]
-    DeviceLookupFailed,
+    #[error("Failed to lookup device: {0}")]
+    DeviceLookupFailed(GError),
+
+    /// Failed to enumerate the devices.
+    #[error("Failed to enumerate devices: {0}")]
+    DeviceEnumerationFailed(GError),
+
+    /// Failed to close the device manager.
+    #[error("Failed to close the device manager: {0}")]
+    DeviceManagerCloseFailed(GError),
 
     /// Failed to detach a session.
-    #[error("Failed to detach the current session")]
-    SessionDetachError,
+    #[error("Failed to detach the current session: {0}")]
+    SessionDetachError(GError),
 
     /// Failed to create a script in a session.
-    #[error("Failed to create the script")]
-    ScriptCreationError,
+    #[error("Failed to create the script: {0}")]
+    ScriptCreationError(GError),
 
     /// Failled to load a script in a session.
-    #[error("Failed to load the script")]
-    LoadingFailed,
+    #[error("Failed to load the script: {0}")]
+    LoadingFailed(GError),
 
     /// Failed to unload a script in a session.
-    #[error("Failed to unload the script")]
-    UnloadingFailed,
+    #[error("Failed to unload the script: {0}")]
+    UnloadingFailed(GError),
+
+    /// Failed to bundle an agent.
+    #[error("Failed to compile the agent: {0}")]
+    CompilationFailed(GError),
+
+    /// The operation was cancelled through a [`Cancellable`](crate::Cancellable).
+    #[error("Operation was cancelled")]
+    Cancelled,
 
     /// CString conversion failed.
     #[error("Failed to convert the string into CString")]
     CStringFailed,
 
     /// Failed to inject library
-    #[error("Failed to inject library ({code}) {message}")]
-    InjectFailed {
-        /// Error code
-        code: i32,
-        /// Error message
-        message: String,
-    },
+    #[error("Failed to inject library: {0}")]
+    InjectFailed(GError),
+
+    /// Failed to stop monitoring an injected library
+    #[error("Failed to demonitor injection: {0}")]
+    DemonitorFailed(GError),
+
+    /// Failed to recreate the thread of an injected library
+    #[error("Failed to recreate injection thread: {0}")]
+    RecreateThreadFailed(GError),
+
+    /// The operation is not supported by this implementation.
+    #[error("Operation not supported")]
+    NotSupported,
 
     /// Failed to query device parameters
-    #[error("Failed to query device system parameters ({code}) {message}")]
-    DeviceQuerySystemParametersFailed {
-        /// Error code
-        code: i32,
-        /// Error message
-        message: String,
-    },
+    #[error("Failed to query device system parameters: {0}")]
+    DeviceQuerySystemParametersFailed(GError),
+
+    /// Failed to enumerate the processes of a device
+    #[error("Failed to enumerate processes: {0}")]
+    ProcessEnumerationFailed(GError),
 
     /// Failed to spawn program
-    #[error("Failed to spawn program ({code}) {message}")]
-    SpawnFailed {
-        /// Error code
-        code: i32,
-        /// Error message
-        message: String,
-    },
+    #[error("Failed to spawn program: {0}")]
+    SpawnFailed(GError),
 
     /// Failed to resume
-    #[error("Failed to resume ({code}) {message}")]
-    ResumeFailed {
-        /// Error code
-        code: i32,
-        /// Error message
-        message: String,
-    },
+    #[error("Failed to resume: {0}")]
+    ResumeFailed(GError),
 
     /// Failed to kill
-    #[error("Failed to kill PID ({code}) {message}")]
-    KillFailed {
-        /// Error code
-        code: i32,
-        /// Error message
-        message: String,
-    },
+    #[error("Failed to kill PID: {0}")]
+    KillFailed(GError),
+
+    /// Failed to write to the standard input of a process
+    #[error("Failed to write input to PID: {0}")]
+    InputFailed(GError),
 
     /// Received unexpected RPC message.
     #[error("Unexpected RPC message received.")]
@@ -92,5 +104,227 @@ pub enum Error {
     RpcJsError {
         /// Error message from JavaScript.
         message: String,
+        /// Stack trace of the JavaScript error, if reported.
+        stack: Option<String>,
+        /// `stack` resolved through the script's [`SourceMap`](crate::SourceMap), if it has
+        /// one.
+        original_stack: Option<String>,
     },
+
+    /// A source map could not be parsed.
+    #[error("Invalid source map: {0}")]
+    InvalidSourceMap(String),
+}
+
+impl Error {
+    /// Returns the underlying `GError` reported by frida-core, if any.
+    pub fn gerror(&self) -> Option<&GError> {
+        match self {
+            Self::DeviceAttachError(e)
+            | Self::DeviceLookupFailed(e)
+            | Self::DeviceEnumerationFailed(e)
+            | Self::DeviceManagerCloseFailed(e)
+            | Self::SessionDetachError(e)
+            | Self::ScriptCreationError(e)
+            | Self::LoadingFailed(e)
+            | Self::UnloadingFailed(e)
+            | Self::CompilationFailed(e)
+            | Self::InjectFailed(e)
+            | Self::DemonitorFailed(e)
+            | Self::RecreateThreadFailed(e)
+            | Self::DeviceQuerySystemParametersFailed(e)
+            | Self::ProcessEnumerationFailed(e)
+            | Self::SpawnFailed(e)
+            | Self::ResumeFailed(e)
+            | Self::KillFailed(e)
+            | Self::InputFailed(e) => Some(e),
+            Self::Cancelled
+            | Self::CStringFailed
+            | Self::NotSupported
+            | Self::RpcUnexpectedMessage
+            | Self::RpcJsError { .. }
+            | Self::InvalidSourceMap(_) => None,
+        }
+    }
+
+    /// Returns the [`FridaErrorCode`] of the underlying error, if it is in the Frida error domain.
+    pub fn frida_code(&self) -> Option<FridaErrorCode> {
+        self.gerror().and_then(GError::frida_code)
+    }
+}
+
+/// Owned copy of a `GError` reported by frida-core.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GError {
+    /// Name of the error domain quark, e.g. `frida-error-quark`.
+    pub domain: String,
+    /// Error code, specific to the domain.
+    pub code: i32,
+    /// Human-readable error message.
+    pub message: String,
+}
+
+impl GError {
+    /// Name of the quark of the `FRIDA_ERROR` domain.
+    pub const FRIDA_DOMAIN: &'static str = "frida-error-quark";
+
+    /// Take ownership of a non-null `GError`, copying its contents and freeing it.
+    pub(crate) unsafe fn from_raw(error: *mut frida_sys::GError) -> Self {
+        unsafe {
+            let domain = frida_sys::g_quark_to_string((*error).domain);
+            let domain = if domain.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(domain).to_string_lossy().into_owned()
+            };
+            let message = if (*error).message.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr((*error).message)
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            let code = (*error).code;
+            frida_sys::g_error_free(error);
+
+            GError {
+                domain,
+                code,
+                message,
+            }
+        }
+    }
+
+    /// Creates an error in the Frida error domain.
+    pub(crate) fn frida(code: FridaErrorCode, message: impl Into<String>) -> Self {
+        GError {
+            domain: Self::FRIDA_DOMAIN.to_string(),
+            code: code.code(),
+            message: message.into(),
+        }
+    }
+
+    /// Name of the quark of the `G_IO_ERROR` domain, used for cancellation.
+    pub const IO_DOMAIN: &'static str = "g-io-error-quark";
+
+    /// Returns whether this error reports a cancelled operation.
+    // On Windows, the constants are i32 instead of u32, making the cast a no-op there.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn is_cancelled(&self) -> bool {
+        self.domain == Self::IO_DOMAIN
+            && self.code as frida_sys::GIOErrorEnum == frida_sys::GIOErrorEnum_G_IO_ERROR_CANCELLED
+    }
+
+    /// Returns the [`FridaErrorCode`] if this error belongs to the Frida error domain.
+    pub fn frida_code(&self) -> Option<FridaErrorCode> {
+        if self.domain != Self::FRIDA_DOMAIN {
+            return None;
+        }
+        FridaErrorCode::from_code(self.code)
+    }
+}
+
+impl std::fmt::Display for GError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} ({} {})", self.message, self.domain, self.code)
+    }
+}
+
+/// Turns a `GError` out-parameter into a `Result`, freeing the error if one was set.
+///
+/// Cancelled operations are reported as [`Error::Cancelled`], any other error is wrapped
+/// with `variant`.
+pub(crate) fn check_gerror(
+    error: *mut frida_sys::GError,
+    variant: fn(GError) -> Error,
+) -> crate::Result<()> {
+    if error.is_null() {
+        return Ok(());
+    }
+    let error = unsafe { GError::from_raw(error) };
+    if error.is_cancelled() {
+        Err(Error::Cancelled)
+    } else {
+        Err(variant(error))
+    }
+}
+
+/// Error codes of the `FRIDA_ERROR` domain.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FridaErrorCode {
+    /// The Frida server is not running.
+    ServerNotRunning,
+    /// The executable was not found.
+    ExecutableNotFound,
+    /// The executable is not supported.
+    ExecutableNotSupported,
+    /// The process was not found.
+    ProcessNotFound,
+    /// The process is not responding.
+    ProcessNotResponding,
+    /// An argument was invalid, e.g. a script with a syntax error.
+    InvalidArgument,
+    /// The operation is invalid in the current state.
+    InvalidOperation,
+    /// Permission was denied.
+    PermissionDenied,
+    /// The address is already in use.
+    AddressInUse,
+    /// The operation timed out.
+    TimedOut,
+    /// The operation is not supported.
+    NotSupported,
+    /// A protocol error occurred.
+    Protocol,
+    /// A transport error occurred.
+    Transport,
+}
+
+impl FridaErrorCode {
+    // On Windows, the constants are i32 instead of u32, making the cast a no-op there.
+    #[allow(clippy::unnecessary_cast)]
+    fn from_code(code: i32) -> Option<Self> {
+        let code = code as frida_sys::FridaError;
+        Some(match code {
+            frida_sys::FridaError_FRIDA_ERROR_SERVER_NOT_RUNNING => Self::ServerNotRunning,
+            frida_sys::FridaError_FRIDA_ERROR_EXECUTABLE_NOT_FOUND => Self::ExecutableNotFound,
+            frida_sys::FridaError_FRIDA_ERROR_EXECUTABLE_NOT_SUPPORTED => {
+                Self::ExecutableNotSupported
+            }
+            frida_sys::FridaError_FRIDA_ERROR_PROCESS_NOT_FOUND => Self::ProcessNotFound,
+            frida_sys::FridaError_FRIDA_ERROR_PROCESS_NOT_RESPONDING => Self::ProcessNotResponding,
+            frida_sys::FridaError_FRIDA_ERROR_INVALID_ARGUMENT => Self::InvalidArgument,
+            frida_sys::FridaError_FRIDA_ERROR_INVALID_OPERATION => Self::InvalidOperation,
+            frida_sys::FridaError_FRIDA_ERROR_PERMISSION_DENIED => Self::PermissionDenied,
+            frida_sys::FridaError_FRIDA_ERROR_ADDRESS_IN_USE => Self::AddressInUse,
+            frida_sys::FridaError_FRIDA_ERROR_TIMED_OUT => Self::TimedOut,
+            frida_sys::FridaError_FRIDA_ERROR_NOT_SUPPORTED => Self::NotSupported,
+            frida_sys::FridaError_FRIDA_ERROR_PROTOCOL => Self::Protocol,
+            frida_sys::FridaError_FRIDA_ERROR_TRANSPORT => Self::Transport,
+            _ => return None,
+        })
+    }
+
+    // On Windows, the constants are i32 instead of u32, making the cast a no-op there.
+    #[allow(clippy::unnecessary_cast)]
+    fn code(self) -> i32 {
+        (match self {
+            Self::ServerNotRunning => frida_sys::FridaError_FRIDA_ERROR_SERVER_NOT_RUNNING,
+            Self::ExecutableNotFound => frida_sys::FridaError_FRIDA_ERROR_EXECUTABLE_NOT_FOUND,
+            Self::ExecutableNotSupported => {
+                frida_sys::FridaError_FRIDA_ERROR_EXECUTABLE_NOT_SUPPORTED
+            }
+            Self::ProcessNotFound => frida_sys::FridaError_FRIDA_ERROR_PROCESS_NOT_FOUND,
+            Self::ProcessNotResponding => frida_sys::FridaError_FRIDA_ERROR_PROCESS_NOT_RESPONDING,
+            Self::InvalidArgument => frida_sys::FridaError_FRIDA_ERROR_INVALID_ARGUMENT,
+            Self::InvalidOperation => frida_sys::FridaError_FRIDA_ERROR_INVALID_OPERATION,
+            Self::PermissionDenied => frida_sys::FridaError_FRIDA_ERROR_PERMISSION_DENIED,
+            Self::AddressInUse => frida_sys::FridaError_FRIDA_ERROR_ADDRESS_IN_USE,
+            Self::TimedOut => frida_sys::FridaError_FRIDA_ERROR_TIMED_OUT,
+            Self::NotSupported => frida_sys::FridaError_FRIDA_ERROR_NOT_SUPPORTED,
+            Self::Protocol => frida_sys::FridaError_FRIDA_ERROR_PROTOCOL,
+            Self::Transport => frida_sys::FridaError_FRIDA_ERROR_TRANSPORT,
+        }) as i32
+    }
 }
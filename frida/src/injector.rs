@@ -1,7 +1,10 @@
-use crate::{Device, Error, Result};
-use std::ffi::CString;
-use std::marker::PhantomData;
+use crate::cancellable::{Cancellable, cancellable_ptr};
+use crate::error::check_gerror;
+use crate::signal::connect_handler;
+use crate::{Device, Error, Frida, Result};
+use std::ffi::{CString, c_void};
 use std::path::Path;
+use std::sync::mpsc::Sender;
 
 #[cfg(unix)]
 use std::os::unix::ffi::OsStrExt;
@@ -11,16 +14,21 @@ use frida_sys::{_FridaInjector, g_bytes_new, g_bytes_unref};
 /// Local library injector
 ///
 /// Implements [Inject] to allow library injection into a target process.
-pub struct Injector<'a> {
+pub struct Injector {
     injector_ptr: *mut _FridaInjector,
-    phantom: PhantomData<&'a _FridaInjector>,
+    // Dropped after the injector is closed and unreferenced.
+    _frida: Frida,
 }
 
-impl<'a> Injector<'a> {
-    pub(crate) fn from_raw(injector_ptr: *mut _FridaInjector) -> Injector<'a> {
+// frida-core marshals every injector operation onto its own main context.
+unsafe impl Send for Injector {}
+unsafe impl Sync for Injector {}
+
+impl Injector {
+    pub(crate) fn from_raw(injector_ptr: *mut _FridaInjector, frida: Frida) -> Injector {
         Injector {
             injector_ptr,
-            phantom: PhantomData,
+            _frida: frida,
         }
     }
 
@@ -29,7 +37,8 @@ impl<'a> Injector<'a> {
     /// The `frida-helper` is a binary compiled into the Frida devkit, and is codesigned
     /// to allow debugging. It is spawned and injection is delegated to the helper.
     pub fn new() -> Self {
-        Self::from_raw(unsafe { frida_sys::frida_injector_new() })
+        let frida = Frida::obtain();
+        Self::from_raw(unsafe { frida_sys::frida_injector_new() }, frida)
     }
 
     /// Create a new inprocess Injector
@@ -40,17 +49,59 @@ impl<'a> Injector<'a> {
     /// in_process injector may require the debugger process to be codesigned on some
     /// platforms.
     pub fn in_process() -> Self {
-        Self::from_raw(unsafe { frida_sys::frida_injector_new_inprocess() })
+        let frida = Frida::obtain();
+        Self::from_raw(unsafe { frida_sys::frida_injector_new_inprocess() }, frida)
+    }
+
+    /// Stop monitoring the injection identified by `id`
+    ///
+    /// The library stays loaded in the target process, but no `uninjected` event will be
+    /// emitted for it anymore.
+    pub fn demonitor(&self, id: u32) -> Result<()> {
+        let mut error: *mut frida_sys::GError = std::ptr::null_mut();
+        unsafe {
+            frida_sys::frida_injector_demonitor_sync(
+                self.injector_ptr,
+                id,
+                std::ptr::null_mut(),
+                &mut error,
+            )
+        };
+
+        check_gerror(error, Error::DemonitorFailed)?;
+
+        Ok(())
+    }
+
+    /// Recreate the thread of the injection identified by `id` in the process `pid`
+    ///
+    /// Used to resume monitoring an injection after the target process was forked or its
+    /// injector thread went away.
+    pub fn recreate_thread(&self, pid: u32, id: u32) -> Result<()> {
+        let mut error: *mut frida_sys::GError = std::ptr::null_mut();
+        unsafe {
+            frida_sys::frida_injector_recreate_thread_sync(
+                self.injector_ptr,
+                pid,
+                id,
+                std::ptr::null_mut(),
+                &mut error,
+            )
+        };
+
+        check_gerror(error, Error::RecreateThreadFailed)?;
+
+        Ok(())
     }
 }
 
-impl Default for Injector<'_> {
+impl Default for Injector {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Drop for Injector<'_> {
+impl Drop for Injector {
     fn drop(&mut self) {
         unsafe {
             frida_sys::frida_injector_close_sync(
@@ -109,6 +160,25 @@ pub trait Inject {
         entrypoint: E,
         data: D,
     ) -> Result<u32>
+    where
+        D: Into<Vec<u8>>,
+        P: AsRef<Path>,
+        E: AsRef<str>,
+    {
+        self.inject_library_file_with_cancellable(pid, path, entrypoint, data, None)
+    }
+
+    /// Inject a library into a target process, giving up once `cancellable` is cancelled
+    ///
+    /// See [Inject::inject_library_file_sync] for details.
+    fn inject_library_file_with_cancellable<D, E, P>(
+        &mut self,
+        pid: u32,
+        path: P,
+        entrypoint: E,
+        data: D,
+        cancellable: Option<&Cancellable>,
+    ) -> Result<u32>
     where
         D: Into<Vec<u8>>,
         P: AsRef<Path>,
@@ -125,18 +195,87 @@ pub trait Inject {
         entrypoint: E,
         data: D,
     ) -> Result<u32>
+    where
+        D: Into<Vec<u8>>,
+        E: AsRef<str>,
+    {
+        self.inject_library_blob_with_cancellable(pid, blob, entrypoint, data, None)
+    }
+
+    /// Inject a library blob into a target process, giving up once `cancellable` is cancelled
+    ///
+    /// See [Inject::inject_library_blob_sync] for details.
+    fn inject_library_blob_with_cancellable<D, E>(
+        &mut self,
+        pid: u32,
+        blob: &[u8],
+        entrypoint: E,
+        data: D,
+        cancellable: Option<&Cancellable>,
+    ) -> Result<u32>
     where
         D: Into<Vec<u8>>,
         E: AsRef<str>;
+
+    /// Handles the `uninjected` signal and wraps into [`UninjectedHandler`].
+    ///
+    /// The handler is called with the injection id returned by the `inject_*` functions once
+    /// the injected library's entrypoint has returned and the library was unloaded. It is
+    /// called from the Frida main context.
+    ///
+    /// The default implementation returns [`Error::NotSupported`], for injectors that never
+    /// report unloaded libraries.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    ///# use crate::frida::{Injector, Inject};
+    /// let mut injector = Injector::new();
+    /// let (tx, rx) = std::sync::mpsc::channel();
+    /// injector.handle_uninjected(tx).unwrap();
+    ///
+    /// let id = injector
+    ///     .inject_library_file_sync(1337, "/path/to/payload.so", "entrypoint", "")
+    ///     .unwrap();
+    /// assert_eq!(rx.recv().unwrap(), id);
+    /// ```
+    fn handle_uninjected<H: UninjectedHandler + Send + 'static>(&self, _handler: H) -> Result<()> {
+        Err(Error::NotSupported)
+    }
 }
 
-impl Inject for Injector<'_> {
-    fn inject_library_file_sync<D, E, P>(
+/// Represents an `uninjected` signal handler.
+pub trait UninjectedHandler {
+    /// Handler called when the library injected with the given id has been unloaded.
+    fn on_uninjected(&mut self, id: u32);
+}
+
+impl UninjectedHandler for Sender<u32> {
+    fn on_uninjected(&mut self, id: u32) {
+        let _ = self.send(id);
+    }
+}
+
+unsafe extern "C" fn call_on_uninjected<H: UninjectedHandler>(
+    _instance: *mut c_void,
+    id: frida_sys::guint,
+    user_data: *mut c_void,
+) {
+    unsafe {
+        if let Some(handler) = (user_data as *mut H).as_mut() {
+            handler.on_uninjected(id);
+        }
+    }
+}
+
+impl Inject for Injector {
+    fn inject_library_file_with_cancellable<D, E, P>(
         &mut self,
         pid: u32,
         path: P,
         entrypoint: E,
         data: D,
+        cancellable: Option<&Cancellable>,
     ) -> Result<u32>
     where
         D: Into<Vec<u8>>,
@@ -168,28 +307,22 @@ impl Inject for Injector<'_> {
                 path.as_ptr() as *const frida_sys::gchar,
                 entrypoint.as_ptr() as *const frida_sys::gchar,
                 data.as_ptr() as *const frida_sys::gchar,
-                std::ptr::null_mut(),
+                cancellable_ptr(cancellable),
                 &mut error,
             )
         };
-        if !error.is_null() {
-            let message = unsafe { CString::from_raw((*error).message) }
-                .into_string()
-                .map_err(|_| Error::CStringFailed)?;
-            let code = unsafe { (*error).code };
-
-            return Err(Error::InjectFailed { code, message });
-        }
+        check_gerror(error, Error::InjectFailed)?;
 
         Ok(id)
     }
 
-    fn inject_library_blob_sync<D, E>(
+    fn inject_library_blob_with_cancellable<D, E>(
         &mut self,
         pid: u32,
         blob: &[u8],
         entrypoint: E,
         data: D,
+        cancellable: Option<&Cancellable>,
     ) -> Result<u32>
     where
         D: Into<Vec<u8>>,
@@ -208,33 +341,39 @@ impl Inject for Injector<'_> {
                 g_blob,
                 entrypoint.as_ptr() as *const frida_sys::gchar,
                 data.as_ptr() as *const frida_sys::gchar,
-                std::ptr::null_mut(),
+                cancellable_ptr(cancellable),
                 &mut error,
             );
             g_bytes_unref(g_blob);
             id
         };
 
-        if !error.is_null() {
-            let message = unsafe { CString::from_raw((*error).message) }
-                .into_string()
-                .map_err(|_| Error::CStringFailed)?;
-            let code = unsafe { (*error).code };
-
-            return Err(Error::InjectFailed { code, message });
-        }
+        check_gerror(error, Error::InjectFailed)?;
 
         Ok(id)
     }
+
+    fn handle_uninjected<H: UninjectedHandler + Send + 'static>(&self, handler: H) -> Result<()> {
+        unsafe {
+            connect_handler(
+                self.injector_ptr as _,
+                "uninjected",
+                call_on_uninjected::<H> as *mut c_void,
+                handler,
+            )?;
+        }
+        Ok(())
+    }
 }
 
-impl Inject for Device<'_> {
-    fn inject_library_file_sync<D, E, P>(
+impl Inject for Device {
+    fn inject_library_file_with_cancellable<D, E, P>(
         &mut self,
         pid: u32,
         path: P,
         entrypoint: E,
         data: D,
+        cancellable: Option<&Cancellable>,
     ) -> Result<u32>
     where
         D: Into<Vec<u8>>,
@@ -266,28 +405,22 @@ impl Inject for Device<'_> {
                 path.as_ptr() as *const frida_sys::gchar,
                 entrypoint.as_ptr() as *const frida_sys::gchar,
                 data.as_ptr() as *const frida_sys::gchar,
-                std::ptr::null_mut(),
+                cancellable_ptr(cancellable),
                 &mut error,
             )
         };
-        if !error.is_null() {
-            let message = unsafe { CString::from_raw((*error).message) }
-                .into_string()
-                .map_err(|_| Error::CStringFailed)?;
-            let code = unsafe { (*error).code };
-
-            return Err(Error::InjectFailed { code, message });
-        }
+        check_gerror(error, Error::InjectFailed)?;
 
         Ok(id)
     }
 
-    fn inject_library_blob_sync<D, E>(
+    fn inject_library_blob_with_cancellable<D, E>(
         &mut self,
         pid: u32,
         blob: &[u8],
         entrypoint: E,
         data: D,
+        cancellable: Option<&Cancellable>,
     ) -> Result<u32>
     where
         D: Into<Vec<u8>>,
@@ -306,22 +439,27 @@ impl Inject for Device<'_> {
                 g_blob,
                 entrypoint.as_ptr() as *const frida_sys::gchar,
                 data.as_ptr() as *const frida_sys::gchar,
-                std::ptr::null_mut(),
+                cancellable_ptr(cancellable),
                 &mut error,
             );
             g_bytes_unref(g_blob);
             id
         };
 
-        if !error.is_null() {
-            let message = unsafe { CString::from_raw((*error).message) }
-                .into_string()
-                .map_err(|_| Error::CStringFailed)?;
-            let code = unsafe { (*error).code };
-
-            return Err(Error::InjectFailed { code, message });
-        }
+        check_gerror(error, Error::InjectFailed)?;
 
         Ok(id)
     }
+
+    fn handle_uninjected<H: UninjectedHandler + Send + 'static>(&self, handler: H) -> Result<()> {
+        unsafe {
+            connect_handler(
+                self.device_ptr as _,
+                "uninjected",
+                call_on_uninjected::<H> as *mut c_void,
+                handler,
+            )?;
+        }
+        Ok(())
+    }
 }
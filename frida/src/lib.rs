@@ -12,6 +12,19 @@
 #![allow(clippy::missing_safety_doc)]
 
 use std::ffi::CStr;
+use std::sync::Once;
+
+mod backend;
+pub use backend::*;
+
+mod cancellable;
+pub use cancellable::Cancellable;
+
+mod compiler;
+pub use compiler::*;
+
+mod crash;
+pub use crash::*;
 
 mod device;
 pub use device::*;
@@ -20,11 +33,16 @@ mod device_manager;
 pub use device_manager::*;
 
 mod error;
-pub use error::Error;
+pub use error::{Error, FridaErrorCode, GError};
 
 mod injector;
 pub use injector::*;
 
+pub mod mock;
+
+mod parameters;
+pub use parameters::*;
+
 mod process;
 pub use process::*;
 
@@ -34,24 +52,50 @@ pub use script::*;
 mod session;
 pub use session::*;
 
+mod signal;
+
+mod sourcemap;
+pub use sourcemap::*;
+
 mod variant;
 pub use variant::*;
 
+#[cfg(feature = "dynamic-loading")]
+pub use frida_sys::LoadError;
+
 #[doc(hidden)]
 pub type Result<T> = std::result::Result<T, error::Error>;
 
 /// Context required for instantiation of all structures under the Frida namespace.
-pub struct Frida;
+///
+/// The runtime is initialized by the first [`Frida::obtain`] and is never torn down:
+/// unlike `Gum` in frida-gum, handles are not reference-counted and dropping the last one
+/// doesn't call `frida_deinit`. frida-core cannot be initialized again once deinitialized,
+/// and closing a [`DeviceManager`] needs the runtime's main context. The helper processes
+/// and threads are shut down by [`DeviceManager::close`] instead.
+#[derive(Clone)]
+pub struct Frida {
+    _private: (),
+}
 
 impl Frida {
     /// Obtain a Frida handle, ensuring that the runtime is properly initialized. This may
     /// be called as many times as needed, and results in a no-op if the Frida runtime is
     /// already initialized.
-    pub unsafe fn obtain() -> Frida {
-        unsafe {
-            frida_sys::frida_init();
-            Frida {}
-        }
+    pub fn obtain() -> Frida {
+        FRIDA_INIT.call_once(|| unsafe { frida_sys::frida_init() });
+        Self { _private: () }
+    }
+
+    /// Loads frida-core from the shared library at `path`, e.g. `libfrida-core.so` from an
+    /// installed Frida build.
+    ///
+    /// This must succeed before calling [`Frida::obtain`] or any other function of this
+    /// crate, and can only succeed once per process. Every frida-core function used by this
+    /// crate is checked to be exported by the library.
+    #[cfg(feature = "dynamic-loading")]
+    pub fn load_library(path: impl AsRef<std::ffi::OsStr>) -> std::result::Result<(), LoadError> {
+        frida_sys::load(path, REQUIRED_SYMBOLS)
     }
 
     /// Gets the current version of frida core
@@ -102,8 +146,9 @@ impl Frida {
     }
 }
 
-impl Drop for Frida {
-    fn drop(&mut self) {
-        unsafe { frida_sys::frida_deinit() };
-    }
-}
+/// The frida-core functions used by this crate, which must be exported by a library loaded
+/// with [`Frida::load_library`].
+#[cfg(feature = "dynamic-loading")]
+const REQUIRED_SYMBOLS: &[&str] = include!(concat!(env!("OUT_DIR"), "/required_symbols.rs"));
+
+static FRIDA_INIT: Once = Once::new();
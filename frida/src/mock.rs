@@ -0,0 +1,380 @@
+//! In-memory implementation of the [`DeviceBackend`], [`SessionBackend`] and
+//! [`ScriptBackend`] traits.
+//!
+//! The mock doesn't use frida-core: it never attaches to a process nor runs JavaScript.
+//! Instead, tests describe the processes of a [`MockDevice`], the functions exported by
+//! its scripts and the messages those scripts send. Mocks are independent of each other,
+//! so tests using them can run in parallel.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::backend::{DeviceBackend, ScriptBackend, SessionBackend};
+use crate::error::GError;
+use crate::{
+    DeviceType, Error, FridaErrorCode, Message, Result, ScriptConfig, ScriptHandler, SpawnConfig,
+};
+
+type ScriptHook = Arc<dyn Fn(&MockScript) + Send + Sync>;
+type ExportFn = Arc<dyn Fn(Value) -> std::result::Result<Value, String> + Send + Sync>;
+
+/// A process of a [`MockDevice`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockProcess {
+    /// PID of the process.
+    pub pid: u32,
+    /// Name of the process, or the program it was spawned from.
+    pub name: String,
+    /// Whether the process is running, i.e. it wasn't spawned or was resumed since.
+    pub resumed: bool,
+}
+
+/// In-memory device.
+///
+/// Cloning yields a handle to the same device.
+#[derive(Clone)]
+pub struct MockDevice {
+    inner: Arc<MockDeviceInner>,
+}
+
+struct MockDeviceInner {
+    id: String,
+    name: String,
+    next_pid: AtomicU32,
+    processes: Mutex<HashMap<u32, MockProcess>>,
+    sessions: Mutex<Vec<MockSession>>,
+    // Shared with the sessions, which don't keep the device alive.
+    script_hook: Arc<Mutex<Option<ScriptHook>>>,
+}
+
+impl MockDevice {
+    /// Creates a device without any process.
+    pub fn new(id: &str, name: &str) -> Self {
+        MockDevice {
+            inner: Arc::new(MockDeviceInner {
+                id: id.to_string(),
+                name: name.to_string(),
+                next_pid: AtomicU32::new(1000),
+                processes: Mutex::new(HashMap::new()),
+                sessions: Mutex::new(Vec::new()),
+                script_hook: Arc::new(Mutex::new(None)),
+            }),
+        }
+    }
+
+    /// Adds a running process, which can then be attached to.
+    pub fn add_process(&self, pid: u32, name: &str) {
+        lock(&self.inner.processes).insert(
+            pid,
+            MockProcess {
+                pid,
+                name: name.to_string(),
+                resumed: true,
+            },
+        );
+    }
+
+    /// Returns the processes of the device, sorted by PID.
+    pub fn processes(&self) -> Vec<MockProcess> {
+        let mut processes: Vec<_> = lock(&self.inner.processes).values().cloned().collect();
+        processes.sort_by_key(|p| p.pid);
+        processes
+    }
+
+    /// Returns the sessions created by [`attach`](DeviceBackend::attach), oldest first.
+    pub fn sessions(&self) -> Vec<MockSession> {
+        lock(&self.inner.sessions).clone()
+    }
+
+    /// Sets a hook called with every script created on this device, before it is returned.
+    ///
+    /// This is where tests declare the functions a script exports, see
+    /// [`MockScript::export`].
+    pub fn on_create_script<F: Fn(&MockScript) + Send + Sync + 'static>(&self, hook: F) {
+        *lock(&self.inner.script_hook) = Some(Arc::new(hook));
+    }
+
+    fn process_not_found(pid: u32) -> GError {
+        GError::frida(
+            FridaErrorCode::ProcessNotFound,
+            format!("Unable to find process with pid {pid}"),
+        )
+    }
+}
+
+impl DeviceBackend for MockDevice {
+    type Session = MockSession;
+
+    fn get_id(&self) -> &str {
+        &self.inner.id
+    }
+
+    fn get_name(&self) -> &str {
+        &self.inner.name
+    }
+
+    fn get_type(&self) -> DeviceType {
+        DeviceType::Local
+    }
+
+    fn attach(&self, pid: u32) -> Result<MockSession> {
+        if !lock(&self.inner.processes).contains_key(&pid) {
+            return Err(Error::DeviceAttachError(Self::process_not_found(pid)));
+        }
+
+        let session = MockSession {
+            inner: Arc::new(MockSessionInner {
+                pid,
+                script_hook: self.inner.script_hook.clone(),
+                detached: AtomicBool::new(false),
+                scripts: Mutex::new(Vec::new()),
+            }),
+        };
+        lock(&self.inner.sessions).push(session.clone());
+        Ok(session)
+    }
+
+    fn spawn(&self, program: &str, _options: &SpawnConfig) -> Result<u32> {
+        let pid = self.inner.next_pid.fetch_add(1, Ordering::Relaxed);
+        lock(&self.inner.processes).insert(
+            pid,
+            MockProcess {
+                pid,
+                name: program.to_string(),
+                resumed: false,
+            },
+        );
+        Ok(pid)
+    }
+
+    fn resume(&self, pid: u32) -> Result<()> {
+        match lock(&self.inner.processes).get_mut(&pid) {
+            Some(process) => {
+                process.resumed = true;
+                Ok(())
+            }
+            None => Err(Error::ResumeFailed(Self::process_not_found(pid))),
+        }
+    }
+
+    fn kill(&self, pid: u32) -> Result<()> {
+        match lock(&self.inner.processes).remove(&pid) {
+            Some(_) => Ok(()),
+            None => Err(Error::KillFailed(Self::process_not_found(pid))),
+        }
+    }
+}
+
+/// In-memory session, created by [`MockDevice`].
+///
+/// Cloning yields a handle to the same session.
+#[derive(Clone)]
+pub struct MockSession {
+    inner: Arc<MockSessionInner>,
+}
+
+struct MockSessionInner {
+    pid: u32,
+    script_hook: Arc<Mutex<Option<ScriptHook>>>,
+    detached: AtomicBool,
+    scripts: Mutex<Vec<MockScript>>,
+}
+
+impl MockSession {
+    /// Returns the PID of the process this session is attached to.
+    pub fn pid(&self) -> u32 {
+        self.inner.pid
+    }
+
+    /// Returns the scripts created in this session, oldest first.
+    pub fn scripts(&self) -> Vec<MockScript> {
+        lock(&self.inner.scripts).clone()
+    }
+}
+
+impl SessionBackend for MockSession {
+    type Script = MockScript;
+
+    fn is_detached(&self) -> bool {
+        self.inner.detached.load(Ordering::SeqCst)
+    }
+
+    fn create_script(&self, source: &str, _options: &ScriptConfig) -> Result<MockScript> {
+        if self.is_detached() {
+            return Err(Error::ScriptCreationError(GError::frida(
+                FridaErrorCode::InvalidOperation,
+                "Session is detached",
+            )));
+        }
+
+        let script = MockScript {
+            inner: Arc::new(MockScriptInner {
+                source: source.to_string(),
+                state: Mutex::new(MockScriptState::Created),
+                exports: Mutex::new(HashMap::new()),
+                posted: Mutex::new(Vec::new()),
+                handler: Mutex::new(None),
+            }),
+        };
+        let hook = lock(&self.inner.script_hook).clone();
+        if let Some(hook) = hook {
+            hook(&script);
+        }
+        lock(&self.inner.scripts).push(script.clone());
+        Ok(script)
+    }
+
+    fn detach(&self) -> Result<()> {
+        self.inner.detached.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// In-memory script, created by [`MockSession`].
+///
+/// Cloning yields a handle to the same script.
+#[derive(Clone)]
+pub struct MockScript {
+    inner: Arc<MockScriptInner>,
+}
+
+struct MockScriptInner {
+    source: String,
+    state: Mutex<MockScriptState>,
+    exports: Mutex<HashMap<String, ExportFn>>,
+    posted: Mutex<Vec<(String, Option<Vec<u8>>)>>,
+    handler: Mutex<Option<Box<dyn ScriptHandler + Send>>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MockScriptState {
+    Created,
+    Loaded,
+    Destroyed,
+}
+
+impl MockScript {
+    /// Returns the source the script was created from.
+    pub fn source(&self) -> &str {
+        &self.inner.source
+    }
+
+    /// Returns whether the script is loaded.
+    pub fn is_loaded(&self) -> bool {
+        *lock(&self.inner.state) == MockScriptState::Loaded
+    }
+
+    /// Exports `function` as `name`, as if the script set it in `rpc.exports`.
+    ///
+    /// `function` receives the arguments as a JSON array. Returning an error makes the call
+    /// fail with [`Error::RpcJsError`].
+    pub fn export<F>(&self, name: &str, function: F)
+    where
+        F: Fn(Value) -> std::result::Result<Value, String> + Send + Sync + 'static,
+    {
+        lock(&self.inner.exports).insert(name.to_string(), Arc::new(function));
+    }
+
+    /// Delivers `message` to the handler set with
+    /// [`handle_message`](ScriptBackend::handle_message), as if the script sent it.
+    ///
+    /// The message is dropped if no handler is set.
+    pub fn emit(&self, message: Message, data: Option<Vec<u8>>) {
+        if let Some(handler) = lock(&self.inner.handler).as_deref_mut() {
+            handler.on_message(message, data);
+        }
+    }
+
+    /// Returns the messages posted to the script, oldest first.
+    pub fn posted(&self) -> Vec<(String, Option<Vec<u8>>)> {
+        lock(&self.inner.posted).clone()
+    }
+
+    fn check_loaded(&self) -> Result<()> {
+        match *lock(&self.inner.state) {
+            MockScriptState::Loaded => Ok(()),
+            _ => Err(Error::RpcJsError {
+                message: "script is not loaded".to_string(),
+                stack: None,
+                original_stack: None,
+            }),
+        }
+    }
+}
+
+impl ScriptBackend for MockScript {
+    fn load(&self) -> Result<()> {
+        let mut state = lock(&self.inner.state);
+        match *state {
+            MockScriptState::Created => {
+                *state = MockScriptState::Loaded;
+                Ok(())
+            }
+            MockScriptState::Loaded => Err(Error::LoadingFailed(GError::frida(
+                FridaErrorCode::InvalidOperation,
+                "Script is already loaded",
+            ))),
+            MockScriptState::Destroyed => Err(Error::LoadingFailed(GError::frida(
+                FridaErrorCode::InvalidOperation,
+                "Script is destroyed",
+            ))),
+        }
+    }
+
+    fn unload(&self) -> Result<()> {
+        let mut state = lock(&self.inner.state);
+        if *state == MockScriptState::Destroyed {
+            return Err(Error::UnloadingFailed(GError::frida(
+                FridaErrorCode::InvalidOperation,
+                "Script is destroyed",
+            )));
+        }
+        *state = MockScriptState::Destroyed;
+        Ok(())
+    }
+
+    fn handle_message<I: ScriptHandler + Send + 'static>(&self, handler: I) -> Result<()> {
+        *lock(&self.inner.handler) = Some(Box::new(handler));
+        Ok(())
+    }
+
+    fn post(&self, message: &str, data: Option<&[u8]>) -> Result<()> {
+        lock(&self.inner.posted).push((message.to_string(), data.map(<[u8]>::to_vec)));
+        Ok(())
+    }
+
+    fn list_exports(&self) -> Result<Vec<String>> {
+        self.check_loaded()?;
+        let mut names: Vec<_> = lock(&self.inner.exports).keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn call(&self, function_name: &str, args: Option<Value>) -> Result<Option<Value>> {
+        self.check_loaded()?;
+        let function = lock(&self.inner.exports).get(function_name).cloned();
+        let Some(function) = function else {
+            return Err(Error::RpcJsError {
+                message: format!("unable to find method '{function_name}'"),
+                stack: None,
+                original_stack: None,
+            });
+        };
+
+        match function(args.unwrap_or_else(|| Value::Array(Vec::new()))) {
+            Ok(Value::Null) => Ok(None),
+            Ok(value) => Ok(Some(value)),
+            Err(message) => Err(Error::RpcJsError {
+                message,
+                stack: None,
+                original_stack: None,
+            }),
+        }
+    }
+}
+
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use crate::variant::Variant;
+
+/// Typed view of the parameters returned by
+/// [`Device::query_system_parameters`](crate::Device::query_system_parameters).
+///
+/// Every field is optional, as the set of parameters depends on the device. Parameters
+/// without a typed field, or with an unexpected type, are kept in [`other`](Self::other).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SystemParameters {
+    /// Identifier of the operating system, e.g. `linux`, `macos`, `windows` or `android`.
+    pub os_id: Option<String>,
+    /// Human-readable name of the operating system.
+    pub os_name: Option<String>,
+    /// Version of the operating system.
+    pub os_version: Option<String>,
+    /// Platform of the device, e.g. `linux`, `darwin`, `windows` or `freebsd`.
+    pub platform: Option<String>,
+    /// CPU architecture of the device, e.g. `ia32`, `x64`, `arm` or `arm64`.
+    pub arch: Option<String>,
+    /// Level of access Frida has on the device.
+    pub access: Option<DeviceAccess>,
+    /// Host name of the device.
+    pub hostname: Option<String>,
+    /// Remaining parameters, including unknown members of the `os` map.
+    pub other: HashMap<String, Variant>,
+}
+
+impl From<HashMap<String, Variant>> for SystemParameters {
+    fn from(mut map: HashMap<String, Variant>) -> Self {
+        let mut params = SystemParameters::default();
+
+        if let Some(Variant::Map(os)) = map.get_mut("os") {
+            params.os_id = take_string(os, "id");
+            params.os_name = take_string(os, "name");
+            params.os_version = take_string(os, "version");
+            if os.is_empty() {
+                map.remove("os");
+            }
+        }
+        params.platform = take_string(&mut map, "platform");
+        params.arch = take_string(&mut map, "arch");
+        params.access = take(&mut map, "access", |v| match v.get_string()? {
+            "full" => Some(DeviceAccess::Full),
+            "jailed" => Some(DeviceAccess::Jailed),
+            _ => None,
+        });
+        params.hostname = take_string(&mut map, "name");
+
+        params.other = map;
+        params
+    }
+}
+
+/// Level of access Frida has on a device.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DeviceAccess {
+    /// Frida can instrument any process.
+    Full,
+    /// Frida is restricted, e.g. to debuggable apps on a non-jailbroken device.
+    Jailed,
+}
+
+/// Typed view of the parameters returned by
+/// [`Process::get_parameters`](crate::Process::get_parameters).
+///
+/// Parameters are only populated for processes enumerated with
+/// [`Scope::Full`](crate::Scope::Full), and which ones are available depends on the host.
+/// Parameters without a typed field, or with an unexpected type, are kept in
+/// [`other`](Self::other).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcessParameters {
+    /// Path of the executable.
+    pub path: Option<String>,
+    /// Name of the user running the process.
+    pub user: Option<String>,
+    /// PID of the parent process.
+    pub ppid: Option<u32>,
+    /// Start time of the process, as an ISO 8601 timestamp.
+    pub started: Option<String>,
+    /// Icons of the process, usually of increasing size.
+    pub icons: Vec<ProcessIcon>,
+    /// Whether the process is the frontmost application.
+    pub frontmost: Option<bool>,
+    /// Remaining parameters.
+    pub other: HashMap<String, Variant>,
+}
+
+impl From<HashMap<String, Variant>> for ProcessParameters {
+    fn from(mut map: HashMap<String, Variant>) -> Self {
+        ProcessParameters {
+            path: take_string(&mut map, "path"),
+            user: take_string(&mut map, "user").or_else(|| {
+                // Some hosts describe the user as a map, which stays available raw.
+                let user = map.get("user")?.get_map()?;
+                user.get("name")?.get_string().map(str::to_owned)
+            }),
+            ppid: take(&mut map, "ppid", |v| u32::try_from(v.get_int()?).ok()),
+            started: take_string(&mut map, "started"),
+            icons: take(&mut map, "icons", |v| {
+                v.get_maplist()?.iter().map(ProcessIcon::from_map).collect()
+            })
+            .unwrap_or_default(),
+            frontmost: take(&mut map, "frontmost", Variant::get_bool),
+            other: map,
+        }
+    }
+}
+
+/// Icon of a process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessIcon {
+    /// Image format, `rgba` for raw pixels or `png`.
+    pub format: String,
+    /// Width in pixels, for `rgba` icons.
+    pub width: Option<u32>,
+    /// Height in pixels, for `rgba` icons.
+    pub height: Option<u32>,
+    /// Image data.
+    pub image: Vec<u8>,
+}
+
+impl ProcessIcon {
+    fn from_map(map: &HashMap<String, Variant>) -> Option<Self> {
+        let dimension = |key: &str| u32::try_from(map.get(key)?.get_int()?).ok();
+        Some(ProcessIcon {
+            format: map.get("format")?.get_string()?.to_owned(),
+            width: dimension("width"),
+            height: dimension("height"),
+            image: map.get("image")?.get_bytes()?.to_vec(),
+        })
+    }
+}
+
+/// Removes `key` from `map` if `get` accepts its value, returning the extracted value.
+fn take<T>(
+    map: &mut HashMap<String, Variant>,
+    key: &str,
+    get: impl FnOnce(&Variant) -> Option<T>,
+) -> Option<T> {
+    let value = get(map.get(key)?)?;
+    map.remove(key);
+    Some(value)
+}
+
+fn take_string(map: &mut HashMap<String, Variant>, key: &str) -> Option<String> {
+    take(map, key, |v| v.get_string().map(str::to_owned))
+}
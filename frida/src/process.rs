@@ -4,25 +4,26 @@
  * Licence: wxWindows Library Licence, Version 3.1
  */
 
-use crate::variant::Variant;
+use crate::Frida;
+use crate::parameters::ProcessParameters;
+use crate::variant::{Variant, variant_map_from_hash_table};
 use frida_sys::{_FridaProcess, FridaSpawnOptions};
 use std::collections::HashMap;
 use std::ffi::{CStr, CString};
-use std::marker::PhantomData;
-use std::os::raw::c_void;
 
 /// Process management in Frida.
-pub struct Process<'a> {
+pub struct Process {
     process_ptr: *mut _FridaProcess,
-    phantom: PhantomData<&'a _FridaProcess>,
+    frida: Frida,
 }
 
-impl<'a> Process<'a> {
-    pub(crate) fn from_raw(process_ptr: *mut _FridaProcess) -> Process<'a> {
-        Process {
-            process_ptr,
-            phantom: PhantomData,
-        }
+// A process is an immutable snapshot taken by frida-core.
+unsafe impl Send for Process {}
+unsafe impl Sync for Process {}
+
+impl Process {
+    pub(crate) fn from_raw(process_ptr: *mut _FridaProcess, frida: Frida) -> Process {
+        Process { process_ptr, frida }
     }
 
     /// Returns the name of the process.
@@ -47,27 +48,26 @@ impl<'a> Process<'a> {
     /// Common keys (host-dependent): `ppid` (Int64), `path` (String),
     /// `user` (String), `started` (String, ISO timestamp).
     pub fn get_parameters(&self) -> HashMap<String, Variant> {
-        let mut out = HashMap::new();
         unsafe {
-            let hash = frida_sys::frida_process_get_parameters(self.process_ptr);
-            if hash.is_null() {
-                return out;
-            }
-            let mut iter: frida_sys::GHashTableIter = std::mem::MaybeUninit::zeroed().assume_init();
-            frida_sys::g_hash_table_iter_init(&mut iter, hash);
-            let mut key: *mut c_void = std::ptr::null_mut();
-            let mut value: *mut c_void = std::ptr::null_mut();
-            while frida_sys::g_hash_table_iter_next(&mut iter, &mut key, &mut value) != 0 {
-                let k = CStr::from_ptr(key.cast()).to_string_lossy().into_owned();
-                let v = Variant::from_ptr(value.cast());
-                out.insert(k, v);
-            }
+            variant_map_from_hash_table(frida_sys::frida_process_get_parameters(self.process_ptr))
         }
-        out
+    }
+
+    /// Returns the parameters of [`get_parameters`](Process::get_parameters) as a
+    /// [`ProcessParameters`].
+    pub fn parameters(&self) -> ProcessParameters {
+        self.get_parameters().into()
+    }
+}
+
+impl Clone for Process {
+    fn clone(&self) -> Self {
+        unsafe { frida_sys::g_object_ref(self.process_ptr as _) };
+        Process::from_raw(self.process_ptr, self.frida.clone())
     }
 }
 
-impl Drop for Process<'_> {
+impl Drop for Process {
     fn drop(&mut self) {
         unsafe { frida_sys::frida_unref(self.process_ptr as _) }
     }
@@ -85,22 +85,23 @@ pub enum SpawnStdio {
 }
 
 /// Process Spawn Options
-pub struct SpawnOptions<'a> {
+pub struct SpawnOptions {
     pub(crate) options_ptr: *mut FridaSpawnOptions,
-    phantom: PhantomData<&'a FridaSpawnOptions>,
 }
 
-impl SpawnOptions<'_> {
-    pub(crate) fn from_raw(options_ptr: *mut FridaSpawnOptions) -> Self {
-        Self {
-            options_ptr,
-            phantom: PhantomData,
-        }
-    }
+// The options are only mutated through the consuming builder methods.
+unsafe impl Send for SpawnOptions {}
+unsafe impl Sync for SpawnOptions {}
 
+impl SpawnOptions {
     /// Create an empty SpawnOptions instance
+    ///
+    /// The options are a plain data object: creating them doesn't initialize the Frida
+    /// runtime.
     pub fn new() -> Self {
-        Self::from_raw(unsafe { frida_sys::frida_spawn_options_new() })
+        Self {
+            options_ptr: unsafe { frida_sys::frida_spawn_options_new() },
+        }
     }
 
     /// Set the argv vector
@@ -188,16 +189,51 @@ impl SpawnOptions<'_> {
         unsafe { frida_sys::frida_spawn_options_set_stdio(self.options_ptr, stdio as _) }
         self
     }
+
+    /// Set an auxiliary, backend-specific option
+    ///
+    /// # Example
+    /// ```no_run
+    ///# use frida::SpawnOptions;
+    /// let options = SpawnOptions::new().aux("aslr", "disable");
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `key` contains a NUL byte or `value` has no GVariant representation
+    /// (see [`Variant::Unsupported`]).
+    pub fn aux<K, V>(self, key: K, value: V) -> Self
+    where
+        K: AsRef<str>,
+        V: Into<Variant>,
+    {
+        self.try_aux(key.as_ref(), &value.into())
+            .expect("aux option must be representable as a GVariant")
+    }
+
+    /// Like [`aux`](Self::aux), but returns `None` instead of panicking.
+    pub(crate) fn try_aux(self, key: &str, value: &Variant) -> Option<Self> {
+        let key = CString::new(key).ok()?;
+        let value = value.to_ptr()?;
+        unsafe {
+            let aux = frida_sys::frida_spawn_options_get_aux(self.options_ptr);
+            frida_sys::g_hash_table_insert(
+                aux,
+                frida_sys::g_strdup(key.as_ptr()) as _,
+                frida_sys::g_variant_ref_sink(value) as _,
+            );
+        }
+        Some(self)
+    }
 }
 
-impl Default for SpawnOptions<'_> {
+impl Default for SpawnOptions {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Drop for SpawnOptions<'_> {
+impl Drop for SpawnOptions {
     fn drop(&mut self) {
-        unsafe { frida_sys::frida_unref(self.options_ptr as _) }
+        unsafe { frida_sys::g_object_unref(self.options_ptr as _) }
     }
 }
@@ -9,13 +9,20 @@ use frida_sys::{
 };
 use serde::Deserialize;
 use serde_json::Value;
-use std::sync::mpsc::{Receiver, Sender, channel};
-use std::{cell::RefCell, marker::PhantomData, rc::Rc};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Sender, channel};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::{
     ffi::{CStr, CString, c_char, c_void},
     ptr::null_mut,
 };
 
+use crate::cancellable::{Cancellable, cancellable_ptr};
+use crate::error::check_gerror;
+use crate::session::Session;
+use crate::signal::connect_handler;
+use crate::sourcemap::{OriginalPosition, ScriptSourceMaps, SourceMap};
 use crate::{Error, Result};
 
 /// Represents a Frida message
@@ -64,6 +71,25 @@ pub struct MessageError {
     pub line_number: usize,
     /// Column number with the error.
     pub column_number: usize,
+    /// Position of the error in the original sources, if the script has a [`SourceMap`]
+    /// for `file_name` covering it.
+    #[serde(skip)]
+    pub original_position: Option<OriginalPosition>,
+    /// `stack` with its frames resolved through the script's source maps, if it has any.
+    #[serde(skip)]
+    pub original_stack: Option<String>,
+}
+
+impl MessageError {
+    /// Resolves the position and stack trace of the error through `source_maps`, each
+    /// position through the map of its file.
+    ///
+    /// This is done automatically for the errors of scripts created with source maps.
+    pub fn resolve(&mut self, source_maps: &ScriptSourceMaps) {
+        self.original_position =
+            source_maps.lookup(&self.file_name, self.line_number, self.column_number);
+        self.original_stack = Some(source_maps.map_stack(&self.stack));
+    }
 }
 
 /// Represents a Message Log Level Types.
@@ -81,46 +107,43 @@ pub enum MessageLogLevel {
     Error,
 }
 
-// PATCH (frida-rust#189): user_data is `*mut CallbackHandler` (set by
-// handle_message). The pre-patch code cast it to `*mut I` and called
-// I::on_message on garbage memory; that only worked when I was a ZST.
-// Route through cb.script_handler (where handle_message actually stored
-// the boxed trait object) instead. The `I` type parameter is now unused
-// but kept so monomorphization still produces a distinct fn pointer per
-// handler type, matching the original ABI.
-unsafe extern "C" fn call_on_message<I: ScriptHandler>(
+unsafe extern "C" fn call_on_message(
     _script_ptr: *mut _FridaScript,
     message: *const i8,
     data: *const frida_sys::_GBytes,
     user_data: *mut c_void,
 ) {
     unsafe {
-        let _ = std::marker::PhantomData::<I>;
+        let router = match (user_data as *const Arc<MessageRouter>).as_ref() {
+            Some(router) => router,
+            None => return,
+        };
 
         let c_msg = CStr::from_ptr(message as *const c_char)
             .to_str()
             .unwrap_or_default();
 
-        let formatted_msg: Message = serde_json::from_str(c_msg).unwrap_or_else(|err| {
+        let mut formatted_msg: Message = serde_json::from_str(c_msg).unwrap_or_else(|err| {
             Message::Other(serde_json::json!({
                 "error": err.to_string(),
                 "data": c_msg
             }))
         });
 
-        let callback_handler: *mut CallbackHandler = user_data as _;
-        let cb = match callback_handler.as_mut() {
-            Some(cb) => cb,
-            None => return,
-        };
-
         match formatted_msg {
             Message::Send(ref msg)
                 if msg.payload.get(0).and_then(|v| v.as_str()) == Some("frida:rpc") =>
             {
-                on_message(cb, formatted_msg);
+                let id = msg.payload.get(1).and_then(|v| v.as_u64());
+                router.reply(id, formatted_msg);
             }
             _ => {
+                if let Message::Error(error) = &mut formatted_msg
+                    && !router.source_maps.is_empty()
+                {
+                    error.resolve(&router.source_maps);
+                }
+
                 // Retrieve extra message data, if any.
                 let data_vec = if data.is_null() {
                     None
@@ -142,79 +165,105 @@ unsafe extern "C" fn call_on_message<I: ScriptHandler>(
                     }
                 };
 
-                if let Some(handler) = cb.script_handler.as_deref_mut() {
-                    handler.on_message(formatted_msg, data_vec);
-                }
+                router.dispatch(formatted_msg, data_vec);
             }
         }
     }
 }
 
-fn on_message(cb_handler: &mut CallbackHandler, message: Message) {
-    let (tx, _) = &cb_handler.channel;
-    let _ = tx.send((message, None));
-}
-
 /// Represents a script signal handler.
+///
+/// The handler is called from the Frida main context, hence the `Send` bound on
+/// [`Script::handle_message`].
 pub trait ScriptHandler {
     /// Handler called when a message is shared from JavaScript to Rust.
     fn on_message(&mut self, message: Message, data: Option<Vec<u8>>);
 }
 
 /// Represents a Frida script.
-pub struct Script<'a> {
+///
+/// Cloning a script takes a new reference to the same underlying object; the clones share
+/// the message handler and RPC state. Each handle keeps the [`Session`] it was created in
+/// alive.
+pub struct Script {
     script_ptr: *mut _FridaScript,
-    rpc_id_counter: Rc<RefCell<usize>>,
-    callback_handler: Rc<RefCell<CallbackHandler>>,
+    session: Session,
+    router: Arc<MessageRouter>,
     ///Exports of the script.
-    pub exports: Exports<'a>,
-    phantom: PhantomData<&'a _FridaScript>,
+    pub exports: Exports,
 }
 
+// frida-core marshals every script operation onto its own main context, and the state
+// shared with the `message` signal handler is synchronized.
+unsafe impl Send for Script {}
+unsafe impl Sync for Script {}
+
 /// This represents the exports of the script.
-pub struct Exports<'a> {
+pub struct Exports {
     script_ptr: *mut _FridaScript,
-    rpc_id_counter: Rc<RefCell<usize>>,
-    callback_handler: Rc<RefCell<CallbackHandler>>,
-    phantom: PhantomData<&'a _FridaScript>,
+    router: Arc<MessageRouter>,
 }
 
-impl Exports<'_> {
-    fn inc_id(&mut self) -> usize {
-        let mut counter_borrow = self.rpc_id_counter.borrow_mut();
-        *counter_borrow += 1;
-        *counter_borrow
+unsafe impl Send for Exports {}
+unsafe impl Sync for Exports {}
+
+impl Script {
+    pub(crate) fn from_raw(
+        script_ptr: *mut _FridaScript,
+        session: Session,
+        source_maps: ScriptSourceMaps,
+    ) -> Result<Script> {
+        let router = MessageRouter {
+            source_maps,
+            ..Default::default()
+        };
+        let script = Script::with_router(script_ptr, session, Arc::new(router));
+        unsafe {
+            connect_handler(
+                script_ptr as _,
+                "message",
+                call_on_message as *mut c_void,
+                script.router.clone(),
+            )?;
+        }
+        Ok(script)
     }
-}
 
-impl<'a> Script<'a> {
-    pub(crate) fn from_raw(script_ptr: *mut _FridaScript) -> Script<'a> {
-        let rpc_counter = Rc::new(RefCell::new(0));
-        let handler = Rc::new(RefCell::new(CallbackHandler::new()));
+    fn with_router(
+        script_ptr: *mut _FridaScript,
+        session: Session,
+        router: Arc<MessageRouter>,
+    ) -> Script {
         Script {
             script_ptr,
-            phantom: PhantomData,
-            rpc_id_counter: rpc_counter.clone(),
-            callback_handler: handler.clone(),
-            exports: Exports {
-                script_ptr,
-                phantom: PhantomData,
-                rpc_id_counter: rpc_counter,
-                callback_handler: handler,
-            },
+            session,
+            router: router.clone(),
+            exports: Exports { script_ptr, router },
         }
     }
 
+    /// Returns the [`Session`] this script was created in.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
     /// Loads the script into the process.
     pub fn load(&self) -> Result<()> {
+        self.load_with_cancellable(None)
+    }
+
+    /// Loads the script into the process, giving up once `cancellable` is cancelled.
+    pub fn load_with_cancellable(&self, cancellable: Option<&Cancellable>) -> Result<()> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
-        unsafe { frida_sys::frida_script_load_sync(self.script_ptr, null_mut(), &mut error) };
+        unsafe {
+            frida_sys::frida_script_load_sync(
+                self.script_ptr,
+                cancellable_ptr(cancellable),
+                &mut error,
+            )
+        };
 
-        if error.is_null() {
-            Ok(())
-        } else {
-            Err(Error::LoadingFailed)
-        }
+        check_gerror(error, Error::LoadingFailed)
     }
 
     /// Unloads the script from the process.
@@ -222,15 +271,15 @@ impl<'a> Script<'a> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
         unsafe { frida_sys::frida_script_unload_sync(self.script_ptr, null_mut(), &mut error) };
 
-        if error.is_null() {
-            Ok(())
-        } else {
-            Err(Error::UnloadingFailed)
-        }
+        check_gerror(error, Error::UnloadingFailed)
     }
 
     /// Handles the `message` signal for the script and wraps into [`ScriptHandler`].
     ///
+    /// Replaces the handler set by a previous call, on this script or any of its clones.
+    /// RPC replies are consumed by [`list_exports`](Script::list_exports) and
+    /// [`Exports::call`] and never reach the handler.
+    ///
     /// # Example
     ///
     /// ```
@@ -245,28 +294,8 @@ impl<'a> Script<'a> {
     ///     }
     /// }
     /// ```
-    pub fn handle_message<I: ScriptHandler + 'static>(&mut self, handler: I) -> Result<()> {
-        let message = CString::new("message").map_err(|_| Error::CStringFailed)?;
-        let mut borrowed_callback_handler = self.callback_handler.borrow_mut();
-        (*borrowed_callback_handler).add_handler(handler);
-        let user_data =
-            (&(*borrowed_callback_handler) as *const _ as *mut CallbackHandler) as *mut c_void;
-        unsafe {
-            let callback = Some(std::mem::transmute::<
-                *mut std::ffi::c_void,
-                unsafe extern "C" fn(),
-            >(call_on_message::<I> as *mut c_void));
-
-            frida_sys::g_signal_connect_data(
-                self.script_ptr as _,
-                message.as_ptr(),
-                callback,
-                user_data,
-                None,
-                0,
-            )
-        };
-
+    pub fn handle_message<I: ScriptHandler + Send + 'static>(&self, handler: I) -> Result<()> {
+        *lock(&self.router.script_handler) = Some(Box::new(handler));
         Ok(())
     }
 
@@ -274,45 +303,18 @@ impl<'a> Script<'a> {
     ///
     /// NOTE: `message` must be valid JSON otherwise the script will throw a SyntaxError
     pub fn post<S: AsRef<str>>(&self, message: S, data: Option<&[u8]>) -> Result<()> {
-        let message = CString::new(message.as_ref()).map_err(|_| Error::CStringFailed)?;
-
-        unsafe {
-            let g_data = if let Some(data) = data {
-                g_bytes_new(data.as_ptr() as _, data.len() as _)
-            } else {
-                std::ptr::null_mut()
-            };
-            frida_sys::frida_script_post(self.script_ptr as _, message.as_ptr() as _, g_data);
-            g_bytes_unref(g_data);
-        }
-
-        Ok(())
-    }
-
-    fn inc_id(&mut self) -> usize {
-        let mut counter_borrow = self.rpc_id_counter.borrow_mut();
-        *counter_borrow += 1;
-        *counter_borrow
+        post_message(self.script_ptr, message.as_ref(), data)
     }
 
     /// List all the exported attributes from the script's rpc
-    pub fn list_exports(&mut self) -> Result<Vec<String>> {
-        let json_req = {
-            let name = "frida:rpc".into();
-            let id = self.inc_id().into();
-            let rpc_type = "list".into();
-            let rpc_function = Value::Null;
-            let args = Value::Null;
-
-            let rpc_query: [Value; 5] = [name, id, rpc_type, rpc_function, args];
-
-            serde_json::to_string(&rpc_query).unwrap()
-        };
-
-        self.post(&json_req, None).unwrap();
-        let borrowed_callback_handler = self.callback_handler.borrow();
-        let (_, rx) = &borrowed_callback_handler.channel;
-        let (rpc_result, _) = rx.recv().unwrap();
+    pub fn list_exports(&self) -> Result<Vec<String>> {
+        let rpc_result = rpc_request(
+            self.script_ptr,
+            &self.router,
+            "list",
+            Value::Null,
+            Value::Null,
+        )?;
 
         let func_list: Vec<String> = match rpc_result {
             Message::Send(r) => {
@@ -336,33 +338,19 @@ impl<'a> Script<'a> {
     }
 }
 
-impl Exports<'_> {
+impl Exports {
     /// Run exported functions from a Frida script.
-    pub fn call(&mut self, function_name: &str, args: Option<Value>) -> Result<Option<Value>> {
-        let json_req: String = {
-            let name = "frida:rpc";
-            let id = self.inc_id();
-            let rpc_type = "call";
-
-            let args: String = match args {
-                Some(a) => serde_json::to_string(&a).unwrap(),
-                None => "[]".into(),
-            };
-
-            format!("[\"{name}\", {id}, \"{rpc_type}\", \"{function_name}\", {args}]",)
-        };
-
-        let message = CString::new(json_req.as_str()).map_err(|_| Error::CStringFailed)?;
-
-        unsafe {
-            let g_data = std::ptr::null_mut();
-            frida_sys::frida_script_post(self.script_ptr as _, message.as_ptr() as _, g_data);
-            g_bytes_unref(g_data);
-        }
-
-        let borrowed_callback_handler = self.callback_handler.borrow();
-        let (_, rx) = &borrowed_callback_handler.channel;
-        let (rpc_result, _) = rx.recv().unwrap();
+    ///
+    /// Calls may be issued concurrently from several threads; each one waits for its own
+    /// reply.
+    pub fn call(&self, function_name: &str, args: Option<Value>) -> Result<Option<Value>> {
+        let rpc_result = rpc_request(
+            self.script_ptr,
+            &self.router,
+            "call",
+            function_name.into(),
+            args.unwrap_or_else(|| Value::Array(Vec::new())),
+        )?;
 
         match rpc_result {
             Message::Send(r) => {
@@ -385,7 +373,22 @@ impl Exports<'_> {
                             "RPC call failed. Result is not ok and no error message provided.",
                         )
                         .to_string();
-                    Err(Error::RpcJsError { message: err_msg })
+                    let stack = r
+                        .payload
+                        .get(4)
+                        .and_then(|v| v.get(1))
+                        .and_then(|v| v.as_str())
+                        .map(str::to_owned);
+                    let source_maps = &self.router.source_maps;
+                    let original_stack = stack
+                        .as_deref()
+                        .filter(|_| !source_maps.is_empty())
+                        .map(|stack| source_maps.map_stack(stack));
+                    Err(Error::RpcJsError {
+                        message: err_msg,
+                        stack,
+                        original_stack,
+                    })
                 }
             }
             _ => Err(Error::RpcUnexpectedMessage),
@@ -393,13 +396,67 @@ impl Exports<'_> {
     }
 }
 
-impl Drop for Script<'_> {
+impl Clone for Script {
+    fn clone(&self) -> Self {
+        unsafe { frida_sys::g_object_ref(self.script_ptr as _) };
+        Script::with_router(self.script_ptr, self.session.clone(), self.router.clone())
+    }
+}
+
+impl Drop for Script {
     fn drop(&mut self) {
         unsafe { frida_sys::frida_unref(self.script_ptr as _) }
     }
 }
 
+fn post_message(script_ptr: *mut _FridaScript, message: &str, data: Option<&[u8]>) -> Result<()> {
+    let message = CString::new(message).map_err(|_| Error::CStringFailed)?;
+
+    unsafe {
+        let g_data = if let Some(data) = data {
+            g_bytes_new(data.as_ptr() as _, data.len() as _)
+        } else {
+            std::ptr::null_mut()
+        };
+        frida_sys::frida_script_post(script_ptr as _, message.as_ptr() as _, g_data);
+        if !g_data.is_null() {
+            g_bytes_unref(g_data);
+        }
+    }
+
+    Ok(())
+}
+
+/// Posts a `frida:rpc` request and blocks until the matching reply arrives.
+fn rpc_request(
+    script_ptr: *mut _FridaScript,
+    router: &MessageRouter,
+    rpc_type: &str,
+    rpc_function: Value,
+    args: Value,
+) -> Result<Message> {
+    let id = router.rpc_id_counter.fetch_add(1, Ordering::Relaxed) + 1;
+    let rpc_query: [Value; 5] = [
+        "frida:rpc".into(),
+        id.into(),
+        rpc_type.into(),
+        rpc_function,
+        args,
+    ];
+    let json_req = serde_json::to_string(&rpc_query).map_err(|_| Error::RpcUnexpectedMessage)?;
+
+    let (tx, rx) = channel();
+    lock(&router.pending_rpc).insert(id, tx);
+    if let Err(err) = post_message(script_ptr, &json_req, None) {
+        lock(&router.pending_rpc).remove(&id);
+        return Err(err);
+    }
+
+    rx.recv().map_err(|_| Error::RpcUnexpectedMessage)
+}
+
 /// The JavaScript runtime of Frida.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ScriptRuntime {
     /// Default Frida runtime.
     Default,
@@ -422,13 +479,17 @@ impl From<ScriptRuntime> for frida_sys::FridaScriptRuntime {
 /// Represents options passed to the Frida script registrar.
 pub struct ScriptOption {
     ptr: *mut FridaScriptOptions,
+    source_map: Option<SourceMap>,
 }
 
 impl ScriptOption {
     /// Create a new set of script options.
     pub fn new() -> Self {
         let ptr = unsafe { frida_sys::frida_script_options_new() };
-        Self { ptr }
+        Self {
+            ptr,
+            source_map: None,
+        }
     }
 
     /// Get the name of the script.
@@ -438,8 +499,12 @@ impl ScriptOption {
     }
 
     /// Set the name of the script.
+    ///
+    /// # Panics
+    /// Panics if `name` contains a NUL byte.
     pub fn set_name(self, name: &str) -> Self {
-        unsafe { frida_sys::frida_script_options_set_name(self.ptr, name.as_ptr() as _) };
+        let name = CString::new(name).unwrap();
+        unsafe { frida_sys::frida_script_options_set_name(self.ptr, name.as_ptr()) };
         self
     }
 
@@ -449,6 +514,33 @@ impl ScriptOption {
         self
     }
 
+    /// Set the source map of the script, used to resolve the positions of its errors to
+    /// the original sources.
+    ///
+    /// The map applies to the file Frida names after the script, `/<name>.js`, so the
+    /// script must be given a name with [`set_name`](Self::set_name). Without a map, the
+    /// one embedded in the source as an inline `sourceMappingURL`, if any, is used. The
+    /// maps of a bundle built by [`Compiler`](crate::Compiler) are read from the bundle
+    /// instead, see [`ScriptSourceMaps`].
+    pub fn set_source_map(mut self, source_map: SourceMap) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+
+    pub(crate) fn source_map(&self) -> Option<SourceMap> {
+        self.source_map.clone()
+    }
+
+    /// The name of the script, if set.
+    pub(crate) fn name(&self) -> Option<String> {
+        let name = unsafe { frida_sys::frida_script_options_get_name(self.ptr) };
+        (!name.is_null()).then(|| {
+            unsafe { CStr::from_ptr(name) }
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+
     pub(crate) fn as_mut_ptr(&mut self) -> *mut FridaScriptOptions {
         self.ptr
     }
@@ -470,22 +562,30 @@ impl Drop for ScriptOption {
     }
 }
 
-type MsgSender = Sender<(Message, Option<Vec<u8>>)>;
-type MsgReceiver = Receiver<(Message, Option<Vec<u8>>)>;
-struct CallbackHandler {
-    channel: (MsgSender, MsgReceiver),
-    script_handler: Option<Box<dyn ScriptHandler>>,
+/// State shared by the handles of a script and its `message` signal handler.
+#[derive(Default)]
+struct MessageRouter {
+    rpc_id_counter: AtomicUsize,
+    pending_rpc: Mutex<HashMap<usize, Sender<Message>>>,
+    script_handler: Mutex<Option<Box<dyn ScriptHandler + Send>>>,
+    source_maps: ScriptSourceMaps,
 }
 
-impl CallbackHandler {
-    fn new() -> Self {
-        Self {
-            channel: channel(),
-            script_handler: None,
+impl MessageRouter {
+    fn reply(&self, id: Option<u64>, message: Message) {
+        let pending = id.and_then(|id| lock(&self.pending_rpc).remove(&(id as usize)));
+        if let Some(tx) = pending {
+            let _ = tx.send(message);
         }
     }
 
-    fn add_handler<I: ScriptHandler + 'static>(&mut self, handler: I) {
-        self.script_handler = Some(Box::from(handler));
+    fn dispatch(&self, message: Message, data: Option<Vec<u8>>) {
+        if let Some(handler) = lock(&self.script_handler).as_deref_mut() {
+            handler.on_message(message, data);
+        }
     }
 }
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
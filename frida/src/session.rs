@@ -5,41 +5,68 @@
  */
 
 use frida_sys::{_FridaSession, g_bytes_get_data, g_bytes_new, g_bytes_unref, gsize};
-use std::ffi::CString;
-use std::marker::PhantomData;
+use std::ffi::{CString, c_void};
 use std::ptr::null_mut;
+use std::sync::mpsc::Sender;
 
+use crate::cancellable::{Cancellable, cancellable_ptr};
+use crate::crash::Crash;
+use crate::device::Device;
+use crate::error::check_gerror;
 use crate::script::{Script, ScriptOption};
+use crate::signal::connect_handler;
+use crate::sourcemap::ScriptSourceMaps;
 use crate::{Error, Result};
 
 /// Represents a Frida session.
-pub struct Session<'a> {
+///
+/// Cloning a session takes a new reference to the same underlying object. Each handle
+/// keeps the [`Device`] it was attached through alive.
+pub struct Session {
     session_ptr: *mut _FridaSession,
-    phantom: PhantomData<&'a _FridaSession>,
+    device: Device,
 }
 
-impl<'a> Session<'a> {
-    pub(crate) fn from_raw(session_ptr: *mut _FridaSession) -> Session<'a> {
+// frida-core marshals every session operation onto its own main context.
+unsafe impl Send for Session {}
+unsafe impl Sync for Session {}
+
+impl Session {
+    pub(crate) fn from_raw(session_ptr: *mut _FridaSession, device: Device) -> Session {
         Session {
             session_ptr,
-            phantom: PhantomData,
+            device,
         }
     }
 
+    /// Returns the [`Device`] this session was attached through.
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
     /// Returns if the session is detached or not.
     pub fn is_detached(&self) -> bool {
         unsafe { frida_sys::frida_session_is_detached(self.session_ptr) == 1 }
     }
 
     /// Creates a [`Script`] attached to current session.
-    pub fn create_script<'b>(
-        &'a self,
+    pub fn create_script(&self, source: &str, option: &mut ScriptOption) -> Result<Script> {
+        self.create_script_with_cancellable(source, option, None)
+    }
+
+    /// Creates a [`Script`] attached to current session, giving up once `cancellable` is
+    /// cancelled.
+    pub fn create_script_with_cancellable(
+        &self,
         source: &str,
         option: &mut ScriptOption,
-    ) -> Result<Script<'b>>
-    where
-        'a: 'b,
-    {
+        cancellable: Option<&Cancellable>,
+    ) -> Result<Script> {
+        let source_maps = ScriptSourceMaps::for_script(
+            option.name().as_deref(),
+            Some(source),
+            option.source_map(),
+        );
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
         match CString::new(source) {
             Ok(source) => {
@@ -48,15 +75,12 @@ impl<'a> Session<'a> {
                         self.session_ptr,
                         source.as_ptr(),
                         option.as_mut_ptr(),
-                        null_mut(),
+                        cancellable_ptr(cancellable),
                         &mut error,
                     )
                 };
-                if error.is_null() {
-                    Ok(Script::from_raw(script))
-                } else {
-                    Err(Error::ScriptCreationError)
-                }
+                check_gerror(error, Error::ScriptCreationError)?;
+                Script::from_raw(script, self.clone(), source_maps)
             }
             Err(_) => Err(Error::CStringFailed),
         }
@@ -69,14 +93,11 @@ impl<'a> Session<'a> {
     /// Note: bytecode is runtime-specific. A blob produced with the QJS
     /// runtime won't load under V8 and vice-versa — make sure the bytecode
     /// was compiled with the same `ScriptRuntime` you pass here.
-    pub fn create_script_from_bytes<'b>(
-        &'a self,
+    pub fn create_script_from_bytes(
+        &self,
         bytes: &[u8],
         option: &mut ScriptOption,
-    ) -> Result<Script<'b>>
-    where
-        'a: 'b,
-    {
+    ) -> Result<Script> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
         let script = unsafe {
             let g = g_bytes_new(bytes.as_ptr() as _, bytes.len() as _);
@@ -90,11 +111,10 @@ impl<'a> Session<'a> {
             g_bytes_unref(g);
             s
         };
-        if error.is_null() {
-            Ok(Script::from_raw(script))
-        } else {
-            Err(Error::ScriptCreationError)
-        }
+        check_gerror(error, Error::ScriptCreationError)?;
+        let source_maps =
+            ScriptSourceMaps::for_script(option.name().as_deref(), None, option.source_map());
+        Script::from_raw(script, self.clone(), source_maps)
     }
 
     /// Compile JS source to V8/QJS bytecode. Runtime is taken from `option`
@@ -111,9 +131,7 @@ impl<'a> Session<'a> {
                 null_mut(),
                 &mut error,
             );
-            if !error.is_null() {
-                return Err(Error::ScriptCreationError);
-            }
+            check_gerror(error, Error::ScriptCreationError)?;
             let mut len: gsize = 0;
             let raw = g_bytes_get_data(g, &mut len) as *const u8;
             let out = if raw.is_null() || len == 0 {
@@ -126,6 +144,22 @@ impl<'a> Session<'a> {
         }
     }
 
+    /// Handles the `detached` signal for the session and wraps into [`DetachHandler`].
+    ///
+    /// The handler is told why the session ended and, when the target process crashed,
+    /// receives the [`Crash`] details. It is called from the Frida main context.
+    pub fn handle_detached<H: DetachHandler + Send + 'static>(&self, handler: H) -> Result<()> {
+        unsafe {
+            connect_handler(
+                self.session_ptr as _,
+                "detached",
+                call_on_detached::<H> as *mut c_void,
+                handler,
+            )?;
+        }
+        Ok(())
+    }
+
     /// Detaches the current session.
     pub fn detach(&self) -> Result<()> {
         let mut error: *mut frida_sys::GError = std::ptr::null_mut();
@@ -133,16 +167,92 @@ impl<'a> Session<'a> {
             frida_sys::frida_session_detach_sync(self.session_ptr, std::ptr::null_mut(), &mut error)
         }
 
-        if error.is_null() {
-            Ok(())
-        } else {
-            Err(Error::SessionDetachError)
-        }
+        check_gerror(error, Error::SessionDetachError)
     }
 }
 
-impl Drop for Session<'_> {
+impl Clone for Session {
+    fn clone(&self) -> Self {
+        unsafe { frida_sys::g_object_ref(self.session_ptr as _) };
+        Session::from_raw(self.session_ptr, self.device.clone())
+    }
+}
+
+impl Drop for Session {
     fn drop(&mut self) {
         unsafe { frida_sys::frida_unref(self.session_ptr as _) }
     }
 }
+
+/// Reason for a session being detached.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SessionDetachReason {
+    /// The session was detached on request.
+    ApplicationRequested,
+    /// The target process was replaced, e.g. by `exec()`.
+    ProcessReplaced,
+    /// The target process terminated or crashed.
+    ProcessTerminated,
+    /// The connection to the device was terminated.
+    ConnectionTerminated,
+    /// The device was lost.
+    DeviceLost,
+    /// A reason added in a later version of frida-core, by its raw value.
+    Unknown(u32),
+}
+
+impl From<frida_sys::FridaSessionDetachReason> for SessionDetachReason {
+    fn from(value: frida_sys::FridaSessionDetachReason) -> Self {
+        match value {
+            frida_sys::FridaSessionDetachReason_FRIDA_SESSION_DETACH_REASON_APPLICATION_REQUESTED => {
+                Self::ApplicationRequested
+            }
+            frida_sys::FridaSessionDetachReason_FRIDA_SESSION_DETACH_REASON_PROCESS_REPLACED => {
+                Self::ProcessReplaced
+            }
+            frida_sys::FridaSessionDetachReason_FRIDA_SESSION_DETACH_REASON_PROCESS_TERMINATED => {
+                Self::ProcessTerminated
+            }
+            frida_sys::FridaSessionDetachReason_FRIDA_SESSION_DETACH_REASON_CONNECTION_TERMINATED => {
+                Self::ConnectionTerminated
+            }
+            frida_sys::FridaSessionDetachReason_FRIDA_SESSION_DETACH_REASON_DEVICE_LOST => {
+                Self::DeviceLost
+            }
+            // Raised from the `detached` signal handler, which must not panic.
+            value => Self::Unknown(value as _),
+        }
+    }
+}
+
+/// Represents a session `detached` signal handler.
+pub trait DetachHandler {
+    /// Handler called when the session is detached. `crash` is set when the target
+    /// process crashed.
+    fn on_detached(&mut self, reason: SessionDetachReason, crash: Option<Crash>);
+}
+
+impl DetachHandler for Sender<(SessionDetachReason, Option<Crash>)> {
+    fn on_detached(&mut self, reason: SessionDetachReason, crash: Option<Crash>) {
+        let _ = self.send((reason, crash));
+    }
+}
+
+unsafe extern "C" fn call_on_detached<H: DetachHandler>(
+    _session_ptr: *mut _FridaSession,
+    reason: frida_sys::FridaSessionDetachReason,
+    crash: *mut frida_sys::_FridaCrash,
+    user_data: *mut c_void,
+) {
+    unsafe {
+        let crash = if crash.is_null() {
+            None
+        } else {
+            Some(Crash::from_raw(crash))
+        };
+        if let Some(handler) = (user_data as *mut H).as_mut() {
+            handler.on_detached(reason.into(), crash);
+        }
+    }
+}
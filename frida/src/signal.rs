@@ -0,0 +1,41 @@
+use std::ffi::{CString, c_void};
+
+use crate::{Error, Result};
+
+/// Connects `callback` to `signal` on a GObject `instance`, handing it `handler` as user data.
+///
+/// The handler is boxed and owned by the signal closure: it is dropped by GLib once the
+/// handler is disconnected or the instance is finalized.
+pub(crate) unsafe fn connect_handler<H: Send + 'static>(
+    instance: *mut c_void,
+    signal: &str,
+    callback: *mut c_void,
+    handler: H,
+) -> Result<frida_sys::gulong> {
+    let signal = CString::new(signal).map_err(|_| Error::CStringFailed)?;
+    let user_data = Box::into_raw(Box::new(handler)) as *mut c_void;
+    unsafe {
+        let callback = Some(std::mem::transmute::<
+            *mut std::ffi::c_void,
+            unsafe extern "C" fn(),
+        >(callback));
+
+        Ok(frida_sys::g_signal_connect_data(
+            instance as _,
+            signal.as_ptr(),
+            callback,
+            user_data,
+            Some(destroy_handler::<H>),
+            0,
+        ))
+    }
+}
+
+unsafe extern "C" fn destroy_handler<H>(
+    user_data: frida_sys::gpointer,
+    _closure: *mut frida_sys::GClosure,
+) {
+    unsafe {
+        drop(Box::from_raw(user_data as *mut H));
+    }
+}
@@ -0,0 +1,410 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::{Error, Result};
+
+/// A [source map](https://sourcemaps.info/spec.html) (revision 3), used to resolve
+/// positions in a compiled script back to its original sources.
+///
+/// Attach one to a script with [`ScriptOption::set_source_map`](crate::ScriptOption::set_source_map),
+/// or embed it in the source as an inline `//# sourceMappingURL=data:...` comment. Errors
+/// reported by the script then carry the original positions, see
+/// [`MessageError`](crate::MessageError) and [`Error::RpcJsError`]. The maps of a bundle
+/// built by [`Compiler`](crate::Compiler) are read from the bundle, see
+/// [`ScriptSourceMaps`].
+///
+/// Lines and columns are 1-based, like the ones reported by the JavaScript runtimes.
+#[derive(Clone, Debug)]
+pub struct SourceMap {
+    sources: Vec<String>,
+    names: Vec<String>,
+    lines: Vec<Vec<Mapping>>,
+}
+
+/// A position in the original sources of a script.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OriginalPosition {
+    /// Path or URL of the original source file.
+    pub source: String,
+    /// Line in the original source, 1-based.
+    pub line: usize,
+    /// Column in the original source, 1-based.
+    pub column: usize,
+    /// Original name of the symbol at this position, if recorded.
+    pub name: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Mapping {
+    generated_column: i64,
+    original: Option<Original>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Original {
+    source: i64,
+    line: i64,
+    column: i64,
+    name: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSourceMap {
+    version: u32,
+    #[serde(default)]
+    source_root: Option<String>,
+    sources: Vec<Option<String>>,
+    #[serde(default)]
+    names: Vec<String>,
+    mappings: String,
+}
+
+impl SourceMap {
+    /// Parses a JSON-encoded source map. Index maps (with `sections`) are not supported.
+    pub fn parse(json: &str) -> Result<SourceMap> {
+        let raw: RawSourceMap =
+            serde_json::from_str(json).map_err(|e| Error::InvalidSourceMap(e.to_string()))?;
+        if raw.version != 3 {
+            return Err(Error::InvalidSourceMap(format!(
+                "unsupported version {}",
+                raw.version
+            )));
+        }
+
+        let source_root = raw
+            .source_root
+            .filter(|root| !root.is_empty())
+            .map(|root| format!("{}/", root.trim_end_matches('/')));
+        let sources = raw
+            .sources
+            .into_iter()
+            .map(|source| {
+                let source = source.unwrap_or_default();
+                match &source_root {
+                    Some(root) if !source.contains("://") && !source.starts_with('/') => {
+                        format!("{root}{source}")
+                    }
+                    _ => source,
+                }
+            })
+            .collect();
+
+        Ok(SourceMap {
+            sources,
+            names: raw.names,
+            lines: decode_mappings(&raw.mappings)?,
+        })
+    }
+
+    /// Extracts the source map embedded in `source` as an inline
+    /// `//# sourceMappingURL=data:application/json;base64,...` comment.
+    ///
+    /// Returns `None` if there is no such comment, if it refers to an external file, or if
+    /// the embedded map is invalid.
+    pub fn from_inline(source: &str) -> Option<SourceMap> {
+        SourceMap::from_data_url(source_mapping_url(source)?)
+    }
+
+    fn from_data_url(url: &str) -> Option<SourceMap> {
+        let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+        let json = if header.ends_with(";base64") {
+            String::from_utf8(decode_base64(data)?).ok()?
+        } else {
+            data.to_string()
+        };
+        SourceMap::parse(&json).ok()
+    }
+
+    /// Returns the original position of the generated `line` and `column`, if mapped.
+    pub fn lookup(&self, line: usize, column: usize) -> Option<OriginalPosition> {
+        let segments = self.lines.get(line.checked_sub(1)?)?;
+        let column = i64::try_from(column.checked_sub(1)?).ok()?;
+        let index = segments.partition_point(|m| m.generated_column <= column);
+        let original = segments.get(index.checked_sub(1)?)?.original?;
+
+        Some(OriginalPosition {
+            source: self
+                .sources
+                .get(usize::try_from(original.source).ok()?)?
+                .clone(),
+            line: usize::try_from(original.line).ok()? + 1,
+            column: usize::try_from(original.column).ok()? + 1,
+            name: original
+                .name
+                .and_then(|name| self.names.get(usize::try_from(name).ok()?).cloned()),
+        })
+    }
+}
+
+/// The source maps of a script, by the name of the generated file they map.
+///
+/// Frida names the file of a plain script after the script, e.g. `/agent.js` for a script
+/// named `agent`. A bundle built by [`Compiler`](crate::Compiler) holds a file per module
+/// instead, such as `/index.js`, each with its own map when built with
+/// [`SourceMaps::Included`](crate::SourceMaps::Included).
+///
+/// Only positions in a file with a map are resolved, so that frames of other modules and
+/// of Frida's own runtime are left as they are.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptSourceMaps {
+    maps: HashMap<String, SourceMap>,
+}
+
+impl ScriptSourceMaps {
+    /// Creates an empty set of source maps.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the source maps of a script named `name` whose source is `source`.
+    ///
+    /// The maps of a bundle are read from the bundle. Otherwise, `source_map`, or else the
+    /// map inline in `source`, maps the file of the script.
+    pub(crate) fn for_script(
+        name: Option<&str>,
+        source: Option<&str>,
+        source_map: Option<SourceMap>,
+    ) -> Self {
+        if let Some(maps) = source.and_then(Self::from_bundle) {
+            return maps;
+        }
+        let mut maps = Self::new();
+        if let (Some(name), Some(map)) =
+            (name, source_map.or_else(|| SourceMap::from_inline(source?)))
+        {
+            maps.insert(format!("/{name}.js"), map);
+        }
+        maps
+    }
+
+    /// Reads the source maps of a bundle built by [`Compiler`](crate::Compiler).
+    ///
+    /// The map of each module is the one its `sourceMappingURL` refers to, either inline or
+    /// another file of the bundle, defaulting to the file named after the module with a
+    /// `.map` extension. Returns `None` if `source` isn't a bundle.
+    pub fn from_bundle(source: &str) -> Option<Self> {
+        let files = bundle_files(source)?;
+        let mut maps = Self::new();
+        for (name, contents) in &files {
+            if name.ends_with(".map") {
+                continue;
+            }
+            let map = match source_mapping_url(contents) {
+                Some(url) if url.starts_with("data:") => SourceMap::from_data_url(url),
+                url => {
+                    let path = match url {
+                        Some(url) if url.starts_with('/') => url.to_string(),
+                        Some(url) => format!("{}{url}", &name[..=name.rfind('/').unwrap_or(0)]),
+                        None => format!("{name}.map"),
+                    };
+                    files
+                        .iter()
+                        .find(|(name, _)| *name == path)
+                        .and_then(|(_, json)| SourceMap::parse(json).ok())
+                }
+            };
+            if let Some(map) = map {
+                maps.insert(*name, map);
+            }
+        }
+        Some(maps)
+    }
+
+    /// Sets the map of the generated `file`, replacing its previous one.
+    pub fn insert(&mut self, file: impl Into<String>, source_map: SourceMap) {
+        self.maps.insert(file.into(), source_map);
+    }
+
+    /// Returns the map of the generated `file`, which may be given as a `file://` URL.
+    pub fn get(&self, file: &str) -> Option<&SourceMap> {
+        self.maps.get(file.strip_prefix("file://").unwrap_or(file))
+    }
+
+    /// Returns whether no file has a map.
+    pub fn is_empty(&self) -> bool {
+        self.maps.is_empty()
+    }
+
+    /// Returns the original position of the generated `line` and `column` of `file`, if
+    /// mapped.
+    pub fn lookup(&self, file: &str, line: usize, column: usize) -> Option<OriginalPosition> {
+        self.get(file)?.lookup(line, column)
+    }
+
+    /// Rewrites the `file:line:column` positions of a JavaScript stack trace to their
+    /// original positions.
+    ///
+    /// Frames of files without a map, or without a mapping for their position, are kept as
+    /// they are.
+    pub fn map_stack(&self, stack: &str) -> String {
+        stack
+            .lines()
+            .map(|frame| self.map_frame(frame).unwrap_or_else(|| frame.to_string()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn map_frame(&self, frame: &str) -> Option<String> {
+        let frame = frame.trim_end();
+        let (location, suffix) = match frame.strip_suffix(')') {
+            Some(location) => (location, ")"),
+            None => (frame, ""),
+        };
+        let (rest, column) = location.rsplit_once(':')?;
+        let (rest, line) = rest.rsplit_once(':')?;
+        let file_start = rest.rfind(['(', ' ']).map_or(0, |i| i + 1);
+
+        let original = self.lookup(
+            &rest[file_start..],
+            line.parse().ok()?,
+            column.parse().ok()?,
+        )?;
+        Some(format!(
+            "{}{}:{}:{}{}",
+            &rest[..file_start],
+            original.source,
+            original.line,
+            original.column,
+            suffix
+        ))
+    }
+}
+
+/// Returns the URL of the last `sourceMappingURL` comment of `source`.
+fn source_mapping_url(source: &str) -> Option<&str> {
+    source.lines().rev().find_map(|line| {
+        let line = line.trim();
+        line.strip_prefix("//# sourceMappingURL=")
+            .or_else(|| line.strip_prefix("//@ sourceMappingURL="))
+            .map(str::trim)
+    })
+}
+
+/// Splits a bundle built by frida's compiler into its files, by name.
+///
+/// A bundle starts with a `📦` line, followed by a `<size> <name>` line per file, where
+/// `size` is in bytes, and `↻ <alias>` lines naming aliases of the previous file. The
+/// contents of the files follow, in the same order, each preceded by a `✄` line.
+fn bundle_files(source: &str) -> Option<Vec<(&str, &str)>> {
+    const SEPARATOR: &str = "\n✄\n";
+
+    let (header, mut body) = source.strip_prefix("📦\n")?.split_once(SEPARATOR)?;
+    let mut files = Vec::new();
+    for entry in header.lines().filter(|line| !line.starts_with("↻ ")) {
+        let (size, name) = entry.split_once(' ')?;
+        let size = size.parse().ok()?;
+        if !files.is_empty() {
+            body = body.strip_prefix(SEPARATOR)?;
+        }
+        files.push((name, body.get(..size)?));
+        body = &body[size..];
+    }
+    Some(files)
+}
+
+fn decode_mappings(mappings: &str) -> Result<Vec<Vec<Mapping>>> {
+    let mut lines = Vec::new();
+    let (mut source, mut line, mut column, mut name) = (0, 0, 0, 0);
+
+    for generated_line in mappings.split(';') {
+        let mut generated_column = 0;
+        let mut segments = Vec::new();
+        for segment in generated_line.split(',').filter(|s| !s.is_empty()) {
+            let fields = decode_vlq(segment)?;
+            generated_column += fields[0];
+            let original = match fields.len() {
+                1 => None,
+                4 | 5 => {
+                    source += fields[1];
+                    line += fields[2];
+                    column += fields[3];
+                    let name = fields.get(4).map(|delta| {
+                        name += delta;
+                        name
+                    });
+                    Some(Original {
+                        source,
+                        line,
+                        column,
+                        name,
+                    })
+                }
+                n => {
+                    return Err(Error::InvalidSourceMap(format!(
+                        "mapping segment with {n} fields"
+                    )));
+                }
+            };
+            segments.push(Mapping {
+                generated_column,
+                original,
+            });
+        }
+        segments.sort_by_key(|m| m.generated_column);
+        lines.push(segments);
+    }
+
+    Ok(lines)
+}
+
+/// Decodes the base64 VLQ values of a non-empty mapping segment.
+fn decode_vlq(segment: &str) -> Result<Vec<i64>> {
+    let invalid = || Error::InvalidSourceMap(format!("invalid mapping segment {segment:?}"));
+    let mut values = Vec::new();
+    let (mut value, mut shift) = (0i64, 0);
+
+    for c in segment.bytes() {
+        let digit = i64::from(base64_value(c).ok_or_else(invalid)?);
+        value += (digit & 0b11111) << shift;
+        if digit & 0b100000 != 0 {
+            shift += 5;
+            if shift > 55 {
+                return Err(invalid());
+            }
+        } else {
+            values.push(if value & 1 == 1 {
+                -(value >> 1)
+            } else {
+                value >> 1
+            });
+            (value, shift) = (0, 0);
+        }
+    }
+
+    if shift != 0 || values.is_empty() {
+        return Err(invalid());
+    }
+    Ok(values)
+}
+
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() / 4 * 3);
+    let (mut buffer, mut bits) = (0u32, 0);
+
+    for c in data.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        if c == b'=' {
+            break;
+        }
+        buffer = (buffer << 6) | u32::from(base64_value(c)?);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(out)
+}
+
+fn base64_value(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    }
+}
@@ -1,8 +1,13 @@
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
 use std::collections::HashMap;
 use std::ffi::{CStr, CString};
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone)]
 /// GVariant types used by Frida
+///
+/// Doubles compare by their bit pattern, so that `Variant` can be `Eq`: a NaN equals
+/// itself, and `0.0` differs from `-0.0`.
 pub enum Variant {
     /// String
     String(String),
@@ -13,18 +18,53 @@ pub enum Variant {
     /// Integer value
     Int64(i64),
 
+    /// Double precision floating point value
+    Double(f64),
+
+    /// Byte array (`ay`), e.g. the image data of process icons
+    ByteArray(Vec<u8>),
+
+    /// Array of strings (`as`)
+    StringArray(Vec<String>),
+
+    /// Tuple of heterogeneous values
+    Tuple(Vec<Variant>),
+
+    /// A boxed value (`v`)
+    Variant(Box<Variant>),
+
     /// Map
     Map(HashMap<String, Variant>),
 
     /// Array of Maps
     MapList(Vec<HashMap<String, Variant>>),
 
-    /// GVariant type signatures we don't decode (e.g. "ay" byte arrays
-    /// returned by frida for process icons). The string holds the original
+    /// GVariant type signatures we don't decode. The string holds the original
     /// type signature so callers can identify and skip.
     Unsupported(String),
 }
 
+impl PartialEq for Variant {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Boolean(a), Self::Boolean(b)) => a == b,
+            (Self::Int64(a), Self::Int64(b)) => a == b,
+            (Self::Double(a), Self::Double(b)) => a.to_bits() == b.to_bits(),
+            (Self::ByteArray(a), Self::ByteArray(b)) => a == b,
+            (Self::StringArray(a), Self::StringArray(b)) => a == b,
+            (Self::Tuple(a), Self::Tuple(b)) => a == b,
+            (Self::Variant(a), Self::Variant(b)) => a == b,
+            (Self::Map(a), Self::Map(b)) => a == b,
+            (Self::MapList(a), Self::MapList(b)) => a == b,
+            (Self::Unsupported(a), Self::Unsupported(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Variant {}
+
 impl Variant {
     /// Construct a GVariant from a raw pointer
     pub(crate) unsafe fn from_ptr(variant: *mut frida_sys::GVariant) -> Self {
@@ -50,16 +90,89 @@ impl Variant {
                 "u" => Self::Int64(frida_sys::g_variant_get_uint32(variant) as i64),
                 "x" => Self::Int64(frida_sys::g_variant_get_int64(variant)),
                 "t" => Self::Int64(frida_sys::g_variant_get_uint64(variant) as i64),
+                "d" => Self::Double(frida_sys::g_variant_get_double(variant)),
+                "ay" => Self::ByteArray(byte_array_to_vec(variant)),
+                "as" => Self::StringArray(string_array_to_vec(variant)),
+                "v" => {
+                    let inner = frida_sys::g_variant_get_variant(variant);
+                    let value = Variant::from_ptr(inner);
+                    frida_sys::g_variant_unref(inner);
+                    Self::Variant(Box::new(value))
+                }
                 "a{sv}" => Self::Map(sv_array_to_map(variant)),
                 "aa{sv}" => Self::MapList(asv_array_to_maplist(variant)),
+                sig if sig.starts_with('(') => Self::Tuple(tuple_to_vec(variant)),
                 // Don't panic on unknown signatures; preserve the sig so callers
-                // can `Variant::Unsupported(sig)` and decide. Frida on Windows
-                // packs process icons as "ay" which we have no use for.
+                // can `Variant::Unsupported(sig)` and decide.
                 other => Self::Unsupported(other.to_string()),
             }
         }
     }
 
+    /// Build a new floating GVariant reference holding this value.
+    ///
+    /// Returns `None` for [`Variant::Unsupported`] and for strings with interior NUL bytes,
+    /// which have no GVariant representation.
+    pub(crate) fn to_ptr(&self) -> Option<*mut frida_sys::GVariant> {
+        unsafe {
+            let ptr = match self {
+                Self::String(s) => {
+                    let s = CString::new(s.as_str()).ok()?;
+                    frida_sys::g_variant_new_string(s.as_ptr())
+                }
+                Self::Boolean(b) => frida_sys::g_variant_new_boolean(*b as frida_sys::gboolean),
+                Self::Int64(i) => frida_sys::g_variant_new_int64(*i),
+                Self::Double(d) => frida_sys::g_variant_new_double(*d),
+                Self::ByteArray(bytes) => frida_sys::g_variant_new_fixed_array(
+                    variant_type(c"y"),
+                    bytes.as_ptr() as _,
+                    bytes.len() as _,
+                    1,
+                ),
+                Self::StringArray(strings) => {
+                    let strings = strings
+                        .iter()
+                        .map(|s| CString::new(s.as_str()))
+                        .collect::<Result<Vec<_>, _>>()
+                        .ok()?;
+                    let ptrs: Vec<*const frida_sys::gchar> =
+                        strings.iter().map(|s| s.as_ptr()).collect();
+                    frida_sys::g_variant_new_strv(ptrs.as_ptr(), ptrs.len() as _)
+                }
+                Self::Tuple(values) => {
+                    let mut children = Vec::with_capacity(values.len());
+                    for value in values {
+                        let Some(child) = value.to_ptr() else {
+                            for child in children {
+                                frida_sys::g_variant_unref(child);
+                            }
+                            return None;
+                        };
+                        children.push(child);
+                    }
+                    frida_sys::g_variant_new_tuple(children.as_ptr(), children.len() as _)
+                }
+                Self::Variant(inner) => frida_sys::g_variant_new_variant(inner.to_ptr()?),
+                Self::Map(map) => map_to_sv_array(map)?,
+                Self::MapList(list) => {
+                    let builder = frida_sys::g_variant_builder_new(variant_type(c"aa{sv}"));
+                    for map in list {
+                        let Some(value) = map_to_sv_array(map) else {
+                            frida_sys::g_variant_builder_unref(builder);
+                            return None;
+                        };
+                        frida_sys::g_variant_builder_add_value(builder, value);
+                    }
+                    let ptr = frida_sys::g_variant_builder_end(builder);
+                    frida_sys::g_variant_builder_unref(builder);
+                    ptr
+                }
+                Self::Unsupported(_) => return None,
+            };
+            Some(ptr)
+        }
+    }
+
     /// Get the string value of a variant, if any
     pub fn get_string(&self) -> Option<&str> {
         let Self::String(s) = self else {
@@ -80,6 +193,40 @@ impl Variant {
         Some(*b)
     }
 
+    /// Get the floating point value of a variant, if any
+    pub fn get_double(&self) -> Option<f64> {
+        let Self::Double(d) = self else { return None };
+        Some(*d)
+    }
+
+    /// Get the byte array value of a variant, if any
+    pub fn get_bytes(&self) -> Option<&[u8]> {
+        let Self::ByteArray(b) = self else {
+            return None;
+        };
+        Some(b)
+    }
+
+    /// Get the string array value of a variant, if any
+    pub fn get_string_array(&self) -> Option<&[String]> {
+        let Self::StringArray(a) = self else {
+            return None;
+        };
+        Some(a)
+    }
+
+    /// Get the tuple members of a variant, if any
+    pub fn get_tuple(&self) -> Option<&[Variant]> {
+        let Self::Tuple(t) = self else { return None };
+        Some(t)
+    }
+
+    /// Get the value boxed inside a variant, if any
+    pub fn get_variant(&self) -> Option<&Variant> {
+        let Self::Variant(v) = self else { return None };
+        Some(v)
+    }
+
     /// Get the mapping value of a variant, if any
     pub fn get_map(&self) -> Option<&HashMap<String, Variant>> {
         let Self::Map(m) = self else { return None };
@@ -101,6 +248,17 @@ impl std::fmt::Debug for Variant {
             Self::String(s) => s.fmt(f),
             Self::Int64(num) => num.fmt(f),
             Self::Boolean(b) => b.fmt(f),
+            Self::Double(d) => d.fmt(f),
+            Self::ByteArray(b) => write!(f, "<{} bytes>", b.len()),
+            Self::StringArray(a) => a.fmt(f),
+            Self::Tuple(t) => {
+                let mut tuple = f.debug_tuple("");
+                for v in t {
+                    tuple.field(v);
+                }
+                tuple.finish()
+            }
+            Self::Variant(v) => v.fmt(f),
             Self::Map(m) => m.fmt(f),
             Self::MapList(l) => l.fmt(f),
             Self::Unsupported(sig) => write!(f, "<unsupported variant: {sig}>"),
@@ -108,6 +266,212 @@ impl std::fmt::Debug for Variant {
     }
 }
 
+impl From<&str> for Variant {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for Variant {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<bool> for Variant {
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
+    }
+}
+
+impl From<i64> for Variant {
+    fn from(value: i64) -> Self {
+        Self::Int64(value)
+    }
+}
+
+impl From<i32> for Variant {
+    fn from(value: i32) -> Self {
+        Self::Int64(value.into())
+    }
+}
+
+impl From<u32> for Variant {
+    fn from(value: u32) -> Self {
+        Self::Int64(value.into())
+    }
+}
+
+impl From<f64> for Variant {
+    fn from(value: f64) -> Self {
+        Self::Double(value)
+    }
+}
+
+impl From<Vec<u8>> for Variant {
+    fn from(value: Vec<u8>) -> Self {
+        Self::ByteArray(value)
+    }
+}
+
+impl From<Vec<String>> for Variant {
+    fn from(value: Vec<String>) -> Self {
+        Self::StringArray(value)
+    }
+}
+
+impl From<HashMap<String, Variant>> for Variant {
+    fn from(value: HashMap<String, Variant>) -> Self {
+        Self::Map(value)
+    }
+}
+
+/// Variants serialize to their natural JSON-like shape: byte arrays become sequences of
+/// numbers, tuples and string arrays become sequences, and boxed variants are unwrapped.
+/// Serializing a [`Variant::Unsupported`] fails, as its value was never decoded.
+impl Serialize for Variant {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::String(s) => serializer.serialize_str(s),
+            Self::Boolean(b) => serializer.serialize_bool(*b),
+            Self::Int64(i) => serializer.serialize_i64(*i),
+            Self::Double(d) => serializer.serialize_f64(*d),
+            Self::ByteArray(b) => serializer.serialize_bytes(b),
+            Self::StringArray(a) => a.serialize(serializer),
+            Self::Tuple(t) => {
+                let mut seq = serializer.serialize_seq(Some(t.len()))?;
+                for v in t {
+                    seq.serialize_element(v)?;
+                }
+                seq.end()
+            }
+            Self::Variant(v) => v.serialize(serializer),
+            Self::Map(m) => m.serialize(serializer),
+            Self::MapList(l) => l.serialize(serializer),
+            Self::Unsupported(sig) => Err(serde::ser::Error::custom(format!(
+                "cannot serialize unsupported variant `{sig}`"
+            ))),
+        }
+    }
+}
+
+/// Sequences deserialize into [`Variant::StringArray`] when every element is a string,
+/// into [`Variant::MapList`] when every element is a map, and into [`Variant::Tuple`]
+/// otherwise. Null values are rejected as GVariant has no equivalent.
+impl<'de> Deserialize<'de> for Variant {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(VariantVisitor)
+    }
+}
+
+struct VariantVisitor;
+
+impl<'de> Visitor<'de> for VariantVisitor {
+    type Value = Variant;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a value representable as a GVariant")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Variant, E> {
+        Ok(Variant::Boolean(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Variant, E> {
+        Ok(Variant::Int64(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Variant, E> {
+        i64::try_from(v).map(Variant::Int64).map_err(E::custom)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Variant, E> {
+        Ok(Variant::Double(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Variant, E> {
+        Ok(Variant::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Variant, E> {
+        Ok(Variant::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Variant, E> {
+        Ok(Variant::ByteArray(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Variant, E> {
+        Ok(Variant::ByteArray(v))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Variant, D::Error> {
+        Variant::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Variant, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element::<Variant>()? {
+            values.push(value);
+        }
+
+        if values.is_empty() {
+            return Ok(Variant::Tuple(values));
+        }
+        if values.iter().all(|v| matches!(v, Variant::String(_))) {
+            let strings = values
+                .into_iter()
+                .filter_map(|v| match v {
+                    Variant::String(s) => Some(s),
+                    _ => None,
+                })
+                .collect();
+            return Ok(Variant::StringArray(strings));
+        }
+        if values.iter().all(|v| matches!(v, Variant::Map(_))) {
+            let maps = values
+                .into_iter()
+                .filter_map(|v| match v {
+                    Variant::Map(m) => Some(m),
+                    _ => None,
+                })
+                .collect();
+            return Ok(Variant::MapList(maps));
+        }
+        Ok(Variant::Tuple(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Variant, A::Error> {
+        let mut map = HashMap::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some((key, value)) = access.next_entry::<String, Variant>()? {
+            map.insert(key, value);
+        }
+        Ok(Variant::Map(map))
+    }
+}
+
+impl TryFrom<Variant> for serde_json::Value {
+    type Error = serde_json::Error;
+
+    fn try_from(value: Variant) -> Result<Self, Self::Error> {
+        serde_json::to_value(value)
+    }
+}
+
+impl TryFrom<serde_json::Value> for Variant {
+    type Error = serde_json::Error;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(value)
+    }
+}
+
+fn variant_type(sig: &CStr) -> *const frida_sys::GVariantType {
+    // A GVariantType pointer is the type signature string itself, as the
+    // `G_VARIANT_TYPE` macro does in C.
+    sig.as_ptr() as _
+}
+
 unsafe fn variant_string(variant: *mut frida_sys::GVariant) -> String {
     unsafe {
         CStr::from_ptr(frida_sys::g_variant_get_type_string(variant))
@@ -116,20 +480,115 @@ unsafe fn variant_string(variant: *mut frida_sys::GVariant) -> String {
     }
 }
 
+unsafe fn byte_array_to_vec(variant: *mut frida_sys::GVariant) -> Vec<u8> {
+    unsafe {
+        let mut len: frida_sys::gsize = 0;
+        let data = frida_sys::g_variant_get_fixed_array(variant, &mut len, 1) as *const u8;
+        if data.is_null() || len == 0 {
+            return Vec::new();
+        }
+        std::slice::from_raw_parts(data, len as usize).to_vec()
+    }
+}
+
+unsafe fn string_array_to_vec(variant: *mut frida_sys::GVariant) -> Vec<String> {
+    unsafe {
+        let mut len: frida_sys::gsize = 0;
+        let strv = frida_sys::g_variant_get_strv(variant, &mut len);
+        let ret = (0..len as usize)
+            .map(|i| CStr::from_ptr(*strv.add(i)).to_string_lossy().to_string())
+            .collect();
+        // Only the container is owned by the caller, the strings belong to the variant.
+        frida_sys::g_free(strv as _);
+        ret
+    }
+}
+
+unsafe fn tuple_to_vec(variant: *mut frida_sys::GVariant) -> Vec<Variant> {
+    unsafe {
+        let n = frida_sys::g_variant_n_children(variant);
+        (0..n)
+            .map(|i| {
+                let child = frida_sys::g_variant_get_child_value(variant, i);
+                let value = Variant::from_ptr(child);
+                frida_sys::g_variant_unref(child);
+                value
+            })
+            .collect()
+    }
+}
+
+unsafe fn map_to_sv_array(map: &HashMap<String, Variant>) -> Option<*mut frida_sys::GVariant> {
+    unsafe {
+        let builder = frida_sys::g_variant_builder_new(variant_type(c"a{sv}"));
+        for (key, value) in map {
+            let Some((key, value)) = CString::new(key.as_str())
+                .ok()
+                .and_then(|key| Some((key, value.to_ptr()?)))
+            else {
+                frida_sys::g_variant_builder_unref(builder);
+                return None;
+            };
+            let entry = frida_sys::g_variant_new_dict_entry(
+                frida_sys::g_variant_new_string(key.as_ptr()),
+                frida_sys::g_variant_new_variant(value),
+            );
+            frida_sys::g_variant_builder_add_value(builder, entry);
+        }
+        let ptr = frida_sys::g_variant_builder_end(builder);
+        frida_sys::g_variant_builder_unref(builder);
+        Some(ptr)
+    }
+}
+
+/// Copy a `GHashTable` of strings to GVariants, as used for Frida parameter tables.
+pub(crate) unsafe fn variant_map_from_hash_table(
+    hash: *mut frida_sys::GHashTable,
+) -> HashMap<String, Variant> {
+    let mut out = HashMap::new();
+    unsafe {
+        if hash.is_null() {
+            return out;
+        }
+        let mut iter: frida_sys::GHashTableIter = std::mem::MaybeUninit::zeroed().assume_init();
+        frida_sys::g_hash_table_iter_init(&mut iter, hash);
+        let mut key: *mut std::ffi::c_void = std::ptr::null_mut();
+        let mut value: *mut std::ffi::c_void = std::ptr::null_mut();
+        while frida_sys::g_hash_table_iter_next(&mut iter, &mut key, &mut value) != 0 {
+            let k = CStr::from_ptr(key.cast()).to_string_lossy().into_owned();
+            let v = Variant::from_ptr(value.cast());
+            out.insert(k, v);
+        }
+    }
+    out
+}
+
+// The entries are read with `g_variant_iter_next_value` rather than the variadic
+// `g_variant_iter_loop`, which can't be resolved when frida-core is loaded dynamically.
 unsafe fn sv_array_to_map(variant: *mut frida_sys::GVariant) -> HashMap<String, Variant> {
     unsafe {
         let mut ret = HashMap::new();
-
         let mut iter: frida_sys::GVariantIter = std::mem::MaybeUninit::zeroed().assume_init();
-        let mut value: *mut frida_sys::GVariant = std::ptr::null_mut();
-        let mut key: *const i8 = std::ptr::null_mut();
 
         frida_sys::g_variant_iter_init(&mut iter, variant);
-        let sv = CString::new("{sv}").unwrap();
-        while frida_sys::g_variant_iter_loop(&mut iter, sv.as_ptr(), &mut key, &mut value) != 0 {
-            let key = CStr::from_ptr(key.cast()).to_string_lossy().to_string();
-            let value = Variant::from_ptr(value);
-            ret.insert(key, value);
+        loop {
+            let entry = frida_sys::g_variant_iter_next_value(&mut iter);
+            if entry.is_null() {
+                break;
+            }
+            let key = frida_sys::g_variant_get_child_value(entry, 0);
+            let boxed = frida_sys::g_variant_get_child_value(entry, 1);
+            let value = frida_sys::g_variant_get_variant(boxed);
+
+            let mut sz = 0;
+            let name = CStr::from_ptr(frida_sys::g_variant_get_string(key, &mut sz))
+                .to_string_lossy()
+                .to_string();
+            ret.insert(name, Variant::from_ptr(value));
+
+            for ptr in [value, boxed, key, entry] {
+                frida_sys::g_variant_unref(ptr);
+            }
         }
         ret
     }
@@ -138,24 +597,17 @@ unsafe fn sv_array_to_map(variant: *mut frida_sys::GVariant) -> HashMap<String,
 unsafe fn asv_array_to_maplist(variant: *mut frida_sys::GVariant) -> Vec<HashMap<String, Variant>> {
     unsafe {
         let mut ret = Vec::new();
-        let mut outer: frida_sys::GVariantIter = std::mem::MaybeUninit::zeroed().assume_init();
-        let mut inner = std::ptr::null_mut();
-        let mut key: *const i8 = std::ptr::null_mut();
-        let mut value: *mut frida_sys::GVariant = std::ptr::null_mut();
-
-        frida_sys::g_variant_iter_init(&mut outer, variant);
-        let asv = CString::new("a{sv}").unwrap();
-        let sv = CString::new("{sv}").unwrap();
-        while frida_sys::g_variant_iter_loop(&mut outer, asv.as_ptr(), &mut inner) != 0 {
-            let mut map = HashMap::new();
-            while frida_sys::g_variant_iter_loop(inner, sv.as_ptr(), &mut key, &mut value) != 0 {
-                let key = CStr::from_ptr(key.cast()).to_string_lossy().to_string();
-                let value = Variant::from_ptr(value);
-                map.insert(key, value);
+        let mut iter: frida_sys::GVariantIter = std::mem::MaybeUninit::zeroed().assume_init();
+
+        frida_sys::g_variant_iter_init(&mut iter, variant);
+        loop {
+            let map = frida_sys::g_variant_iter_next_value(&mut iter);
+            if map.is_null() {
+                break;
             }
-            ret.push(map)
+            ret.push(sv_array_to_map(map));
+            frida_sys::g_variant_unref(map);
         }
-
         ret
     }
 }
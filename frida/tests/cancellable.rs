@@ -0,0 +1,35 @@
+//! Integration tests for `Cancellable`.
+//!
+//! The timeout test relies on the Frida main context, so the runtime is
+//! obtained like in the other integration tests.
+
+use frida::{Cancellable, Frida};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
+
+#[test]
+fn cancel_is_shared_between_clones_and_threads() {
+    let cancellable = Cancellable::new();
+    let clone = cancellable.clone();
+    assert!(!cancellable.is_cancelled());
+
+    std::thread::spawn(move || clone.cancel())
+        .join()
+        .expect("cancelling thread should not panic");
+
+    assert!(cancellable.is_cancelled());
+}
+
+#[test]
+fn with_timeout_cancels_after_deadline() {
+    let cancellable = Cancellable::with_timeout(&FRIDA, Duration::from_millis(50));
+    assert!(!cancellable.is_cancelled());
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !cancellable.is_cancelled() {
+        assert!(Instant::now() < deadline, "timeout cancellable never fired");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
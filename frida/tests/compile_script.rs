@@ -8,7 +8,7 @@
 use frida::{DeviceManager, Error, Frida, Message, ScriptHandler, ScriptOption, ScriptRuntime};
 use std::sync::{LazyLock, Mutex, MutexGuard};
 
-static FRIDA: LazyLock<Frida> = LazyLock::new(|| unsafe { Frida::obtain() });
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
 
 // Frida-core is a process-wide singleton: concurrent self-attach from
 // multiple cargo-test threads corrupts its agent state (observed as
@@ -56,7 +56,7 @@ fn compile_script_produces_loadable_bytecode() {
     );
 
     let mut load_opts = qjs_options("roundtrip");
-    let mut script = session
+    let script = session
         .create_script_from_bytes(&bytecode, &mut load_opts)
         .expect("create_script_from_bytes should accept the just-compiled bytecode");
 
@@ -118,7 +118,7 @@ fn create_script_from_bytes_accepts_default_options() {
         .expect("compile_script should succeed with default options");
 
     let mut load_opts = ScriptOption::default();
-    let mut script = session
+    let script = session
         .create_script_from_bytes(&bytecode, &mut load_opts)
         .expect("create_script_from_bytes should succeed with default options");
 
@@ -0,0 +1,100 @@
+//! Integration tests for `Compiler`, the frida-core agent bundler.
+//!
+//! These don't attach to any process, but need the real frida-core runtime.
+
+use frida::{
+    BuildOptions, Compiler, DeviceManager, Error, Frida, ScriptOption, ScriptSourceMaps, SourceMaps,
+};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
+
+// Frida-core is a process-wide singleton; serialize the tests so two
+// threads don't race the device-manager / compiler state.
+static FRIDA_SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial_guard() -> MutexGuard<'static, ()> {
+    FRIDA_SERIAL.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+fn agent_project(test: &str, source: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("frida-compiler-{}-{test}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("package.json"), r#"{ "name": "agent" }"#).unwrap();
+    fs::write(root.join("index.js"), source).unwrap();
+    root
+}
+
+#[test]
+fn build_bundles_the_entrypoint() {
+    let _serial = serial_guard();
+    let root = agent_project("bundle", "send('bundled');\n");
+    let compiler = Compiler::new(&DeviceManager::obtain(&FRIDA));
+
+    let options = BuildOptions::new()
+        .project_root(&root.to_string_lossy())
+        .source_maps(SourceMaps::Included);
+    let bundle = compiler
+        .build("index.js", &options)
+        .expect("bundling a plain script should succeed");
+
+    assert!(bundle.contains("bundled"));
+}
+
+#[test]
+fn build_reports_syntax_errors() {
+    let _serial = serial_guard();
+    let root = agent_project("syntax", "send(;\n");
+    let compiler = Compiler::new(&DeviceManager::obtain(&FRIDA));
+
+    let options = BuildOptions::new().project_root(&root.to_string_lossy());
+    assert!(matches!(
+        compiler.build("index.js", &options),
+        Err(Error::CompilationFailed(_))
+    ));
+}
+
+#[test]
+fn errors_of_a_bundle_resolve_through_its_source_maps() {
+    let _serial = serial_guard();
+    let root = agent_project("sourcemaps", "");
+    fs::remove_file(root.join("index.js")).unwrap();
+    let source = "rpc.exports = {\n  fail(): never {\n    throw new Error(\"boom\");\n  },\n};\n";
+    fs::write(root.join("index.ts"), source).unwrap();
+    let device_manager = DeviceManager::obtain(&FRIDA);
+    let compiler = Compiler::new(&device_manager);
+
+    let options = BuildOptions::new()
+        .project_root(&root.to_string_lossy())
+        .source_maps(SourceMaps::Included);
+    let bundle = compiler
+        .build("index.ts", &options)
+        .expect("bundling a TypeScript agent should succeed");
+    let maps = ScriptSourceMaps::from_bundle(&bundle).expect("the output should be a bundle");
+    assert!(!maps.is_empty(), "the bundle should hold source maps");
+
+    let device = device_manager
+        .get_local_device()
+        .expect("local device should be available");
+    let session = device
+        .attach(0)
+        .expect("attach to self (pid=0) should succeed");
+    let script = session
+        .create_script(&bundle, &mut ScriptOption::new())
+        .expect("the bundle should be loadable");
+    script.load().expect("loading the bundle should succeed");
+
+    match script.exports.call("fail", None) {
+        Err(Error::RpcJsError {
+            original_stack: Some(stack),
+            ..
+        }) => assert!(stack.contains("index.ts:3:"), "unresolved stack: {stack}"),
+        other => panic!("expected a resolved JavaScript error, got {other:?}"),
+    }
+
+    script.unload().expect("unloading should succeed");
+    session.detach().expect("detach should succeed");
+}
@@ -0,0 +1,68 @@
+//! Integration tests for the `GError` details carried by `frida::Error`.
+//!
+//! The test process attaches to itself (pid=0), like the compile_script
+//! tests.
+
+use frida::{DeviceManager, Error, Frida, FridaErrorCode, GError, ScriptOption};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
+
+// Frida-core is a process-wide singleton; every #[test] in this file
+// must hold this lock for the full attach -> detach span.
+static FRIDA_SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial_guard() -> MutexGuard<'static, ()> {
+    FRIDA_SERIAL.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+#[test]
+fn create_script_reports_syntax_error_details() {
+    let _serial = serial_guard();
+    let device_manager = DeviceManager::obtain(&FRIDA);
+    let device = device_manager
+        .get_local_device()
+        .expect("local device should be available");
+    let session = device
+        .attach(0)
+        .expect("attach to self (pid=0) should succeed");
+
+    let err = match session.create_script("function (", &mut ScriptOption::default()) {
+        Ok(_) => panic!("a script with a syntax error must not be created"),
+        Err(err) => err,
+    };
+
+    let Error::ScriptCreationError(ref details) = err else {
+        panic!("expected Error::ScriptCreationError, got {err:?}");
+    };
+    assert_eq!(details.domain, GError::FRIDA_DOMAIN);
+    assert_eq!(err.frida_code(), Some(FridaErrorCode::InvalidArgument));
+    assert!(
+        details.message.contains("line 1"),
+        "syntax error message should carry the position, got {:?}",
+        details.message
+    );
+
+    session.detach().expect("detach should succeed");
+}
+
+#[test]
+fn attach_to_missing_process_reports_process_not_found() {
+    let _serial = serial_guard();
+    let device_manager = DeviceManager::obtain(&FRIDA);
+    let device = device_manager
+        .get_local_device()
+        .expect("local device should be available");
+
+    let err = match device.attach(u32::MAX - 1) {
+        Ok(_) => panic!("attaching to a nonexistent pid must fail"),
+        Err(err) => err,
+    };
+
+    assert!(
+        matches!(err, Error::DeviceAttachError(_)),
+        "expected Error::DeviceAttachError, got {err:?}"
+    );
+    assert_eq!(err.frida_code(), Some(FridaErrorCode::ProcessNotFound));
+    assert!(!err.gerror().unwrap().message.is_empty());
+}
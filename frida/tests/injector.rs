@@ -0,0 +1,102 @@
+//! Integration tests for the local [`Injector`].
+//!
+//! These inject the C library into a child process, using libc functions as entrypoints:
+//! `getpid` returns at once, so the library is unloaded, while `sleep` keeps the injection
+//! alive (its "seconds" are the address of the entrypoint data). Linux/glibc only.
+
+#![cfg(all(target_os = "linux", target_env = "gnu"))]
+
+use frida::{Error, Inject, Injector};
+use std::process::{Child, Command};
+use std::sync::mpsc::channel;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+// Frida-core is a process-wide singleton; serialize the tests so two
+// threads don't race the injector state.
+static FRIDA_SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial_guard() -> MutexGuard<'static, ()> {
+    FRIDA_SERIAL.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+/// The path of the C library mapped into this process, which children share.
+fn libc_path() -> String {
+    let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+    maps.lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .find(|path| path.rsplit('/').next() == Some("libc.so.6"))
+        .expect("libc should be mapped")
+        .to_owned()
+}
+
+/// A child process that outlives the test, killed when dropped.
+struct Target(Child);
+
+impl Target {
+    fn spawn() -> Self {
+        Self(Command::new("sleep").arg("30").spawn().unwrap())
+    }
+
+    fn pid(&self) -> u32 {
+        self.0.id()
+    }
+}
+
+impl Drop for Target {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[test]
+fn uninjected_is_signalled_once_the_entrypoint_returns() {
+    let _serial = serial_guard();
+    let target = Target::spawn();
+    let mut injector = Injector::new();
+    let (tx, rx) = channel();
+    injector.handle_uninjected(tx).unwrap();
+
+    let id = injector
+        .inject_library_file_sync(target.pid(), libc_path(), "getpid", "")
+        .unwrap();
+
+    let uninjected = rx
+        .recv_timeout(Duration::from_secs(10))
+        .expect("timed out waiting for the uninjected signal");
+    assert_eq!(uninjected, id);
+}
+
+#[test]
+fn demonitored_injection_is_forgotten() {
+    let _serial = serial_guard();
+    let target = Target::spawn();
+    let mut injector = Injector::new();
+    let (tx, rx) = channel();
+    injector.handle_uninjected(tx).unwrap();
+
+    let id = injector
+        .inject_library_file_sync(target.pid(), libc_path(), "sleep", "")
+        .unwrap();
+    injector.demonitor(id).unwrap();
+
+    assert!(matches!(
+        injector.demonitor(id),
+        Err(Error::DemonitorFailed(_))
+    ));
+    drop(target);
+    assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+}
+
+#[test]
+fn recreating_the_thread_of_an_unknown_injection_fails() {
+    let _serial = serial_guard();
+    let target = Target::spawn();
+    let injector = Injector::new();
+
+    assert!(matches!(
+        injector.recreate_thread(target.pid(), u32::MAX),
+        Err(Error::RecreateThreadFailed(_))
+    ));
+}
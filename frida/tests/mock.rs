@@ -0,0 +1,156 @@
+//! Tests for the in-memory `frida::mock` backend.
+//!
+//! These never initialize frida-core nor attach to a process, so unlike the
+//! other integration tests they don't need a `Frida` handle or a lock.
+
+use frida::mock::{MockDevice, MockProcess};
+use frida::{
+    DeviceBackend, Error, FridaErrorCode, Message, ScriptBackend, ScriptConfig, ScriptHandler,
+    SessionBackend, SpawnConfig, SpawnOptions,
+};
+use serde_json::{Value, json};
+use std::sync::mpsc::{Sender, channel};
+
+struct Forward(Sender<Message>);
+
+impl ScriptHandler for Forward {
+    fn on_message(&mut self, message: Message, _data: Option<Vec<u8>>) {
+        let _ = self.0.send(message);
+    }
+}
+
+/// Host-side logic under test: injects an agent and sums the values it reports.
+fn sum_with_agent<D: DeviceBackend>(device: &D, pid: u32, values: &[i64]) -> frida::Result<i64> {
+    let session = device.attach(pid)?;
+    let script = session.create_script("/* agent */", &ScriptConfig::default())?;
+    script.load()?;
+    let sum = script
+        .call("sum", Some(json!([values])))?
+        .and_then(|v| v.as_i64())
+        .ok_or(Error::RpcUnexpectedMessage)?;
+    script.unload()?;
+    session.detach()?;
+    Ok(sum)
+}
+
+#[test]
+fn rpc_flow_runs_against_mock_exports() {
+    let device = MockDevice::new("mock", "Mock Device");
+    device.add_process(42, "target");
+    device.on_create_script(|script| {
+        script.export("sum", |args| {
+            let values = args[0].as_array().ok_or("expected an array")?;
+            Ok(json!(values.iter().filter_map(Value::as_i64).sum::<i64>()))
+        });
+    });
+
+    assert_eq!(sum_with_agent(&device, 42, &[1, 2, 3]).unwrap(), 6);
+
+    let sessions = device.sessions();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].pid(), 42);
+    assert!(sessions[0].is_detached());
+    assert!(!sessions[0].scripts()[0].is_loaded());
+}
+
+#[test]
+fn rpc_errors_surface_as_js_errors() {
+    let device = MockDevice::new("mock", "Mock Device");
+    device.add_process(42, "target");
+    device.on_create_script(|script| {
+        script.export("fail", |_| Err("boom".to_string()));
+    });
+
+    let session = device.attach(42).unwrap();
+    let script = session.create_script("", &ScriptConfig::default()).unwrap();
+
+    assert!(matches!(
+        script.call("fail", None),
+        Err(Error::RpcJsError { .. })
+    ));
+    script.load().unwrap();
+    assert_eq!(script.list_exports().unwrap(), vec!["fail".to_string()]);
+    match script.call("fail", None) {
+        Err(Error::RpcJsError { message, .. }) => assert_eq!(message, "boom"),
+        other => panic!("expected a JS error, got {other:?}"),
+    }
+    assert!(matches!(
+        script.call("missing", None),
+        Err(Error::RpcJsError { .. })
+    ));
+}
+
+#[test]
+fn attach_to_unknown_pid_fails_like_frida_core() {
+    let device = MockDevice::new("mock", "Mock Device");
+
+    let err = match device.attach(7) {
+        Ok(_) => panic!("attaching to an unknown pid must fail"),
+        Err(err) => err,
+    };
+    assert!(matches!(err, Error::DeviceAttachError(_)));
+    assert_eq!(err.frida_code(), Some(FridaErrorCode::ProcessNotFound));
+}
+
+#[test]
+fn messages_are_routed_to_the_handler_and_posts_recorded() {
+    let device = MockDevice::new("mock", "Mock Device");
+    device.add_process(42, "target");
+    let session = device.attach(42).unwrap();
+    let script = session.create_script("", &ScriptConfig::default()).unwrap();
+
+    let (tx, rx) = channel();
+    script.handle_message(Forward(tx)).unwrap();
+    script.post(r#"{"type":"ping"}"#, Some(b"raw")).unwrap();
+
+    let mock_script = &session.scripts()[0];
+    assert_eq!(
+        mock_script.posted(),
+        vec![(r#"{"type":"ping"}"#.to_string(), Some(b"raw".to_vec()))]
+    );
+
+    let message = serde_json::from_value(json!({ "type": "send", "payload": "pong" })).unwrap();
+    mock_script.emit(message, None);
+    match rx.try_recv().expect("handler should receive the message") {
+        Message::Send(send) => assert_eq!(send.payload, json!("pong")),
+        other => panic!("expected a send message, got {other:?}"),
+    }
+}
+
+#[test]
+fn spawn_orchestration_tracks_process_state() {
+    let device = MockDevice::new("mock", "Mock Device");
+    let options = SpawnConfig {
+        argv: Some(vec!["cat".to_string()]),
+        ..Default::default()
+    };
+
+    let pid = device.spawn("/bin/cat", &options).unwrap();
+    assert_eq!(
+        device.processes(),
+        vec![MockProcess {
+            pid,
+            name: "/bin/cat".to_string(),
+            resumed: false,
+        }]
+    );
+
+    device.resume(pid).unwrap();
+    assert!(device.processes()[0].resumed);
+
+    device.kill(pid).unwrap();
+    assert!(device.processes().is_empty());
+    assert!(matches!(device.kill(pid), Err(Error::KillFailed(_))));
+}
+
+#[test]
+fn spawn_config_with_nul_bytes_is_rejected_before_reaching_frida_core() {
+    let config = SpawnConfig {
+        cwd: Some("/tmp\0".to_string()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        SpawnOptions::try_from(&config),
+        Err(Error::CStringFailed)
+    ));
+}
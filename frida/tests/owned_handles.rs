@@ -0,0 +1,105 @@
+//! Integration tests for the owned, thread-safe `Device`, `Session` and
+//! `Script` handles.
+//!
+//! The test process attaches to itself (pid=0), like the compile_script
+//! tests.
+
+use frida::{Device, DeviceManager, Frida, Script, ScriptOption, Session};
+use serde_json::json;
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard};
+
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
+
+// Frida-core is a process-wide singleton; every #[test] in this file
+// must hold this lock for the full attach -> detach span.
+static FRIDA_SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial_guard() -> MutexGuard<'static, ()> {
+    FRIDA_SERIAL.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+#[test]
+fn handles_are_send_sync_and_static() {
+    assert_send_sync::<Device>();
+    assert_send_sync::<Session>();
+    assert_send_sync::<Script>();
+}
+
+#[test]
+fn session_outlives_manager_and_moves_across_threads() {
+    let _serial = serial_guard();
+    let session = {
+        let device_manager = DeviceManager::obtain(&FRIDA);
+        let device = device_manager
+            .get_local_device()
+            .expect("local device should be available");
+        device
+            .attach(0)
+            .expect("attach to self (pid=0) should succeed")
+    };
+
+    let script = std::thread::spawn(move || {
+        let script = session
+            .create_script(
+                "rpc.exports = { double: n => n * 2 };",
+                &mut ScriptOption::default(),
+            )
+            .expect("create_script should succeed");
+        script.load().expect("load should succeed");
+        script
+    })
+    .join()
+    .expect("worker thread should not panic");
+
+    assert!(!script.session().device().is_lost());
+
+    script.unload().expect("unload should succeed");
+    script.session().detach().expect("detach should succeed");
+}
+
+#[test]
+fn concurrent_rpc_calls_get_their_own_replies() {
+    let _serial = serial_guard();
+    let device_manager = DeviceManager::obtain(&FRIDA);
+    let device = device_manager
+        .get_local_device()
+        .expect("local device should be available");
+    let session = device
+        .attach(0)
+        .expect("attach to self (pid=0) should succeed");
+    let script = session
+        .create_script(
+            "rpc.exports = { double: n => n * 2 };",
+            &mut ScriptOption::default(),
+        )
+        .expect("create_script should succeed");
+    script.load().expect("load should succeed");
+
+    let script = Arc::new(script);
+    let workers: Vec<_> = (0..8)
+        .map(|n| {
+            let script = script.clone();
+            std::thread::spawn(move || {
+                let result = script
+                    .exports
+                    .call("double", Some(json!([n])))
+                    .expect("rpc call should succeed");
+                assert_eq!(result, Some(json!(n * 2)));
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().expect("rpc thread should not panic");
+    }
+
+    let clone = (*script).clone();
+    assert_eq!(
+        clone.list_exports().expect("list_exports should succeed"),
+        vec!["double".to_string()]
+    );
+
+    script.unload().expect("unload should succeed");
+    session.detach().expect("detach should succeed");
+}
@@ -0,0 +1,112 @@
+//! Tests for the typed `SystemParameters` and `ProcessParameters` views.
+//!
+//! These are pure Rust conversions and don't need the frida-core runtime.
+
+use frida::{DeviceAccess, ProcessIcon, ProcessParameters, SystemParameters, Variant};
+use std::collections::HashMap;
+
+fn map<const N: usize>(entries: [(&str, Variant); N]) -> HashMap<String, Variant> {
+    entries
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect()
+}
+
+#[test]
+fn system_parameters_extract_known_keys() {
+    let params = SystemParameters::from(map([
+        (
+            "os",
+            Variant::Map(map([
+                ("id", Variant::from("linux")),
+                ("name", Variant::from("Debian")),
+                ("version", Variant::from("12")),
+                ("codename", Variant::from("bookworm")),
+            ])),
+        ),
+        ("platform", Variant::from("linux")),
+        ("arch", Variant::from("x64")),
+        ("access", Variant::from("full")),
+        ("name", Variant::from("build-host")),
+        ("hardware", Variant::from("virtual")),
+    ]));
+
+    assert_eq!(params.os_id.as_deref(), Some("linux"));
+    assert_eq!(params.os_name.as_deref(), Some("Debian"));
+    assert_eq!(params.os_version.as_deref(), Some("12"));
+    assert_eq!(params.platform.as_deref(), Some("linux"));
+    assert_eq!(params.arch.as_deref(), Some("x64"));
+    assert_eq!(params.access, Some(DeviceAccess::Full));
+    assert_eq!(params.hostname.as_deref(), Some("build-host"));
+    assert_eq!(
+        params.other,
+        map([
+            (
+                "os",
+                Variant::Map(map([("codename", Variant::from("bookworm"))]))
+            ),
+            ("hardware", Variant::from("virtual")),
+        ])
+    );
+}
+
+#[test]
+fn system_parameters_keep_unexpected_types_raw() {
+    let params = SystemParameters::from(map([
+        ("arch", Variant::from(64)),
+        ("access", Variant::from("partial")),
+    ]));
+
+    assert_eq!(params.arch, None);
+    assert_eq!(params.access, None);
+    assert_eq!(params.other.len(), 2);
+}
+
+#[test]
+fn process_parameters_extract_known_keys() {
+    let params = ProcessParameters::from(map([
+        ("path", Variant::from("/usr/bin/cat")),
+        ("user", Variant::from("root")),
+        ("ppid", Variant::from(1)),
+        ("started", Variant::from("2024-01-01T00:00:00.000Z")),
+        ("frontmost", Variant::from(true)),
+        (
+            "icons",
+            Variant::MapList(vec![map([
+                ("format", Variant::from("png")),
+                ("image", Variant::from(vec![0x89u8, b'P'])),
+            ])]),
+        ),
+        ("application", Variant::from("cat")),
+    ]));
+
+    assert_eq!(params.path.as_deref(), Some("/usr/bin/cat"));
+    assert_eq!(params.user.as_deref(), Some("root"));
+    assert_eq!(params.ppid, Some(1));
+    assert_eq!(params.started.as_deref(), Some("2024-01-01T00:00:00.000Z"));
+    assert_eq!(params.frontmost, Some(true));
+    assert_eq!(
+        params.icons,
+        vec![ProcessIcon {
+            format: "png".to_string(),
+            width: None,
+            height: None,
+            image: vec![0x89, b'P'],
+        }]
+    );
+    assert_eq!(params.other, map([("application", Variant::from("cat"))]));
+}
+
+#[test]
+fn process_parameters_accept_user_maps() {
+    let params = ProcessParameters::from(map([(
+        "user",
+        Variant::Map(map([
+            ("name", Variant::from("nobody")),
+            ("uid", Variant::from(65534)),
+        ])),
+    )]));
+
+    assert_eq!(params.user.as_deref(), Some("nobody"));
+    assert!(params.other.contains_key("user"));
+}
@@ -1,5 +1,5 @@
-//! Integration tests for `Device::enumerate_processes_with_options` and
-//! `Process::get_parameters`.
+//! Integration tests for `Device::enumerate_processes_with_options`,
+//! `Process::get_parameters` and `Process::parameters`.
 //!
 //! Tests run against the real frida-core (auto-downloaded via the
 //! `auto-download` feature) and inspect the running test process itself —
@@ -12,7 +12,7 @@
 use frida::{DeviceManager, Frida, Scope, Variant};
 use std::sync::{LazyLock, Mutex, MutexGuard};
 
-static FRIDA: LazyLock<Frida> = LazyLock::new(|| unsafe { Frida::obtain() });
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
 
 // Frida-core is a process-wide singleton; serialize the tests so two
 // threads don't race the device-manager / process-enumeration state.
@@ -30,7 +30,9 @@ fn get_parameters_returns_data_under_full_scope() {
         .get_local_device()
         .expect("local device should be available");
 
-    let processes = device.enumerate_processes_with_options(Scope::Full);
+    let processes = device
+        .enumerate_processes_with_options(Scope::Full)
+        .expect("enumerate_processes should succeed");
     assert!(
         !processes.is_empty(),
         "Scope::Full enumeration returned no processes — the test runner itself should be present"
@@ -68,6 +70,34 @@ fn get_parameters_returns_data_under_full_scope() {
     );
 }
 
+#[cfg(target_os = "linux")]
+#[test]
+fn typed_parameters_describe_the_test_process() {
+    let _serial = serial_guard();
+    let dm = DeviceManager::obtain(&FRIDA);
+    let device = dm
+        .get_local_device()
+        .expect("local device should be available");
+
+    let processes = device
+        .enumerate_processes_with_options(Scope::Full)
+        .expect("enumerate_processes should succeed");
+    let own_pid = std::process::id();
+    let me = processes
+        .iter()
+        .find(|p| p.get_pid() == own_pid)
+        .expect("test process pid should appear in the Scope::Full enumeration");
+
+    let params = me.parameters();
+    assert_eq!(params.ppid, Some(std::os::unix::process::parent_id()));
+    let exe = std::env::current_exe().expect("current_exe should be available");
+    assert_eq!(params.path.as_deref(), exe.to_str());
+    assert!(
+        !params.other.contains_key("ppid") && !params.other.contains_key("path"),
+        "typed keys must not be duplicated in `other`"
+    );
+}
+
 #[test]
 fn variant_iteration_never_panics_on_unknown_signatures() {
     // Core regression target of this PR: `Variant::from_ptr` previously
@@ -86,7 +116,9 @@ fn variant_iteration_never_panics_on_unknown_signatures() {
         .get_local_device()
         .expect("local device should be available");
 
-    let processes = device.enumerate_processes_with_options(Scope::Full);
+    let processes = device
+        .enumerate_processes_with_options(Scope::Full)
+        .expect("enumerate_processes should succeed");
     for p in &processes {
         for (_key, v) in p.get_parameters() {
             // Run every accessor and the Debug impl — any panic here
@@ -114,7 +146,9 @@ fn get_parameters_is_empty_under_minimal_scope() {
     // whole reason Scope::Full exists. The plain enumerate_processes()
     // alias also uses Minimal; regressions here would silently bloat
     // every caller's enumeration.
-    let processes = device.enumerate_processes_with_options(Scope::Minimal);
+    let processes = device
+        .enumerate_processes_with_options(Scope::Minimal)
+        .expect("enumerate_processes should succeed");
     let own_pid = std::process::id();
     let me = processes
         .iter()
@@ -0,0 +1,100 @@
+//! Integration tests for the `Frida` runtime and `DeviceManager::close`.
+//!
+//! The runtime is initialized once and never torn down, so these check that
+//! closing device managers releases their helpers across repeated cycles;
+//! `runtime_obtain.rs` covers obtaining handles again once every one was
+//! dropped.
+
+use frida::{DeviceManager, Frida};
+use std::sync::mpsc::channel;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::Duration;
+
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
+
+// Frida-core is a process-wide singleton; every #[test] in this file
+// must hold this lock while it uses a device manager.
+static FRIDA_SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial_guard() -> MutexGuard<'static, ()> {
+    FRIDA_SERIAL.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+fn run_cycle() {
+    let frida = Frida::obtain();
+    let device_manager = DeviceManager::obtain(&frida);
+    let device = device_manager
+        .get_local_device()
+        .expect("local device should be available");
+    assert!(
+        !device
+            .enumerate_processes()
+            .expect("enumerate_processes should succeed")
+            .is_empty()
+    );
+
+    device_manager.close().expect("close should succeed");
+    assert!(device.is_lost(), "devices should be lost once closed");
+    device_manager
+        .close()
+        .expect("closing twice should be a no-op");
+}
+
+#[test]
+fn close_is_shared_between_clones() {
+    let _serial = serial_guard();
+    let device_manager = DeviceManager::obtain(&FRIDA);
+    let clone = device_manager.clone();
+    let device = device_manager
+        .get_local_device()
+        .expect("local device should be available");
+
+    clone.close().expect("close should succeed");
+    assert!(device.is_lost());
+    device_manager
+        .close()
+        .expect("closing through another handle should be a no-op");
+}
+
+#[test]
+fn dropping_a_manager_on_the_main_context_does_not_block() {
+    let _serial = serial_guard();
+    let device_manager = DeviceManager::obtain(&FRIDA);
+    device_manager
+        .get_local_device()
+        .expect("local device should be available");
+
+    let (tx, rx) = channel();
+    FRIDA.schedule_on_main(move || {
+        drop(device_manager);
+        let _ = tx.send(());
+    });
+    rx.recv_timeout(Duration::from_secs(10))
+        .expect("dropping the last manager handle on the main context should not block");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn repeated_obtain_and_drop_does_not_leak_threads() {
+    fn thread_count() -> usize {
+        std::fs::read_dir("/proc/self/task")
+            .expect("/proc/self/task should be readable")
+            .count()
+    }
+
+    let _serial = serial_guard();
+
+    // Let the runtime spawn its long-lived threads before measuring.
+    run_cycle();
+    let baseline = thread_count();
+
+    for _ in 0..20 {
+        run_cycle();
+    }
+
+    let after = thread_count();
+    assert!(
+        after <= baseline + 1,
+        "thread count grew from {baseline} to {after} over 20 cycles"
+    );
+}
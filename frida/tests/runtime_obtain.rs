@@ -0,0 +1,30 @@
+//! Integration test for obtaining `Frida` handles again once every handle
+//! was dropped.
+//!
+//! The runtime is never torn down, so a later `Frida::obtain` reuses it. The
+//! test lives in its own test binary, so that no other handle is alive in
+//! between.
+
+use frida::{DeviceManager, Frida};
+
+#[test]
+fn runtime_can_be_obtained_again_after_the_last_handle_is_dropped() {
+    for _ in 0..2 {
+        let frida = Frida::obtain();
+        let device_manager = DeviceManager::obtain(&frida);
+        let device = device_manager
+            .get_local_device()
+            .expect("local device should be available");
+        assert!(
+            !device
+                .enumerate_processes()
+                .expect("enumerate_processes should succeed")
+                .is_empty()
+        );
+
+        // Dropped without `close`, so the manager is closed asynchronously.
+        drop(device);
+        drop(device_manager);
+        drop(frida);
+    }
+}
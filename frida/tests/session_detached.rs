@@ -0,0 +1,44 @@
+//! Integration tests for `Session::handle_detached`.
+//!
+//! The test process attaches to itself (pid=0), like the compile_script
+//! tests, and detaches on request.
+
+use frida::{DeviceManager, Frida, SessionDetachReason};
+use std::sync::mpsc::channel;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::Duration;
+
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
+
+// Frida-core is a process-wide singleton; every #[test] in this file
+// must hold this lock for the full attach -> detach span.
+static FRIDA_SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial_guard() -> MutexGuard<'static, ()> {
+    FRIDA_SERIAL.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+#[test]
+fn requested_detach_is_reported_without_crash() {
+    let _serial = serial_guard();
+    let device_manager = DeviceManager::obtain(&FRIDA);
+    let device = device_manager
+        .get_local_device()
+        .expect("local device should be available");
+    let session = device
+        .attach(0)
+        .expect("attach to self (pid=0) should succeed");
+
+    let (tx, rx) = channel();
+    session
+        .handle_detached(tx)
+        .expect("handle_detached should succeed");
+
+    session.detach().expect("detach should succeed");
+
+    let (reason, crash) = rx
+        .recv_timeout(Duration::from_secs(10))
+        .expect("detached signal should be emitted");
+    assert_eq!(reason, SessionDetachReason::ApplicationRequested);
+    assert!(crash.is_none(), "unexpected crash report: {crash:?}");
+}
@@ -0,0 +1,130 @@
+//! Tests for resolving script positions through source maps.
+//!
+//! These are pure and don't need a `Frida` handle.
+
+use frida::{Error, OriginalPosition, ScriptSourceMaps, SourceMap};
+
+// Generated line 1 maps column 1 to agent.ts 1:1 and column 11 to agent.ts 2:5 (`greet`);
+// generated line 2 maps column 3 to util.ts 7:3 and leaves column 21 onwards unmapped.
+const MAP: &str = r#"{
+    "version": 3,
+    "file": "agent.js",
+    "sourceRoot": "src",
+    "sources": ["agent.ts", "util.ts"],
+    "names": ["greet"],
+    "mappings": "AAAA,UACIA;ECKF,kB"
+}"#;
+
+fn position(source: &str, line: usize, column: usize, name: Option<&str>) -> OriginalPosition {
+    OriginalPosition {
+        source: source.to_string(),
+        line,
+        column,
+        name: name.map(str::to_owned),
+    }
+}
+
+#[test]
+fn lookup_resolves_generated_positions() {
+    let map = SourceMap::parse(MAP).unwrap();
+
+    assert_eq!(map.lookup(1, 1), Some(position("src/agent.ts", 1, 1, None)));
+    assert_eq!(
+        map.lookup(1, 11),
+        Some(position("src/agent.ts", 2, 5, Some("greet")))
+    );
+    assert_eq!(
+        map.lookup(1, 40),
+        Some(position("src/agent.ts", 2, 5, Some("greet")))
+    );
+    assert_eq!(map.lookup(2, 3), Some(position("src/util.ts", 7, 3, None)));
+}
+
+#[test]
+fn lookup_misses_unmapped_positions() {
+    let map = SourceMap::parse(MAP).unwrap();
+
+    assert_eq!(map.lookup(2, 1), None);
+    assert_eq!(map.lookup(2, 21), None);
+    assert_eq!(map.lookup(3, 1), None);
+    assert_eq!(map.lookup(0, 1), None);
+}
+
+#[test]
+fn map_stack_rewrites_frames_of_mapped_files() {
+    let mut maps = ScriptSourceMaps::new();
+    maps.insert("/agent.js", SourceMap::parse(MAP).unwrap());
+    let stack = "Error: boom\n    at greet (/agent.js:1:11)\n    at file:///agent.js:2:3\n    at /other.js:1:11\n    at frida/runtime/message-dispatcher.js:1:1";
+
+    assert_eq!(
+        maps.map_stack(stack),
+        "Error: boom\n    at greet (src/agent.ts:2:5)\n    at src/util.ts:7:3\n    at /other.js:1:11\n    at frida/runtime/message-dispatcher.js:1:1"
+    );
+    assert_eq!(maps.lookup("/other.js", 1, 11), None);
+}
+
+/// Builds a bundle in the format of frida's compiler.
+fn bundle(files: &[(&str, &str)]) -> String {
+    let mut header = vec!["📦".to_string()];
+    for (name, contents) in files {
+        header.push(format!("{} {name}", contents.len()));
+    }
+    header.push("↻ /alias".to_string());
+    let mut parts = vec![header.join("\n")];
+    parts.extend(files.iter().map(|(_, contents)| contents.to_string()));
+    parts.join("\n✄\n")
+}
+
+#[test]
+fn bundle_maps_are_keyed_by_module() {
+    let source = bundle(&[
+        (
+            "/src/index.js",
+            "send(1);\n//# sourceMappingURL=index.js.map\n",
+        ),
+        (
+            "/src/index.js.map",
+            r#"{"version":3,"sources":["index.ts"],"names":[],"mappings":"AAAA"}"#,
+        ),
+        // A file whose contents look like the separator.
+        ("/src/util.js", "send('\n✄\n');\n"),
+        (
+            "/src/util.js.map",
+            r#"{"version":3,"sources":["util.ts"],"names":[],"mappings":";AACA"}"#,
+        ),
+        ("/src/plain.js", "send(2);\n"),
+    ]);
+    let maps = ScriptSourceMaps::from_bundle(&source).expect("bundle");
+
+    assert_eq!(
+        maps.lookup("/src/index.js", 1, 1),
+        Some(position("index.ts", 1, 1, None))
+    );
+    assert_eq!(
+        maps.lookup("/src/util.js", 2, 1),
+        Some(position("util.ts", 2, 1, None))
+    );
+    assert!(maps.get("/src/plain.js").is_none());
+    assert!(ScriptSourceMaps::from_bundle("send(1);\n").is_none());
+}
+
+#[test]
+fn inline_source_map_is_extracted() {
+    let source = "send(1);\n//# sourceMappingURL=data:application/json;charset=utf-8;base64,eyJ2ZXJzaW9uIjozLCJzb3VyY2VzIjpbImFnZW50LnRzIl0sIm5hbWVzIjpbXSwibWFwcGluZ3MiOiJBQUFBIn0=\n";
+    let map = SourceMap::from_inline(source).expect("inline source map");
+
+    assert_eq!(map.lookup(1, 5), Some(position("agent.ts", 1, 1, None)));
+    assert!(SourceMap::from_inline("send(1);\n//# sourceMappingURL=agent.js.map\n").is_none());
+    assert!(SourceMap::from_inline("send(1);\n").is_none());
+}
+
+#[test]
+fn invalid_source_maps_are_rejected() {
+    let invalid = |json: &str| matches!(SourceMap::parse(json), Err(Error::InvalidSourceMap(_)));
+
+    assert!(invalid(r#"{"version":2,"sources":[],"mappings":""}"#));
+    assert!(invalid(r#"{"version":3,"sources":[],"mappings":"A$"}"#));
+    assert!(invalid(r#"{"version":3,"sources":[],"mappings":"AAA"}"#));
+    assert!(invalid(r#"{"version":3,"sources":[],"mappings":"g"}"#));
+    assert!(invalid(r#"{"version":3,"sections":[]}"#));
+}
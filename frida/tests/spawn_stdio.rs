@@ -0,0 +1,100 @@
+//! Integration tests for `Device::handle_output` and `Device::input`.
+//!
+//! These spawn a real child process through frida-core with
+//! `SpawnStdio::Pipe` and drive its standard streams from Rust. Linux only:
+//! the tests rely on `/bin/sh` and on frida's pipe-based stdio routing.
+
+#![cfg(target_os = "linux")]
+
+use frida::{DeviceManager, Frida, Output, SpawnOptions, SpawnStdio};
+use std::sync::mpsc::{Receiver, channel};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::Duration;
+
+static FRIDA: LazyLock<Frida> = LazyLock::new(Frida::obtain);
+
+// Frida-core is a process-wide singleton; serialize the tests so two
+// threads don't race the device-manager / spawn state.
+static FRIDA_SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial_guard() -> MutexGuard<'static, ()> {
+    FRIDA_SERIAL.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+/// Collects everything written by `pid` to each of `fds` until all of them are closed,
+/// returning the data written to each in the order of `fds`.
+///
+/// The streams are drained together, as their messages are interleaved on the channel.
+fn collect_streams<const N: usize>(rx: &Receiver<Output>, pid: u32, fds: [i32; N]) -> [Vec<u8>; N] {
+    let mut collected = [const { Vec::new() }; N];
+    let mut open = N;
+    while open > 0 {
+        let output = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("timed out waiting for process output");
+        let index = fds.iter().position(|&fd| fd == output.fd);
+        let Some(index) = index.filter(|_| output.pid == pid) else {
+            continue;
+        };
+        if output.data.is_empty() {
+            open -= 1;
+        } else {
+            collected[index].extend_from_slice(&output.data);
+        }
+    }
+    collected
+}
+
+#[test]
+fn piped_stdout_is_delivered_to_output_handler() {
+    let _serial = serial_guard();
+    let device_manager = DeviceManager::obtain(&FRIDA);
+    let device = device_manager
+        .get_local_device()
+        .expect("local device should be available");
+
+    let (tx, rx) = channel();
+    device
+        .handle_output(tx)
+        .expect("handle_output should succeed");
+
+    let options = SpawnOptions::new()
+        .argv(["sh", "-c", "echo out; echo err >&2"])
+        .stdio(SpawnStdio::Pipe);
+    let pid = device
+        .spawn("/bin/sh", &options)
+        .expect("spawn should succeed");
+    device.resume(pid).expect("resume should succeed");
+
+    let [stdout, stderr] = collect_streams(&rx, pid, [1, 2]);
+    assert_eq!(stdout, b"out\n");
+    assert_eq!(stderr, b"err\n");
+}
+
+#[test]
+fn input_is_written_to_piped_stdin() {
+    let _serial = serial_guard();
+    let device_manager = DeviceManager::obtain(&FRIDA);
+    let device = device_manager
+        .get_local_device()
+        .expect("local device should be available");
+
+    let (tx, rx) = channel();
+    device
+        .handle_output(tx)
+        .expect("handle_output should succeed");
+
+    let options = SpawnOptions::new()
+        .argv(["sh", "-c", "read line; echo \"got $line\""])
+        .stdio(SpawnStdio::Pipe);
+    let pid = device
+        .spawn("/bin/sh", &options)
+        .expect("spawn should succeed");
+    device.resume(pid).expect("resume should succeed");
+    device
+        .input(pid, b"ping\n")
+        .expect("input should reach the piped stdin");
+
+    let [stdout] = collect_streams(&rx, pid, [1]);
+    assert_eq!(stdout, b"got ping\n");
+}
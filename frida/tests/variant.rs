@@ -0,0 +1,106 @@
+//! Tests for the serde and `serde_json::Value` conversions of `Variant`.
+//!
+//! These are pure Rust conversions and don't need the frida-core runtime.
+
+use frida::Variant;
+use serde_json::json;
+use std::collections::HashMap;
+
+#[test]
+fn variant_serializes_to_natural_json() {
+    let mut map = HashMap::new();
+    map.insert("name".to_string(), Variant::from("cat"));
+    map.insert("pid".to_string(), Variant::from(1234));
+    map.insert("frontmost".to_string(), Variant::from(false));
+    map.insert("ratio".to_string(), Variant::from(0.5));
+    map.insert(
+        "argv".to_string(),
+        Variant::from(vec!["cat".to_string(), "-n".to_string()]),
+    );
+    map.insert(
+        "pair".to_string(),
+        Variant::Tuple(vec![Variant::from("a"), Variant::from(1)]),
+    );
+    map.insert(
+        "boxed".to_string(),
+        Variant::Variant(Box::new(Variant::from("inner"))),
+    );
+    map.insert("icon".to_string(), Variant::from(vec![0x89u8, b'P']));
+
+    let value = serde_json::Value::try_from(Variant::Map(map)).unwrap();
+    assert_eq!(
+        value,
+        json!({
+            "name": "cat",
+            "pid": 1234,
+            "frontmost": false,
+            "ratio": 0.5,
+            "argv": ["cat", "-n"],
+            "pair": ["a", 1],
+            "boxed": "inner",
+            "icon": [0x89, 0x50],
+        })
+    );
+}
+
+#[test]
+fn variant_deserializes_from_json() {
+    let value = json!({
+        "os": { "id": "linux", "version": "6.1" },
+        "arch": "x64",
+        "names": ["a", "b"],
+        "maps": [{ "k": 1 }, { "k": 2 }],
+        "mixed": ["a", 1, true],
+        "pi": 3.25,
+    });
+
+    let variant = Variant::try_from(value).expect("JSON object should convert");
+    let map = variant.get_map().expect("top-level value should be a map");
+
+    assert_eq!(
+        map["os"].get_map().unwrap()["id"].get_string(),
+        Some("linux")
+    );
+    assert_eq!(map["arch"].get_string(), Some("x64"));
+    assert_eq!(
+        map["names"].get_string_array(),
+        Some(&["a".to_string(), "b".to_string()][..])
+    );
+    assert_eq!(map["maps"].get_maplist().map(|l| l.len()), Some(2));
+    assert_eq!(
+        map["mixed"].get_tuple(),
+        Some(&[Variant::from("a"), Variant::from(1), Variant::from(true)][..])
+    );
+    assert_eq!(map["pi"].get_double(), Some(3.25));
+}
+
+#[test]
+fn variant_rejects_null() {
+    assert!(Variant::try_from(serde_json::Value::Null).is_err());
+}
+
+#[test]
+fn unsupported_variant_does_not_convert_to_json() {
+    let variant = Variant::Tuple(vec![Variant::Unsupported("(ii)".into())]);
+    assert!(serde_json::Value::try_from(variant).is_err());
+}
+
+#[test]
+fn doubles_compare_by_bit_pattern() {
+    assert_eq!(Variant::from(f64::NAN), Variant::from(f64::NAN));
+    assert_ne!(Variant::from(0.0), Variant::from(-0.0));
+}
+
+#[test]
+fn variant_serde_round_trip_preserves_scalars() {
+    for variant in [
+        Variant::from("text"),
+        Variant::from(true),
+        Variant::from(-42),
+        Variant::from(1.5),
+    ] {
+        let encoded = serde_json::to_string(&variant).unwrap();
+        let decoded: Variant = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded, variant);
+    }
+}